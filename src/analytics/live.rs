@@ -0,0 +1,215 @@
+//! 将实盘的订单推送与余额转换为回测使用的成交记录与权益曲线
+
+use super::{Analyzer, Report};
+use crate::{
+    backtest::{EquityPoint, SimPosition, TradeRecord},
+    data::Balance,
+    order::OrderUpdate,
+};
+use bytestring::ByteString;
+use std::collections::{HashMap, HashSet};
+
+/// 实盘的账本，由订单推送中的成交生成 [`TradeRecord`]，由余额生成 [`EquityPoint`]，
+/// 之后与回测结果一样交给 [`Analyzer`] 与 [`Report`]
+///
+/// 只记录计价币种（例如 "USDT"）的余额。已实现盈亏按净持仓计算，与回测一致；
+/// 手续费不是计价币种时（例如币币买入按交易货币收取）按成交价折算。
+#[derive(Debug, Clone)]
+pub struct LiveLedger {
+    currency: ByteString,
+    trades: Vec<TradeRecord>,
+    equity: Vec<EquityPoint>,
+    positions: HashMap<ByteString, SimPosition>,
+    /// 已记录的 (产品ID, 成交ID)，重连后重复推送的成交被忽略
+    seen: HashSet<(ByteString, ByteString)>,
+}
+
+impl LiveLedger {
+    pub fn new(currency: impl Into<ByteString>) -> Self {
+        Self {
+            currency: currency.into(),
+            trades: Vec::new(),
+            equity: Vec::new(),
+            positions: HashMap::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// 记录订单推送中的新成交，没有成交的推送被忽略
+    pub fn on_order_update(&mut self, update: &OrderUpdate) {
+        let Some(fill) = &update.last_fill else {
+            return;
+        };
+        if !self
+            .seen
+            .insert((update.symbol.clone(), fill.trade_id.clone()))
+        {
+            return;
+        }
+
+        let fee = if fill.fee_currency == self.currency {
+            fill.fee
+        } else {
+            fill.fee * fill.price.to_f64()
+        };
+        let position = self
+            .positions
+            .entry(update.symbol.clone())
+            .or_insert_with(|| SimPosition {
+                symbol: update.symbol.clone(),
+                ..Default::default()
+            });
+        let realized_pnl = position.apply_fill(update.side, fill.price.to_f64(), fill.quantity);
+        position.mark_price = fill.price.to_f64();
+
+        self.trades.push(TradeRecord {
+            timestamp: fill.timestamp,
+            symbol: update.symbol.clone(),
+            order_id: update.order_id.clone(),
+            client_order_id: update.client_order_id.clone(),
+            side: update.side,
+            price: fill.price,
+            quantity: fill.quantity,
+            fee,
+            is_maker: fill.is_maker,
+            realized_pnl,
+        });
+    }
+
+    /// 按时间记录计价币种的余额，同一时间只保留最后一个
+    pub fn on_balance(&mut self, balance: &Balance) {
+        if balance.currency != self.currency {
+            return;
+        }
+
+        let point = EquityPoint {
+            timestamp: balance.timestamp,
+            cash: balance.cash,
+            equity: balance.equity,
+        };
+        let index = self
+            .equity
+            .partition_point(|existing| existing.timestamp < point.timestamp);
+        match self.equity.get_mut(index) {
+            Some(existing) if existing.timestamp == point.timestamp => *existing = point,
+            _ => self.equity.insert(index, point),
+        }
+    }
+
+    /// 所有成交，按推送顺序
+    pub fn trades(&self) -> &[TradeRecord] {
+        &self.trades
+    }
+
+    /// 按时间排序的权益
+    pub fn equity(&self) -> &[EquityPoint] {
+        &self.equity
+    }
+
+    /// 按成交推算的持仓，不包含开始记录前已有的持仓
+    pub fn positions(&self) -> impl Iterator<Item = &SimPosition> {
+        self.positions.values()
+    }
+
+    /// 第一条余额的权益，没有余额时为 0
+    pub fn initial_equity(&self) -> f64 {
+        self.equity.first().map_or(0.0, |point| point.equity)
+    }
+
+    pub fn report<'a>(&'a self, name: &'a str, analyzer: &Analyzer) -> Report<'a> {
+        Report {
+            name,
+            performance: analyzer.analyze(self.initial_equity(), &self.trades, &self.equity),
+            equity: &self.equity,
+            trades: &self.trades,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Timestamp,
+        order::{Fill, OrderStatus, OrderType, Side},
+    };
+
+    fn update(
+        trade_id: &str,
+        side: Side,
+        price: &str,
+        fee: f64,
+        fee_currency: &str,
+    ) -> OrderUpdate {
+        let price = price.parse().unwrap();
+        OrderUpdate {
+            symbol: "BTC-USDT".into(),
+            order_id: trade_id.into(),
+            client_order_id: None,
+            side,
+            order_type: OrderType::Market,
+            status: OrderStatus::Filled,
+            price: None,
+            quantity: "1".parse().unwrap(),
+            filled_quantity: "1".parse().unwrap(),
+            average_price: Some(price),
+            fee,
+            fee_currency: fee_currency.into(),
+            last_fill: Some(Fill {
+                trade_id: trade_id.into(),
+                price,
+                quantity: "1".parse().unwrap(),
+                fee,
+                fee_currency: fee_currency.into(),
+                is_maker: false,
+                timestamp: 1_000,
+            }),
+            timestamp: 1_000,
+        }
+    }
+
+    fn balance(currency: &str, equity: f64, timestamp: Timestamp) -> Balance {
+        Balance {
+            currency: currency.into(),
+            equity,
+            cash: equity,
+            available: equity,
+            frozen: 0.0,
+            unrealized_pnl: 0.0,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn converts_fills_and_balances() {
+        let mut ledger = LiveLedger::new("USDT");
+        let buy = update("1", Side::Buy, "100", -0.001, "BTC");
+        ledger.on_order_update(&buy);
+        // 重连后重复推送
+        ledger.on_order_update(&buy);
+        ledger.on_order_update(&update("2", Side::Sell, "110", -0.11, "USDT"));
+
+        let trades = ledger.trades();
+        assert_eq!(trades.len(), 2);
+        assert!((trades[0].fee + 0.1).abs() < 1e-9);
+        assert_eq!(trades[1].realized_pnl, 10.0);
+
+        ledger.on_balance(&balance("USDT", 1_000.0, 1_000));
+        ledger.on_balance(&balance("BTC", 1.0, 1_500));
+        ledger.on_balance(&balance("USDT", 1_010.0, 3_000));
+        ledger.on_balance(&balance("USDT", 1_005.0, 2_000));
+        ledger.on_balance(&balance("USDT", 1_009.8, 3_000));
+        assert_eq!(
+            ledger
+                .equity()
+                .iter()
+                .map(|point| (point.timestamp, point.equity))
+                .collect::<Vec<_>>(),
+            vec![(1_000, 1_000.0), (2_000, 1_005.0), (3_000, 1_009.8)]
+        );
+
+        let report = ledger.report("live", &Analyzer::default());
+        assert_eq!(report.performance.initial_equity, 1_000.0);
+        assert_eq!(report.performance.fills, 2);
+    }
+}
@@ -0,0 +1,620 @@
+//! 由成交记录与权益曲线计算绩效指标，回测与实盘结果均可使用
+
+use crate::{
+    Timestamp,
+    backtest::{EquityPoint, TradeRecord},
+    decimal::Quantity,
+    order::Side,
+};
+use bon::Builder;
+use bytestring::ByteString;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_with::{DurationMilliSeconds, SerializeAs, serde_as};
+use std::{collections::HashMap, time::Duration};
+
+pub mod live;
+pub mod report;
+
+pub use live::LiveLedger;
+pub use report::Report;
+
+/// 一年的毫秒数，加密货币全年交易
+const YEAR_MS: f64 = 365.0 * 86_400_000.0;
+
+/// 绩效指标，比率均为小数，例如 0.1 表示 10%
+#[serde_as]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Performance {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    #[serde_as(as = "FiniteOrNull")]
+    pub initial_equity: f64,
+    #[serde_as(as = "FiniteOrNull")]
+    pub final_equity: f64,
+    #[serde_as(as = "FiniteOrNull")]
+    pub total_return: f64,
+    /// 按复利年化，区间长度为 0 时为 `None`
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub annualized_return: Option<f64>,
+    /// 年化波动率
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub volatility: Option<f64>,
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub sharpe_ratio: Option<f64>,
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub sortino_ratio: Option<f64>,
+    /// 年化收益率与最大回撤之比
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub calmar_ratio: Option<f64>,
+    /// 权益相对之前最高点的最大跌幅，为正数
+    #[serde_as(as = "FiniteOrNull")]
+    pub max_drawdown: f64,
+    /// 权益从最高点回落到重新创出新高的最长时间（毫秒），未恢复时计算到结束
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub max_drawdown_duration: Duration,
+    /// 成交笔数
+    pub fills: usize,
+    /// 已平仓的交易次数，持仓从 0 到再次归 0（或反手）为一次
+    pub round_trips: usize,
+    /// 扣除手续费后盈利的交易占比
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub win_rate: Option<f64>,
+    /// 盈利交易的总盈利与亏损交易的总亏损之比
+    #[serde_as(as = "Option<FiniteOrNull>")]
+    pub profit_factor: Option<f64>,
+    /// 已平仓交易的平均持仓时间（毫秒）
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub average_holding_time: Duration,
+    /// 总成交额与平均权益之比
+    #[serde_as(as = "FiniteOrNull")]
+    pub turnover: f64,
+    /// 持有任意仓位的时间占比
+    #[serde_as(as = "FiniteOrNull")]
+    pub exposure: f64,
+    /// 手续费合计，负数表示支出
+    #[serde_as(as = "FiniteOrNull")]
+    pub total_fees: f64,
+}
+
+/// 序列化浮点数，无穷大与 NaN 写为 `null`，JSON 不能表示这些值
+pub(crate) struct FiniteOrNull;
+
+impl SerializeAs<f64> for FiniteOrNull {
+    fn serialize_as<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() {
+            serializer.serialize_f64(*value)
+        } else {
+            serializer.serialize_none()
+        }
+    }
+}
+
+/// 用于比较多次运行的指标
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    TotalReturn,
+    AnnualizedReturn,
+    #[default]
+    SharpeRatio,
+    SortinoRatio,
+    CalmarRatio,
+    /// 回撤越小越好
+    MaxDrawdown,
+    ProfitFactor,
+    WinRate,
+}
+
+impl Metric {
+    /// 越大越好的得分，指标无法计算时为负无穷
+    pub fn score(self, performance: &Performance) -> f64 {
+        let value = match self {
+            Metric::TotalReturn => Some(performance.total_return),
+            Metric::AnnualizedReturn => performance.annualized_return,
+            Metric::SharpeRatio => performance.sharpe_ratio,
+            Metric::SortinoRatio => performance.sortino_ratio,
+            Metric::CalmarRatio => performance.calmar_ratio,
+            Metric::MaxDrawdown => Some(-performance.max_drawdown),
+            Metric::ProfitFactor => performance.profit_factor,
+            Metric::WinRate => performance.win_rate,
+        };
+
+        value
+            .filter(|value| value.is_finite())
+            .unwrap_or(f64::NEG_INFINITY)
+    }
+}
+
+/// 一次完整的开平仓
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RoundTrip {
+    pub symbol: ByteString,
+    /// 开仓方向，买入为做多
+    pub side: Side,
+    pub open: Timestamp,
+    pub close: Timestamp,
+    /// 已实现盈亏，已扣除开平仓的手续费
+    pub pnl: f64,
+}
+
+/// 绩效计算的参数
+#[derive(Debug, Clone, Builder)]
+pub struct Analyzer {
+    /// 收益率序列的采样周期，用于计算波动率、夏普与索提诺比率
+    #[builder(default = Duration::from_secs(86_400))]
+    period: Duration,
+    /// 年化无风险利率
+    #[builder(default)]
+    risk_free_rate: f64,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl Analyzer {
+    /// `equity` 与 `trades` 需按时间排序，`initial_equity` 为第一条权益记录之前的权益
+    pub fn analyze(
+        &self,
+        initial_equity: f64,
+        trades: &[TradeRecord],
+        equity: &[EquityPoint],
+    ) -> Performance {
+        let start = equity
+            .first()
+            .map(|point| point.timestamp)
+            .into_iter()
+            .chain(trades.first().map(|trade| trade.timestamp))
+            .min()
+            .unwrap_or_default();
+        let end = equity
+            .last()
+            .map(|point| point.timestamp)
+            .into_iter()
+            .chain(trades.last().map(|trade| trade.timestamp))
+            .max()
+            .unwrap_or(start);
+        let final_equity = equity.last().map_or(initial_equity, |point| point.equity);
+        let elapsed = (end - start) as f64;
+
+        let total_return = final_equity / initial_equity - 1.0;
+        let annualized_return = (elapsed > 0.0).then(|| {
+            if final_equity <= 0.0 {
+                -1.0
+            } else {
+                (final_equity / initial_equity).powf(YEAR_MS / elapsed) - 1.0
+            }
+        });
+
+        let returns = self.returns(initial_equity, start, equity);
+        let periods_per_year = YEAR_MS / (self.period.as_millis().max(1) as f64);
+        let risk_free = (1.0 + self.risk_free_rate).powf(1.0 / periods_per_year) - 1.0;
+        let excess: Vec<_> = returns.iter().map(|r| r - risk_free).collect();
+        let annualize = |ratio: f64| ratio * periods_per_year.sqrt();
+
+        let volatility = std_dev(&returns).map(annualize);
+        let sharpe_ratio = mean(&excess)
+            .zip(std_dev(&excess))
+            .filter(|&(_, std)| std > 0.0)
+            .map(|(mean, std)| annualize(mean / std));
+        let downside = mean(
+            &excess
+                .iter()
+                .map(|r| r.min(0.0).powi(2))
+                .collect::<Vec<_>>(),
+        )
+        .map(f64::sqrt);
+        let sortino_ratio = mean(&excess)
+            .zip(downside)
+            .filter(|&(_, downside)| downside > 0.0)
+            .map(|(mean, downside)| annualize(mean / downside));
+
+        let (max_drawdown, max_drawdown_duration) = max_drawdown(initial_equity, start, equity);
+        let calmar_ratio = annualized_return
+            .filter(|_| max_drawdown > 0.0)
+            .map(|annualized| annualized / max_drawdown);
+
+        let trips = round_trips(trades);
+        let wins: Vec<_> = trips.iter().filter(|trip| trip.pnl > 0.0).collect();
+        let gross_profit = wins.iter().map(|trip| trip.pnl).sum::<f64>();
+        let gross_loss = -trips
+            .iter()
+            .filter(|trip| trip.pnl < 0.0)
+            .map(|trip| trip.pnl)
+            .sum::<f64>();
+        let holding = trips
+            .iter()
+            .map(|trip| trip.close - trip.open)
+            .sum::<Timestamp>();
+
+        let traded = trades
+            .iter()
+            .map(|trade| (trade.price * trade.quantity).to_f64())
+            .sum::<f64>();
+        let average_equity = mean(&equity.iter().map(|point| point.equity).collect::<Vec<_>>())
+            .unwrap_or(initial_equity);
+
+        Performance {
+            start,
+            end,
+            initial_equity,
+            final_equity,
+            total_return,
+            annualized_return,
+            volatility,
+            sharpe_ratio,
+            sortino_ratio,
+            calmar_ratio,
+            max_drawdown,
+            max_drawdown_duration,
+            fills: trades.len(),
+            round_trips: trips.len(),
+            win_rate: (!trips.is_empty()).then(|| wins.len() as f64 / trips.len() as f64),
+            profit_factor: (gross_loss > 0.0).then(|| gross_profit / gross_loss),
+            average_holding_time: Duration::from_millis(
+                holding
+                    .checked_div(trips.len() as Timestamp)
+                    .unwrap_or_default() as u64,
+            ),
+            turnover: if average_equity > 0.0 {
+                traded / average_equity
+            } else {
+                0.0
+            },
+            exposure: if elapsed > 0.0 {
+                exposure(trades, end) as f64 / elapsed
+            } else {
+                0.0
+            },
+            total_fees: trades.iter().map(|trade| trade.fee).sum(),
+        }
+    }
+
+    /// 按采样周期取每个周期最后的权益，计算相邻周期的收益率
+    fn returns(&self, initial_equity: f64, start: Timestamp, equity: &[EquityPoint]) -> Vec<f64> {
+        let period = self.period.as_millis().max(1);
+
+        let mut samples = vec![initial_equity];
+        let mut bucket = 0;
+        for point in equity {
+            let index = (point.timestamp - start) / period + 1;
+            // 没有权益记录的周期沿用上一个周期的权益
+            while bucket < index {
+                samples.push(*samples.last().unwrap_or(&initial_equity));
+                bucket += 1;
+            }
+            if let Some(last) = samples.last_mut() {
+                *last = point.equity;
+            }
+        }
+
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] != 0.0)
+            .map(|pair| pair[1] / pair[0] - 1.0)
+            .collect()
+    }
+}
+
+/// 每个权益记录相对之前最高点的回撤，为非正数，例如 -0.1 表示回撤 10%
+pub fn drawdown(initial_equity: f64, equity: &[EquityPoint]) -> Vec<f64> {
+    let mut peak = initial_equity;
+    equity
+        .iter()
+        .map(|point| {
+            peak = peak.max(point.equity);
+            if peak > 0.0 {
+                point.equity / peak - 1.0
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+fn max_drawdown(initial_equity: f64, start: Timestamp, equity: &[EquityPoint]) -> (f64, Duration) {
+    let mut peak = (start, initial_equity);
+    let mut max = 0.0_f64;
+    let mut longest = 0;
+    // 上一个最高点之后是否跌破过该点，没有跌破时新高之间的间隔不算回撤时间
+    let mut dipped = false;
+    for point in equity {
+        if point.equity >= peak.1 {
+            if dipped {
+                longest = longest.max(point.timestamp - peak.0);
+            }
+            peak = (point.timestamp, point.equity);
+            dipped = false;
+        } else {
+            dipped = true;
+            if peak.1 > 0.0 {
+                max = max.max(1.0 - point.equity / peak.1);
+            }
+        }
+    }
+    // 结束时仍未恢复
+    if dipped && let Some(last) = equity.last() {
+        longest = longest.max(last.timestamp - peak.0);
+    }
+
+    (max, Duration::from_millis(longest as u64))
+}
+
+/// 按产品跟踪净持仓，将成交记录拆分为已平仓的交易，反手时手续费按数量拆分
+pub fn round_trips(trades: &[TradeRecord]) -> Vec<RoundTrip> {
+    struct Open {
+        quantity: Quantity,
+        side: Side,
+        open: Timestamp,
+        pnl: f64,
+    }
+
+    let mut positions: HashMap<ByteString, Open> = HashMap::new();
+    let mut trips = Vec::new();
+    for trade in trades {
+        let signed = match trade.side {
+            Side::Buy => trade.quantity,
+            Side::Sell => -trade.quantity,
+        };
+        let position = positions.entry(trade.symbol.clone()).or_insert(Open {
+            quantity: Quantity::ZERO,
+            side: trade.side,
+            open: trade.timestamp,
+            pnl: 0.0,
+        });
+
+        if position.quantity.is_zero() {
+            *position = Open {
+                quantity: signed,
+                side: trade.side,
+                open: trade.timestamp,
+                pnl: trade.fee,
+            };
+            continue;
+        }
+        if position.quantity.is_positive() == signed.is_positive() {
+            position.quantity += signed;
+            position.pnl += trade.fee;
+            continue;
+        }
+
+        let closed = position.quantity.abs().min(trade.quantity);
+        let share = closed.to_f64() / trade.quantity.to_f64();
+        position.quantity += signed;
+        position.pnl += trade.realized_pnl + trade.fee * share;
+        if position.quantity.is_zero() || position.quantity.is_positive() == signed.is_positive() {
+            trips.push(RoundTrip {
+                symbol: trade.symbol.clone(),
+                side: position.side,
+                open: position.open,
+                close: trade.timestamp,
+                pnl: position.pnl,
+            });
+            // 反手，剩余部分开新仓
+            position.side = trade.side;
+            position.open = trade.timestamp;
+            position.pnl = trade.fee * (1.0 - share);
+        }
+    }
+
+    trips
+}
+
+/// 持有任意仓位的总时长
+fn exposure(trades: &[TradeRecord], end: Timestamp) -> Timestamp {
+    let mut positions: HashMap<&ByteString, Quantity> = HashMap::new();
+    let mut since = None;
+    let mut total = 0;
+    for trade in trades {
+        let position = positions.entry(&trade.symbol).or_default();
+        *position += match trade.side {
+            Side::Buy => trade.quantity,
+            Side::Sell => -trade.quantity,
+        };
+
+        let exposed = positions.values().any(|quantity| !quantity.is_zero());
+        match (since, exposed) {
+            (None, true) => since = Some(trade.timestamp),
+            (Some(from), false) => {
+                total += trade.timestamp - from;
+                since = None;
+            }
+            _ => {}
+        }
+    }
+
+    total + since.map_or(0, |from| end.saturating_sub(from))
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// 样本标准差
+fn std_dev(values: &[f64]) -> Option<f64> {
+    let mean = mean(values)?;
+    (values.len() > 1).then(|| {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Timestamp = 86_400_000;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn points(equity: &[(Timestamp, f64)]) -> Vec<EquityPoint> {
+        equity
+            .iter()
+            .map(|&(timestamp, equity)| EquityPoint {
+                timestamp,
+                cash: equity,
+                equity,
+            })
+            .collect()
+    }
+
+    fn trade(
+        symbol: &str,
+        timestamp: Timestamp,
+        side: Side,
+        quantity: &str,
+        price: &str,
+        fee: f64,
+        realized_pnl: f64,
+    ) -> TradeRecord {
+        TradeRecord {
+            timestamp,
+            symbol: symbol.into(),
+            order_id: timestamp.to_string().into(),
+            client_order_id: None,
+            side,
+            price: price.parse().unwrap(),
+            quantity: quantity.parse().unwrap(),
+            fee,
+            is_maker: false,
+            realized_pnl,
+        }
+    }
+
+    #[test]
+    fn analyzes_returns_risk_and_trading() {
+        let trades = [
+            trade("BTC-USDT", 0, Side::Buy, "1", "100", -0.1, 0.0),
+            trade("BTC-USDT", 2 * DAY, Side::Sell, "1", "110", -0.11, 10.0),
+        ];
+        let equity = points(&[(0, 100.0), (DAY, 110.0), (2 * DAY, 99.0), (3 * DAY, 108.9)]);
+        let performance = Analyzer::default().analyze(100.0, &trades, &equity);
+
+        // 日收益率为 0、0.1、-0.1、0.1，均值 0.025，离差平方和 0.0275
+        let std = (0.0275_f64 / 3.0).sqrt();
+        let annualized = 1.089_f64.powf(365.0 / 3.0) - 1.0;
+        assert_eq!((performance.start, performance.end), (0, 3 * DAY));
+        assert_close(performance.total_return, 0.089);
+        assert_close(performance.annualized_return.unwrap(), annualized);
+        assert_close(performance.volatility.unwrap(), std * 365_f64.sqrt());
+        assert_close(
+            performance.sharpe_ratio.unwrap(),
+            0.025 / std * 365_f64.sqrt(),
+        );
+        // 下行偏差为 sqrt(0.01 / 4) = 0.05
+        assert_close(performance.sortino_ratio.unwrap(), 0.5 * 365_f64.sqrt());
+        assert_close(performance.max_drawdown, 0.1);
+        assert_close(performance.calmar_ratio.unwrap(), annualized / 0.1);
+        // 从第 1 天的 110 回落，结束时仍未恢复
+        assert_eq!(
+            performance.max_drawdown_duration,
+            Duration::from_millis(2 * DAY as u64)
+        );
+
+        assert_eq!((performance.fills, performance.round_trips), (2, 1));
+        assert_eq!(performance.win_rate, Some(1.0));
+        assert_eq!(performance.profit_factor, None);
+        assert_eq!(
+            performance.average_holding_time,
+            Duration::from_millis(2 * DAY as u64)
+        );
+        // 成交额 210，平均权益 104.475
+        assert_close(performance.turnover, 210.0 / 104.475);
+        assert_close(performance.exposure, 2.0 / 3.0);
+        assert_close(performance.total_fees, -0.21);
+    }
+
+    #[test]
+    fn buckets_returns_by_period() {
+        let equity = points(&[
+            (0, 100.0),
+            (DAY / 2, 105.0),
+            (DAY, 110.0),
+            // 第 2 天没有记录，沿用第 1 天的权益
+            (3 * DAY + 1, 121.0),
+        ]);
+        let returns = Analyzer::default().returns(100.0, 0, &equity);
+
+        let expected = [0.05, 110.0 / 105.0 - 1.0, 0.0, 0.1];
+        assert_eq!(returns.len(), expected.len());
+        for (actual, expected) in returns.into_iter().zip(expected) {
+            assert_close(actual, expected);
+        }
+
+        let analyzer = Analyzer::builder()
+            .period(Duration::from_millis(2 * DAY as u64))
+            .build();
+        let returns = analyzer.returns(100.0, 0, &equity);
+        assert_eq!(returns.len(), 2);
+        assert_close(returns[0], 0.1);
+        assert_close(returns[1], 0.1);
+    }
+
+    #[test]
+    fn splits_partial_closes_and_reversals() {
+        let trades = [
+            trade("BTC-USDT", 1, Side::Buy, "2", "100", -0.2, 0.0),
+            // 平一半，仍持有多头
+            trade("BTC-USDT", 2, Side::Sell, "1", "110", -0.11, 10.0),
+            trade("ETH-USDT", 3, Side::Buy, "1", "10", -0.01, 0.0),
+            // 平掉剩余的 1 个并反手开空 2 个，手续费按 1:2 拆分
+            trade("BTC-USDT", 4, Side::Sell, "3", "120", -0.36, 20.0),
+            trade("BTC-USDT", 5, Side::Buy, "2", "110", -0.22, 20.0),
+        ];
+        let trips = round_trips(&trades);
+
+        assert_eq!(trips.len(), 2, "the open ETH position is not a round trip");
+        assert_eq!(
+            (trips[0].side, trips[0].open, trips[0].close),
+            (Side::Buy, 1, 4)
+        );
+        assert_close(trips[0].pnl, -0.2 + 10.0 - 0.11 + 20.0 - 0.12);
+        assert_eq!(
+            (trips[1].side, trips[1].open, trips[1].close),
+            (Side::Sell, 4, 5)
+        );
+        assert_close(trips[1].pnl, -0.24 + 20.0 - 0.22);
+    }
+
+    #[test]
+    fn exposure_counts_time_with_any_position() {
+        let trades = [
+            trade("BTC-USDT", 10, Side::Buy, "1", "100", 0.0, 0.0),
+            trade("ETH-USDT", 20, Side::Buy, "1", "10", 0.0, 0.0),
+            trade("BTC-USDT", 30, Side::Sell, "1", "100", 0.0, 0.0),
+            trade("ETH-USDT", 40, Side::Sell, "1", "10", 0.0, 0.0),
+            // 结束时仍持仓，计算到结束
+            trade("BTC-USDT", 50, Side::Sell, "1", "100", 0.0, 0.0),
+        ];
+
+        assert_eq!(exposure(&trades, 100), 30 + 50);
+        assert_eq!(exposure(&[], 100), 0);
+    }
+
+    #[test]
+    fn drawdown_from_the_running_peak() {
+        let equity = points(&[(1, 90.0), (2, 110.0), (3, 99.0), (4, 121.0)]);
+        let expected = [-0.1, 0.0, -0.1, 0.0];
+        for (actual, expected) in drawdown(100.0, &equity).into_iter().zip(expected) {
+            assert_close(actual, expected);
+        }
+    }
+
+    #[test]
+    fn drawdown_duration_only_counts_dips() {
+        // 5 天后才创新高，期间没有跌破之前的最高点
+        let equity = points(&[(5 * DAY, 120.0), (6 * DAY, 114.0), (7 * DAY, 125.0)]);
+        let (max, duration) = max_drawdown(100.0, 0, &equity);
+        assert_close(max, 0.05);
+        assert_eq!(duration, Duration::from_millis(2 * DAY as u64));
+
+        let rising = points(&[(DAY, 100.0), (3 * DAY, 110.0), (10 * DAY, 120.0)]);
+        assert_eq!(max_drawdown(100.0, 0, &rising), (0.0, Duration::ZERO));
+    }
+}
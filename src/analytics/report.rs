@@ -0,0 +1,270 @@
+//! JSON 与独立 HTML 格式的绩效报告
+
+use super::{Performance, drawdown};
+use crate::{
+    Timestamp,
+    backtest::{EquityPoint, TradeRecord},
+};
+use chrono::DateTime;
+use eyre::{Result, WrapErr};
+use serde::Serialize;
+use std::{fmt::Write, fs, path::Path};
+
+/// 图表最多绘制的点数，权益记录更多时均匀抽样
+const MAX_CHART_POINTS: usize = 2_000;
+const CHART_WIDTH: f64 = 960.0;
+const CHART_HEIGHT: f64 = 240.0;
+
+/// 一次回测或实盘运行的报告
+#[derive(Debug, Clone, Serialize)]
+pub struct Report<'a> {
+    pub name: &'a str,
+    pub performance: Performance,
+    pub equity: &'a [EquityPoint],
+    pub trades: &'a [TradeRecord],
+}
+
+impl Report<'_> {
+    /// 无穷大与 NaN（例如没有亏损交易时的盈亏比）写为 `null`
+    pub fn to_json(&self) -> Result<String> {
+        Ok(simd_json::to_string_pretty(self)?)
+    }
+
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json()?)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// 不依赖外部资源的 HTML 页面，包含指标表格、权益曲线与回撤曲线
+    pub fn to_html(&self) -> String {
+        let name = escape(self.name);
+        let equity: Vec<_> = self
+            .equity
+            .iter()
+            .map(|point| (point.timestamp, point.equity))
+            .collect();
+        let drawdown: Vec<_> = self
+            .equity
+            .iter()
+            .zip(drawdown(self.performance.initial_equity, self.equity))
+            .map(|(point, drawdown)| (point.timestamp, drawdown * 100.0))
+            .collect();
+
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{name}</title>
+<style>
+body {{ font-family: system-ui, sans-serif; margin: 2em auto; max-width: 1000px; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 2em; }}
+td {{ padding: 4px 16px 4px 0; border-bottom: 1px solid #eee; }}
+td:last-child {{ text-align: right; font-variant-numeric: tabular-nums; }}
+svg {{ display: block; margin-bottom: 2em; }}
+</style>
+</head>
+<body>
+<h1>{name}</h1>
+<table>
+"#
+        );
+        for (label, value) in self.metrics() {
+            let _ = writeln!(html, "<tr><td>{label}</td><td>{value}</td></tr>");
+        }
+        html.push_str("</table>\n<h2>Equity</h2>\n");
+        html.push_str(&chart(&equity, "#1f77b4", false));
+        html.push_str("<h2>Drawdown (%)</h2>\n");
+        html.push_str(&chart(&drawdown, "#d62728", true));
+        html.push_str("</body>\n</html>\n");
+
+        html
+    }
+
+    pub fn write_html(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_html())
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn metrics(&self) -> Vec<(&'static str, String)> {
+        let p = &self.performance;
+        let percent = |value: f64| format!("{:.2}%", value * 100.0);
+        let ratio =
+            |value: Option<f64>| value.map_or("-".to_owned(), |value| format!("{value:.2}"));
+
+        vec![
+            ("Start", format_time(p.start)),
+            ("End", format_time(p.end)),
+            ("Initial equity", format!("{:.2}", p.initial_equity)),
+            ("Final equity", format!("{:.2}", p.final_equity)),
+            ("Total return", percent(p.total_return)),
+            (
+                "Annualised return",
+                p.annualized_return.map_or("-".to_owned(), percent),
+            ),
+            ("Volatility", p.volatility.map_or("-".to_owned(), percent)),
+            ("Sharpe ratio", ratio(p.sharpe_ratio)),
+            ("Sortino ratio", ratio(p.sortino_ratio)),
+            ("Calmar ratio", ratio(p.calmar_ratio)),
+            ("Max drawdown", percent(p.max_drawdown)),
+            (
+                "Max drawdown duration",
+                format_duration(p.max_drawdown_duration.as_millis()),
+            ),
+            ("Fills", p.fills.to_string()),
+            ("Round trips", p.round_trips.to_string()),
+            ("Win rate", p.win_rate.map_or("-".to_owned(), percent)),
+            ("Profit factor", ratio(p.profit_factor)),
+            (
+                "Average holding time",
+                format_duration(p.average_holding_time.as_millis()),
+            ),
+            ("Turnover", format!("{:.2}", p.turnover)),
+            ("Exposure", percent(p.exposure)),
+            ("Fees", format!("{:.2}", p.total_fees)),
+        ]
+    }
+}
+
+/// 折线图，`fill` 为真时填充折线与 0 之间的区域
+fn chart(points: &[(Timestamp, f64)], color: &str, fill: bool) -> String {
+    let (width, height) = (CHART_WIDTH, CHART_HEIGHT);
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">
+<rect width="100%" height="100%" fill="#fafafa"/>
+"##
+    );
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        svg.push_str("</svg>\n");
+        return svg;
+    };
+
+    let step = points.len().div_ceil(MAX_CHART_POINTS);
+    let sampled: Vec<_> = points
+        .iter()
+        .step_by(step)
+        .chain((!(points.len() - 1).is_multiple_of(step)).then_some(last))
+        .collect();
+
+    let (mut low, mut high) = sampled
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(low, high), &&(_, value)| {
+            (low.min(value), high.max(value))
+        });
+    if fill {
+        high = high.max(0.0);
+        low = low.min(0.0);
+    }
+    if (high - low).abs() < f64::EPSILON {
+        high += 1.0;
+        low -= 1.0;
+    }
+    let span = (last.0 - first.0).max(1) as f64;
+    let (left, top, plot_width, plot_height) = (70.0, 10.0, width - 80.0, height - 40.0);
+    let x = |timestamp: Timestamp| left + (timestamp - first.0) as f64 / span * plot_width;
+    let y = |value: f64| top + (high - value) / (high - low) * plot_height;
+
+    let mut path = String::new();
+    for (i, &&(timestamp, value)) in sampled.iter().enumerate() {
+        let command = if i == 0 { 'M' } else { 'L' };
+        let _ = write!(path, "{command}{:.1},{:.1} ", x(timestamp), y(value));
+    }
+    if fill {
+        let _ = writeln!(
+            svg,
+            r#"<path d="{path}L{:.1},{:.1} L{:.1},{:.1} Z" fill="{color}" fill-opacity="0.25" stroke="none"/>"#,
+            x(last.0),
+            y(0.0),
+            x(first.0),
+            y(0.0)
+        );
+    }
+    let _ = write!(
+        svg,
+        r##"<path d="{path}" fill="none" stroke="{color}" stroke-width="1.5"/>
+<g font-size="11" fill="#555">
+<text x="{label_x}" y="{high_y:.1}" text-anchor="end">{high:.2}</text>
+<text x="{label_x}" y="{low_y:.1}" text-anchor="end">{low:.2}</text>
+<text x="{left}" y="{date_y}">{start}</text>
+<text x="{right}" y="{date_y}" text-anchor="end">{end}</text>
+</g>
+</svg>
+"##,
+        label_x = left - 6.0,
+        high_y = top + 4.0,
+        low_y = top + plot_height,
+        date_y = height - 8.0,
+        right = left + plot_width,
+        start = format_time(first.0),
+        end = format_time(last.0),
+    );
+
+    svg
+}
+
+fn format_time(timestamp: Timestamp) -> String {
+    DateTime::from_timestamp_millis(timestamp as i64).map_or_else(
+        || timestamp.to_string(),
+        |time| time.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )
+}
+
+fn format_duration(millis: u128) -> String {
+    let seconds = millis / 1000;
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3_600 % 24, seconds / 60 % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}s"),
+        (0, 0, _) => format!("{minutes}m {}s", seconds % 60),
+        (0, _, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simd_json::{OwnedValue, StaticNode};
+
+    #[test]
+    fn json_has_no_non_finite_numbers() {
+        let equity = [EquityPoint {
+            timestamp: 1_000,
+            cash: 1_000.0,
+            equity: f64::NAN,
+        }];
+        let report = Report {
+            name: "live",
+            performance: Performance {
+                initial_equity: 1_000.0,
+                total_return: f64::NAN,
+                profit_factor: Some(f64::INFINITY),
+                sharpe_ratio: Some(f64::NEG_INFINITY),
+                ..Default::default()
+            },
+            equity: &equity,
+            trades: &[],
+        };
+
+        let json = report.to_json().unwrap();
+        let value: OwnedValue = simd_json::serde::from_slice(&mut json.into_bytes()).unwrap();
+        let null = OwnedValue::Static(StaticNode::Null);
+        let performance = &value["performance"];
+        assert_eq!(performance["total_return"], null);
+        assert_eq!(performance["profit_factor"], null);
+        assert_eq!(performance["sharpe_ratio"], null);
+        assert_eq!(performance["initial_equity"], 1_000.0);
+        assert_eq!(value["equity"][0]["equity"], null);
+        assert_eq!(value["equity"][0]["cash"], 1_000.0);
+    }
+}
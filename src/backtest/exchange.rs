@@ -0,0 +1,1066 @@
+use super::fill::{FillModel, QueueModel};
+use crate::{
+    Timestamp,
+    analytics::FiniteOrNull,
+    book::{BookLevel, OrderBook},
+    client::OrderExecutor,
+    data::DataEnum,
+    decimal::{Price, Quantity},
+    order::{
+        AmendOrder, CancelOrder, Fill, NewOrder, OrderAck, OrderRef, OrderStatus, OrderType,
+        OrderUpdate, Side, TradeMode,
+    },
+};
+use bytestring::ByteString;
+use eyre::{OptionExt, Result, bail, ensure};
+use serde::Serialize;
+use serde_with::serde_as;
+use std::collections::{HashMap, VecDeque};
+
+/// 某一产品的持仓，数量为负表示空头
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SimPosition {
+    pub symbol: ByteString,
+    pub quantity: Quantity,
+    /// 持仓均价，无持仓时为 0
+    pub average_price: f64,
+    /// 累计已实现盈亏，未扣除手续费
+    pub realized_pnl: f64,
+    /// 用于估值的最新价格
+    pub mark_price: f64,
+}
+
+impl SimPosition {
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.quantity.to_f64() * (self.mark_price - self.average_price)
+    }
+
+    /// 按净持仓记账，返回本次成交的已实现盈亏
+    pub(crate) fn apply_fill(&mut self, side: Side, price: f64, quantity: Quantity) -> f64 {
+        let signed = match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+        let held = self.quantity.to_f64();
+        let traded = signed.to_f64();
+
+        let mut realized = 0.0;
+        if held == 0.0 || held.signum() == traded.signum() {
+            self.average_price =
+                (self.average_price * held.abs() + price * traded.abs()) / (held + traded).abs();
+        } else {
+            let closed = held.abs().min(traded.abs());
+            realized = closed * (price - self.average_price) * held.signum();
+            if traded.abs() > held.abs() {
+                // 反手，剩余部分按成交价开仓
+                self.average_price = price;
+            }
+        }
+
+        self.quantity += signed;
+        if self.quantity.is_zero() {
+            self.average_price = 0.0;
+        }
+        self.realized_pnl += realized;
+
+        realized
+    }
+}
+
+/// 一笔回测成交
+#[serde_as]
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeRecord {
+    pub timestamp: Timestamp,
+    pub symbol: ByteString,
+    pub order_id: ByteString,
+    pub client_order_id: Option<ByteString>,
+    pub side: Side,
+    /// 成交价，吃单已计入滑点
+    pub price: Price,
+    pub quantity: Quantity,
+    /// 手续费，负数表示支出
+    #[serde_as(as = "FiniteOrNull")]
+    pub fee: f64,
+    pub is_maker: bool,
+    /// 本次成交平仓部分的已实现盈亏，未扣除手续费
+    #[serde_as(as = "FiniteOrNull")]
+    pub realized_pnl: f64,
+}
+
+/// 尚未完结的订单
+#[derive(Debug)]
+struct SimOrder {
+    order: NewOrder,
+    order_id: ByteString,
+    filled: Quantity,
+    /// 累计成交金额，用于计算成交均价
+    filled_value: f64,
+    fee: f64,
+    /// 是否已经挂在订单簿上，未挂单的订单在下一条行情按吃单撮合
+    resting: bool,
+    /// 到达交易所的时间，之前开始的行情（例如到达时尚未收盘的K线）不参与撮合
+    arrived: Timestamp,
+    /// 同价位排在订单前面的数量，`None` 表示还没有深度可供估计
+    queue_ahead: Option<Quantity>,
+}
+
+impl SimOrder {
+    fn remaining(&self) -> Quantity {
+        self.order.quantity - self.filled
+    }
+
+    fn limit(&self) -> Option<Price> {
+        self.order
+            .price
+            .filter(|_| self.order.order_type != OrderType::Market)
+    }
+
+    /// 以 `price` 成交是否不差于委托价
+    fn crosses(&self, price: Price) -> bool {
+        match (self.order.side, self.limit()) {
+            (_, None) => true,
+            (Side::Buy, Some(limit)) => price <= limit,
+            (Side::Sell, Some(limit)) => price >= limit,
+        }
+    }
+
+    fn matches(&self, order: &OrderRef) -> bool {
+        match order {
+            OrderRef::Exchange(id) => self.order_id == *id,
+            OrderRef::Client(id) => self.order.client_order_id.as_ref() == Some(id),
+        }
+    }
+}
+
+/// 经过下单延迟后才由交易所处理的请求
+#[derive(Debug)]
+enum Action {
+    Place(SimOrder),
+    Cancel(OrderRef),
+    Amend(AmendOrder),
+}
+
+/// 回测使用的模拟交易所
+///
+/// 实现了 [`OrderExecutor`]，策略看到的下单接口与实盘一致。请求经过 [`FillModel::submit_latency`]
+/// 后才由交易所处理，确认立即返回，订单状态通过 [`SimExchange::take_updates`] 推送。
+///
+/// 订单到达时如果有深度，立即按档位吃掉对手盘并消耗本地订单簿，剩余部分挂单并估计排队位置；
+/// 没有深度时在该产品的下一条行情撮合，不会使用下单时已经可见的价格：
+/// - 成交：市价单与可成交的限价单按成交价成交
+/// - K线：市价单与可成交的限价单按开盘价成交，只使用订单到达后开盘的K线
+///
+/// 挂单按委托价成交，成交条件由 [`QueueModel`] 决定：
+/// - 成交价或K线最高/最低价穿过委托价、对手方最优价达到委托价时全部成交
+/// - 同价位的成交先消耗排在前面的数量，之后部分成交
+///
+/// 吃单计入 [`FillModel::slippage`]，所有成交按 [`FillModel::fees`] 收取手续费。
+/// 币币（[`TradeMode::Cash`]）订单在现金或持仓不足时撤销剩余部分，
+/// 保证金模式不检查资金，允许持有空头。
+#[derive(Debug)]
+pub struct SimExchange {
+    now: Timestamp,
+    cash: f64,
+    /// 手续费与现金的币种，例如 "USDT"
+    quote_currency: ByteString,
+    model: FillModel,
+    next_order_id: u64,
+    /// 按处理时间排序的请求
+    pending: VecDeque<(Timestamp, Action)>,
+    orders: Vec<SimOrder>,
+    /// 由回放的深度维护，吃单会消耗其中的数量直到下一次推送
+    books: HashMap<ByteString, OrderBook>,
+    positions: HashMap<ByteString, SimPosition>,
+    updates: Vec<OrderUpdate>,
+    trades: Vec<TradeRecord>,
+}
+
+impl SimExchange {
+    pub fn new(cash: f64, quote_currency: impl Into<ByteString>, model: FillModel) -> Self {
+        Self {
+            now: 0,
+            cash,
+            quote_currency: quote_currency.into(),
+            model,
+            next_order_id: 1,
+            pending: VecDeque::new(),
+            orders: Vec::new(),
+            books: HashMap::new(),
+            positions: HashMap::new(),
+            updates: Vec::new(),
+            trades: Vec::new(),
+        }
+    }
+
+    pub fn model(&self) -> &FillModel {
+        &self.model
+    }
+
+    pub fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// 现金加上所有持仓按最新价格计算的市值
+    pub fn equity(&self) -> f64 {
+        self.cash
+            + self
+                .positions
+                .values()
+                .map(|position| position.quantity.to_f64() * position.mark_price)
+                .sum::<f64>()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = &SimPosition> {
+        self.positions.values()
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&SimPosition> {
+        self.positions.get(symbol)
+    }
+
+    pub fn trades(&self) -> &[TradeRecord] {
+        &self.trades
+    }
+
+    /// 取出尚未推送给策略的订单更新
+    pub fn take_updates(&mut self) -> Vec<OrderUpdate> {
+        std::mem::take(&mut self.updates)
+    }
+
+    /// 推进模拟时钟，时钟不会倒退
+    pub fn advance_to(&mut self, now: Timestamp) {
+        self.now = self.now.max(now);
+    }
+
+    /// 下一个请求到达交易所的时间
+    pub fn next_pending(&self) -> Option<Timestamp> {
+        self.pending.front().map(|(due, _)| *due)
+    }
+
+    /// 处理所有不晚于 `until` 到达交易所的请求，模拟时钟依次推进到每个请求到达的时间
+    pub fn process_pending(&mut self, until: Timestamp) {
+        while self.next_pending().is_some_and(|due| due <= until) {
+            let Some((due, action)) = self.pending.pop_front() else {
+                break;
+            };
+            self.advance_to(due);
+            match action {
+                Action::Place(order) => {
+                    self.push_update(&order, OrderStatus::New, None);
+                    self.activate(order);
+                }
+                // 到达时订单可能已经完结
+                Action::Cancel(order) => {
+                    if let Some(index) = self.find(&order) {
+                        let order = self.orders.remove(index);
+                        self.finish(order, OrderStatus::Canceled);
+                    }
+                }
+                Action::Amend(amend) => self.amend(amend),
+            }
+        }
+    }
+
+    /// 推进模拟时钟到行情的可见时间，用该行情撮合同一产品的订单并更新估值价格
+    pub fn on_data(&mut self, data: &DataEnum) {
+        self.process_pending(data.available_at());
+        self.advance_to(data.available_at());
+
+        let symbol = data.symbol();
+        if let DataEnum::Book(book) = data {
+            self.books
+                .entry(symbol.clone())
+                .or_insert_with(|| OrderBook::new(symbol.clone()))
+                .apply(book);
+        }
+
+        for order in std::mem::take(&mut self.orders) {
+            if order.order.symbol != symbol || data.timestamp() < order.arrived {
+                self.orders.push(order);
+            } else if order.resting {
+                self.match_resting(order, data);
+            } else {
+                self.take(order, Some(data));
+            }
+        }
+
+        let mark = match data {
+            DataEnum::Trade(trade) => Some(trade.price.to_f64()),
+            DataEnum::Candle(candle) => Some(candle.close.to_f64()),
+            DataEnum::Book(_) => self.books.get(symbol).and_then(OrderBook::mid_price),
+        };
+        if let Some(mark) = mark {
+            self.position_mut(symbol).mark_price = mark;
+        }
+    }
+
+    /// 订单到达交易所，有深度时立即撮合，否则等待下一条行情
+    fn activate(&mut self, mut order: SimOrder) {
+        order.arrived = self.now;
+        if self
+            .books
+            .get(&order.order.symbol)
+            .is_some_and(|book| !book.is_empty())
+        {
+            self.take(order, None);
+        } else {
+            self.orders.push(order);
+        }
+    }
+
+    /// 按吃单撮合刚到达的订单，`data` 为 `None` 或深度时按本地订单簿的档位成交
+    fn take(&mut self, mut order: SimOrder, data: Option<&DataEnum>) {
+        let from_book = matches!(data, None | Some(DataEnum::Book(_)));
+        let liquidity = match data {
+            Some(DataEnum::Trade(trade)) => vec![(trade.price, order.remaining())],
+            Some(DataEnum::Candle(candle)) => vec![(candle.open, order.remaining())],
+            Some(DataEnum::Book(_)) | None => self.liquidity(&order),
+        };
+        let liquidity: Vec<_> = liquidity
+            .into_iter()
+            .filter(|&(price, _)| order.crosses(price))
+            .collect();
+
+        let rejected = match order.order.order_type {
+            // 只做 maker 的订单会立即成交时被撤销
+            OrderType::PostOnly => !liquidity.is_empty(),
+            OrderType::Fok => {
+                liquidity
+                    .iter()
+                    .map(|&(_, quantity)| quantity)
+                    .sum::<Quantity>()
+                    < order.remaining()
+            }
+            _ => false,
+        };
+        if rejected {
+            self.finish(order, OrderStatus::Canceled);
+            return;
+        }
+
+        for (price, quantity) in liquidity {
+            if from_book {
+                self.consume(&order, price, quantity);
+            }
+            let price = self.slipped(&order, price);
+            if !self.fill(&mut order, price, quantity, false) {
+                self.finish(order, OrderStatus::Canceled);
+                return;
+            }
+        }
+
+        if order.remaining().is_zero() {
+            return;
+        }
+        match order.order.order_type {
+            // 下单时已拒绝未知类型
+            OrderType::Market | OrderType::Ioc | OrderType::Fok | OrderType::Unknown => {
+                self.finish(order, OrderStatus::Canceled);
+            }
+            OrderType::Limit | OrderType::PostOnly => {
+                order.resting = true;
+                order.queue_ahead = self.level_quantity(&order);
+                match data {
+                    // K线开盘后价格仍可能穿过委托价
+                    Some(data @ DataEnum::Candle(_)) => self.match_resting(order, data),
+                    _ => self.orders.push(order),
+                }
+            }
+        }
+    }
+
+    /// 本地订单簿中可供成交的对手盘档位，按从优到劣排序
+    fn liquidity(&self, order: &SimOrder) -> Vec<(Price, Quantity)> {
+        let Some(book) = self.books.get(&order.order.symbol) else {
+            return Vec::new();
+        };
+
+        let mut remaining = order.remaining();
+        let mut liquidity = Vec::new();
+        for level in levels(book, opposite(order.order.side)) {
+            if remaining.is_zero() || !order.crosses(level.price) {
+                break;
+            }
+            let quantity = remaining.min(level.quantity);
+            remaining -= quantity;
+            liquidity.push((level.price, quantity));
+        }
+
+        liquidity
+    }
+
+    /// 吃单消耗本地订单簿的数量
+    fn consume(&mut self, order: &SimOrder, price: Price, quantity: Quantity) {
+        let side = opposite(order.order.side);
+        let Some(book) = self.books.get_mut(&order.order.symbol) else {
+            return;
+        };
+        let Some(left) = levels(book, side)
+            .find(|level| level.price == price)
+            .map(|level| level.quantity - quantity)
+        else {
+            return;
+        };
+        book.update_level(
+            side,
+            BookLevel {
+                price,
+                quantity: left.max(Quantity::ZERO),
+            },
+        );
+    }
+
+    /// 本地订单簿中与挂单同价位的数量，没有深度时返回 `None`
+    fn level_quantity(&self, order: &SimOrder) -> Option<Quantity> {
+        let book = self.books.get(&order.order.symbol)?;
+        let limit = order.limit()?;
+
+        Some(
+            levels(book, order.order.side)
+                .find(|level| level.price == limit)
+                .map_or(Quantity::ZERO, |level| level.quantity),
+        )
+    }
+
+    /// 吃单的成交价计入滑点，但不差于委托价
+    fn slipped(&self, order: &SimOrder, price: Price) -> Price {
+        let slipped = self.model.slippage.apply(order.order.side, price);
+        match (order.order.side, order.limit()) {
+            (_, None) => slipped,
+            (Side::Buy, Some(limit)) => slipped.min(limit),
+            (Side::Sell, Some(limit)) => slipped.max(limit),
+        }
+    }
+
+    /// 用一条行情撮合挂单，未完结的订单放回挂单列表
+    fn match_resting(&mut self, mut order: SimOrder, data: &DataEnum) {
+        let Some(limit) = order.limit() else {
+            self.orders.push(order);
+            return;
+        };
+        let side = order.order.side;
+        let through = |price: Price| match side {
+            Side::Buy => price < limit,
+            Side::Sell => price > limit,
+        };
+        let remaining = order.remaining();
+
+        let quantity = match data {
+            DataEnum::Trade(trade) if through(trade.price) => remaining,
+            // 对手方在委托价主动成交，先消耗排在前面的数量
+            DataEnum::Trade(trade) if trade.price == limit && trade.side != side => {
+                match self.model.queue {
+                    QueueModel::Touch => remaining,
+                    QueueModel::Estimate => {
+                        let ahead = order.queue_ahead.unwrap_or(Quantity::ZERO);
+                        order.queue_ahead = Some((ahead - trade.quantity).max(Quantity::ZERO));
+                        (trade.quantity - ahead).max(Quantity::ZERO).min(remaining)
+                    }
+                }
+            }
+            DataEnum::Trade(_) => Quantity::ZERO,
+            DataEnum::Candle(candle) => {
+                let extreme = match side {
+                    Side::Buy => candle.low,
+                    Side::Sell => candle.high,
+                };
+                let touched = self.model.queue == QueueModel::Touch && extreme == limit;
+                if through(extreme) || touched {
+                    remaining
+                } else {
+                    Quantity::ZERO
+                }
+            }
+            DataEnum::Book(_) => {
+                let best = self
+                    .books
+                    .get(&order.order.symbol)
+                    .and_then(|book| levels(book, opposite(side)).next())
+                    .map(|level| level.price);
+                if best.is_some_and(|price| order.crosses(price)) {
+                    remaining
+                } else {
+                    // 同价位数量减少时视为前面的订单撤单，排队位置前移
+                    let level = self.level_quantity(&order).unwrap_or(Quantity::ZERO);
+                    order.queue_ahead =
+                        Some(order.queue_ahead.map_or(level, |ahead| ahead.min(level)));
+                    Quantity::ZERO
+                }
+            }
+        };
+
+        if quantity.is_zero() {
+            self.orders.push(order);
+        } else if !self.fill(&mut order, limit, quantity, true) {
+            self.finish(order, OrderStatus::Canceled);
+        } else if !order.remaining().is_zero() {
+            self.orders.push(order);
+        }
+    }
+
+    /// 记录一笔成交并推送订单更新，币币订单资金不足时不成交并返回 `false`
+    fn fill(
+        &mut self,
+        order: &mut SimOrder,
+        price: Price,
+        quantity: Quantity,
+        is_maker: bool,
+    ) -> bool {
+        let value = (price * quantity).to_f64();
+        let fee = self.model.fees.fee(value, is_maker);
+        let cash = self.cash;
+        let symbol = order.order.symbol.clone();
+        let position = self.position_mut(&symbol);
+
+        if order.order.trade_mode == TradeMode::Cash {
+            let affordable = match order.order.side {
+                Side::Buy => value - fee <= cash,
+                Side::Sell => quantity <= position.quantity,
+            };
+            if !affordable {
+                return false;
+            }
+        }
+
+        let realized = position.apply_fill(order.order.side, price.to_f64(), quantity);
+        if position.mark_price == 0.0 {
+            position.mark_price = price.to_f64();
+        }
+        self.cash += fee
+            + match order.order.side {
+                Side::Buy => -value,
+                Side::Sell => value,
+            };
+
+        order.filled += quantity;
+        order.filled_value += value;
+        order.fee += fee;
+        let trade_id = ByteString::from(format!("{}-{}", order.order_id, self.trades.len() + 1));
+        self.trades.push(TradeRecord {
+            timestamp: self.now,
+            symbol,
+            order_id: order.order_id.clone(),
+            client_order_id: order.order.client_order_id.clone(),
+            side: order.order.side,
+            price,
+            quantity,
+            fee,
+            is_maker,
+            realized_pnl: realized,
+        });
+
+        let fill = Fill {
+            trade_id,
+            price,
+            quantity,
+            fee,
+            fee_currency: self.quote_currency.clone(),
+            is_maker,
+            timestamp: self.now,
+        };
+        let status = if order.remaining().is_zero() {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        self.push_update(order, status, Some(fill));
+
+        true
+    }
+
+    fn amend(&mut self, amend: AmendOrder) {
+        let Some(index) = self.find(&amend.order) else {
+            return;
+        };
+
+        let mut order = self.orders.remove(index);
+        if let Some(quantity) = amend.new_quantity {
+            if quantity <= order.filled {
+                if amend.cancel_on_fail {
+                    self.finish(order, OrderStatus::Canceled);
+                } else {
+                    self.orders.insert(index, order);
+                }
+                return;
+            }
+            order.order.quantity = quantity;
+        }
+        if let Some(price) = amend.new_price {
+            order.order.price = Some(price);
+        }
+
+        // 改单后重新排队
+        order.resting = false;
+        order.queue_ahead = None;
+        self.push_update(&order, OrderStatus::New, None);
+        self.activate(order);
+    }
+
+    fn position_mut(&mut self, symbol: &ByteString) -> &mut SimPosition {
+        self.positions
+            .entry(symbol.clone())
+            .or_insert_with(|| SimPosition {
+                symbol: symbol.clone(),
+                ..Default::default()
+            })
+    }
+
+    fn finish(&mut self, order: SimOrder, status: OrderStatus) {
+        self.push_update(&order, status, None);
+    }
+
+    fn push_update(&mut self, order: &SimOrder, status: OrderStatus, last_fill: Option<Fill>) {
+        let average_price = (!order.filled.is_zero())
+            .then(|| Price::try_from(order.filled_value / order.filled.to_f64()).ok())
+            .flatten();
+
+        self.updates.push(OrderUpdate {
+            symbol: order.order.symbol.clone(),
+            order_id: order.order_id.clone(),
+            client_order_id: order.order.client_order_id.clone(),
+            side: order.order.side,
+            order_type: order.order.order_type,
+            status,
+            price: order.order.price,
+            quantity: order.order.quantity,
+            filled_quantity: order.filled,
+            average_price,
+            fee: order.fee,
+            fee_currency: self.quote_currency.clone(),
+            last_fill,
+            timestamp: self.now,
+        });
+    }
+
+    fn find(&self, order: &OrderRef) -> Option<usize> {
+        self.orders.iter().position(|open| open.matches(order))
+    }
+
+    /// 查找挂单或尚未到达交易所的订单
+    fn lookup(&self, order: &OrderRef) -> Option<OrderAck> {
+        let submitted = self.pending.iter().filter_map(|(_, action)| match action {
+            Action::Place(placed) => Some(placed),
+            _ => None,
+        });
+        self.orders
+            .iter()
+            .chain(submitted)
+            .find(|open| open.matches(order))
+            .map(Self::ack)
+    }
+
+    /// 请求经过下单延迟后到达交易所
+    fn submit(&mut self, action: Action) {
+        let due = self.now + self.model.submit_latency.as_millis();
+        self.pending.push_back((due, action));
+    }
+
+    fn ack(order: &SimOrder) -> OrderAck {
+        OrderAck {
+            order_id: order.order_id.clone(),
+            client_order_id: order.order.client_order_id.clone(),
+        }
+    }
+}
+
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Buy => Side::Sell,
+        Side::Sell => Side::Buy,
+    }
+}
+
+/// 订单簿某一方向的档位，按从优到劣排序
+fn levels(book: &OrderBook, side: Side) -> Box<dyn Iterator<Item = &BookLevel> + '_> {
+    match side {
+        Side::Buy => Box::new(book.bids()),
+        Side::Sell => Box::new(book.asks()),
+    }
+}
+
+impl OrderExecutor for SimExchange {
+    async fn place_order(&mut self, order: NewOrder) -> Result<OrderAck> {
+        ensure!(
+            order.quantity.is_positive(),
+            "Order quantity must be positive, got {}",
+            order.quantity
+        );
+        ensure!(
+            order.order_type != OrderType::Unknown,
+            "Cannot place an order of unknown type on {}",
+            order.symbol
+        );
+        if order.order_type != OrderType::Market && order.price.is_none() {
+            bail!(
+                "Price is required for {:?} order on {}",
+                order.order_type,
+                order.symbol
+            );
+        }
+        if let Some(id) = &order.client_order_id
+            && self.lookup(&OrderRef::Client(id.clone())).is_some()
+        {
+            bail!("Duplicate client order id '{id}'");
+        }
+
+        let order = SimOrder {
+            order,
+            order_id: self.next_order_id.to_string().into(),
+            filled: Quantity::ZERO,
+            filled_value: 0.0,
+            fee: 0.0,
+            resting: false,
+            arrived: 0,
+            queue_ahead: None,
+        };
+        self.next_order_id += 1;
+
+        let ack = Self::ack(&order);
+        self.submit(Action::Place(order));
+
+        Ok(ack)
+    }
+
+    async fn cancel_order(&mut self, cancel: CancelOrder) -> Result<OrderAck> {
+        let ack = self
+            .lookup(&cancel.order)
+            .ok_or_eyre(format!("Order {:?} does not exist", cancel.order))?;
+        self.submit(Action::Cancel(cancel.order));
+
+        Ok(ack)
+    }
+
+    async fn amend_order(&mut self, amend: AmendOrder) -> Result<OrderAck> {
+        let ack = self
+            .lookup(&amend.order)
+            .ok_or_eyre(format!("Order {:?} does not exist", amend.order))?;
+        if let Some(quantity) = amend.new_quantity {
+            ensure!(
+                quantity.is_positive(),
+                "New quantity must be positive, got {quantity}"
+            );
+        }
+        self.submit(Action::Amend(amend));
+
+        Ok(ack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backtest::FeeSchedule,
+        data::{BookAction, BookData, CandleData, CandleInterval, TradeData},
+    };
+
+    const SYMBOL: &str = "BTC-USDT";
+
+    fn dec(s: &str) -> Price {
+        s.parse().unwrap()
+    }
+
+    fn exchange(fees: FeeSchedule) -> SimExchange {
+        SimExchange::new(
+            10_000.0,
+            "USDT",
+            FillModel {
+                fees,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn book(timestamp: Timestamp, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> DataEnum {
+        let levels = |levels: &[(&str, &str)]| {
+            levels
+                .iter()
+                .map(|&(price, quantity)| (dec(price), dec(quantity)))
+                .collect()
+        };
+        DataEnum::Book(BookData {
+            symbol: SYMBOL.into(),
+            action: BookAction::Snapshot,
+            bids: levels(bids),
+            asks: levels(asks),
+            timestamp,
+        })
+    }
+
+    fn trade(timestamp: Timestamp, price: &str, side: Side) -> DataEnum {
+        DataEnum::Trade(TradeData {
+            trade_id: timestamp.to_string().into(),
+            symbol: SYMBOL.into(),
+            price: dec(price),
+            quantity: dec("10"),
+            side,
+            timestamp,
+        })
+    }
+
+    fn candle(timestamp: Timestamp, open: &str, close: &str) -> DataEnum {
+        let (open, close) = (dec(open), dec(close));
+        DataEnum::Candle(CandleData {
+            symbol: SYMBOL.into(),
+            interval: CandleInterval::M1,
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume: dec("1"),
+            timestamp,
+        })
+    }
+
+    fn order(side: Side, order_type: OrderType, quantity: &str, price: Option<&str>) -> NewOrder {
+        NewOrder::builder(SYMBOL)
+            .side(side)
+            .order_type(order_type)
+            .trade_mode(TradeMode::Cross)
+            .quantity(dec(quantity))
+            .maybe_price(price.map(dec))
+            .build()
+    }
+
+    /// 默认的延迟为 0，下单后立即到达交易所
+    async fn place(exchange: &mut SimExchange, order: NewOrder) {
+        exchange.place_order(order).await.unwrap();
+        exchange.process_pending(exchange.now());
+    }
+
+    fn statuses(exchange: &mut SimExchange) -> Vec<OrderStatus> {
+        exchange
+            .take_updates()
+            .into_iter()
+            .map(|update| update.status)
+            .collect()
+    }
+
+    fn fills(exchange: &SimExchange) -> Vec<(Price, Quantity, bool)> {
+        exchange
+            .trades()
+            .iter()
+            .map(|trade| (trade.price, trade.quantity, trade.is_maker))
+            .collect()
+    }
+
+    /// 买一 100，卖一 101，各两档
+    fn with_book() -> SimExchange {
+        let mut exchange = exchange(FeeSchedule::ZERO);
+        exchange.on_data(&book(
+            1_000,
+            &[("100", "1"), ("99", "2")],
+            &[("101", "1"), ("102", "2")],
+        ));
+        exchange
+    }
+
+    #[tokio::test]
+    async fn market_order_walks_the_book() {
+        let mut exchange = with_book();
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Market, "2", None),
+        )
+        .await;
+
+        assert_eq!(
+            fills(&exchange),
+            vec![(dec("101"), dec("1"), false), (dec("102"), dec("1"), false)]
+        );
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![
+                OrderStatus::New,
+                OrderStatus::PartiallyFilled,
+                OrderStatus::Filled
+            ]
+        );
+        // 吃掉的档位在下一次推送前不可再成交
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Ioc, "1", Some("101")),
+        )
+        .await;
+        assert_eq!(exchange.trades().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn limit_order_takes_then_rests() {
+        let mut exchange = with_book();
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Limit, "2", Some("101")),
+        )
+        .await;
+        assert_eq!(fills(&exchange), vec![(dec("101"), dec("1"), false)]);
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![OrderStatus::New, OrderStatus::PartiallyFilled]
+        );
+
+        // 成交价未穿过委托价时不成交
+        exchange.on_data(&trade(2_000, "101.5", Side::Sell));
+        assert_eq!(exchange.trades().len(), 1);
+        exchange.on_data(&trade(3_000, "100.5", Side::Sell));
+        assert_eq!(
+            fills(&exchange)[1..],
+            [(dec("101"), dec("1"), true)],
+            "resting orders fill at the limit price as maker"
+        );
+        assert_eq!(statuses(&mut exchange), vec![OrderStatus::Filled]);
+    }
+
+    #[tokio::test]
+    async fn post_only_is_canceled_when_it_would_take() {
+        let mut exchange = with_book();
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::PostOnly, "1", Some("101")),
+        )
+        .await;
+        assert!(exchange.trades().is_empty());
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![OrderStatus::New, OrderStatus::Canceled]
+        );
+
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::PostOnly, "1", Some("100.5")),
+        )
+        .await;
+        assert_eq!(statuses(&mut exchange), vec![OrderStatus::New]);
+        exchange.on_data(&book(2_000, &[("100", "1")], &[("100.5", "3")]));
+        assert_eq!(fills(&exchange), vec![(dec("100.5"), dec("1"), true)]);
+    }
+
+    #[tokio::test]
+    async fn ioc_cancels_the_remainder() {
+        let mut exchange = with_book();
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Ioc, "3", Some("101")),
+        )
+        .await;
+
+        assert_eq!(fills(&exchange), vec![(dec("101"), dec("1"), false)]);
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![
+                OrderStatus::New,
+                OrderStatus::PartiallyFilled,
+                OrderStatus::Canceled
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fok_fills_completely_or_not_at_all() {
+        let mut exchange = with_book();
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Fok, "2", Some("101")),
+        )
+        .await;
+        assert!(exchange.trades().is_empty());
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![OrderStatus::New, OrderStatus::Canceled]
+        );
+
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Fok, "3", Some("102")),
+        )
+        .await;
+        assert_eq!(
+            fills(&exchange),
+            vec![(dec("101"), dec("1"), false), (dec("102"), dec("2"), false)]
+        );
+        assert_eq!(statuses(&mut exchange).last(), Some(&OrderStatus::Filled));
+    }
+
+    #[tokio::test]
+    async fn candle_orders_fill_at_the_next_open() {
+        let mut exchange = exchange(FeeSchedule::ZERO);
+        let bar = candle(0, "100", "110");
+        exchange.on_data(&bar);
+
+        // 策略在K线收盘时下单
+        assert_eq!(exchange.now(), 60_000);
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Market, "1", None),
+        )
+        .await;
+
+        // 下单前开盘的K线不能用于撮合
+        exchange.on_data(&bar);
+        assert!(exchange.trades().is_empty());
+
+        exchange.on_data(&candle(60_000, "111", "112"));
+        assert_eq!(fills(&exchange), vec![(dec("111"), dec("1"), false)]);
+    }
+
+    #[tokio::test]
+    async fn tracks_cash_positions_and_equity() {
+        let mut exchange = exchange(FeeSchedule {
+            maker: 0.0,
+            taker: 0.001,
+        });
+        exchange.on_data(&book(1_000, &[("100", "5")], &[("101", "1"), ("102", "1")]));
+
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Market, "2", None),
+        )
+        .await;
+        let position = exchange.position(SYMBOL).unwrap();
+        assert_eq!(position.quantity, dec("2"));
+        assert_eq!(position.average_price, 101.5);
+        let cash = 10_000.0 - 203.0 - 0.203;
+        assert!((exchange.cash() - cash).abs() < 1e-9);
+
+        // 按中间价估值
+        exchange.on_data(&book(2_000, &[("110", "5")], &[("111", "5")]));
+        assert!((exchange.equity() - (cash + 2.0 * 110.5)).abs() < 1e-9);
+
+        // 卖出 3 个：平掉多头并反手开空 1 个
+        place(
+            &mut exchange,
+            order(Side::Sell, OrderType::Market, "3", None),
+        )
+        .await;
+        let position = exchange.position(SYMBOL).unwrap();
+        assert_eq!(position.quantity, dec("-1"));
+        assert_eq!(position.average_price, 110.0);
+        assert_eq!(position.realized_pnl, 17.0);
+        assert_eq!(exchange.trades().last().unwrap().realized_pnl, 17.0);
+
+        let cash = cash + 330.0 - 0.33;
+        assert!((exchange.cash() - cash).abs() < 1e-9);
+        assert!((exchange.equity() - (cash - 110.5)).abs() < 1e-9);
+        let fees: f64 = exchange.trades().iter().map(|trade| trade.fee).sum();
+        assert!((fees + 0.533).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn cash_orders_need_funds() {
+        let mut exchange = with_book();
+        let mut sell = order(Side::Sell, OrderType::Market, "1", None);
+        sell.trade_mode = TradeMode::Cash;
+        place(&mut exchange, sell).await;
+
+        assert!(exchange.trades().is_empty());
+        assert_eq!(
+            statuses(&mut exchange),
+            vec![OrderStatus::New, OrderStatus::Canceled]
+        );
+    }
+}
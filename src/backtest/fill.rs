@@ -0,0 +1,271 @@
+use crate::{decimal::Price, order::Side};
+use serde::Deserialize;
+use serde_with::{DurationMilliSeconds, serde_as};
+use std::time::Duration;
+
+/// 挂单的排队模型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueModel {
+    /// 价格触及委托价即全部成交，结果偏乐观
+    Touch,
+    /// 按深度估计排在前面的数量，同价位的成交与撤单消耗队列后才成交；
+    /// 只有K线时，价格穿过委托价才成交
+    #[default]
+    Estimate,
+}
+
+/// 吃单的滑点，按不利方向调整成交价，限价单不会差于委托价
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slippage {
+    #[default]
+    None,
+    /// 固定价差
+    Fixed(Price),
+    /// 成交价的比例，例如 0.0005 表示 5 个基点
+    Percent(f64),
+}
+
+impl Slippage {
+    pub fn apply(self, side: Side, price: Price) -> Price {
+        let adjusted = match self {
+            Slippage::None => return price,
+            Slippage::Fixed(offset) => match side {
+                Side::Buy => price + offset,
+                Side::Sell => price - offset,
+            },
+            Slippage::Percent(rate) => {
+                let factor = match side {
+                    Side::Buy => 1.0 + rate,
+                    Side::Sell => 1.0 - rate,
+                };
+                Price::try_from(factor).map_or(price, |factor| (price * factor).normalize())
+            }
+        };
+
+        adjusted.max(Price::ZERO)
+    }
+}
+
+/// 按成交额收取的手续费率，负数表示返佣
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct FeeSchedule {
+    pub maker: f64,
+    pub taker: f64,
+}
+
+impl FeeSchedule {
+    /// OKX 币币普通用户 Lv1
+    pub const OKX_SPOT_LV1: FeeSchedule = FeeSchedule {
+        maker: 0.0008,
+        taker: 0.001,
+    };
+    /// OKX 合约普通用户 Lv1
+    pub const OKX_SWAP_LV1: FeeSchedule = FeeSchedule {
+        maker: 0.0002,
+        taker: 0.0005,
+    };
+    pub const ZERO: FeeSchedule = FeeSchedule {
+        maker: 0.0,
+        taker: 0.0,
+    };
+
+    /// 成交额为 `value` 的手续费，负数表示支出
+    pub fn fee(&self, value: f64, is_maker: bool) -> f64 {
+        let rate = if is_maker { self.maker } else { self.taker };
+        -value * rate
+    }
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self::OKX_SPOT_LV1
+    }
+}
+
+/// 回测的撮合模型
+#[serde_as]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FillModel {
+    pub queue: QueueModel,
+    pub slippage: Slippage,
+    pub fees: FeeSchedule,
+    /// 下单、撤单、改单从发出到交易所处理的延迟（毫秒）
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub submit_latency: Duration,
+    /// 行情与订单推送从交易所到策略的延迟（毫秒）
+    #[serde_as(as = "DurationMilliSeconds<u64>")]
+    pub market_data_latency: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Timestamp,
+        backtest::SimExchange,
+        client::OrderExecutor,
+        data::{BookAction, BookData, DataEnum, TradeData},
+        decimal::Quantity,
+        order::{NewOrder, OrderType, TradeMode},
+    };
+
+    const SYMBOL: &str = "BTC-USDT";
+
+    fn dec(s: &str) -> Price {
+        s.parse().unwrap()
+    }
+
+    fn sim(model: FillModel) -> SimExchange {
+        SimExchange::new(
+            10_000.0,
+            "USDT",
+            FillModel {
+                fees: FeeSchedule::ZERO,
+                ..model
+            },
+        )
+    }
+
+    fn book(timestamp: Timestamp, bid: (&str, &str), ask: (&str, &str)) -> DataEnum {
+        DataEnum::Book(BookData {
+            symbol: SYMBOL.into(),
+            action: BookAction::Snapshot,
+            bids: vec![(dec(bid.0), dec(bid.1))],
+            asks: vec![(dec(ask.0), dec(ask.1))],
+            timestamp,
+        })
+    }
+
+    fn trade(timestamp: Timestamp, price: &str, quantity: &str, side: Side) -> DataEnum {
+        DataEnum::Trade(TradeData {
+            trade_id: timestamp.to_string().into(),
+            symbol: SYMBOL.into(),
+            price: dec(price),
+            quantity: dec(quantity),
+            side,
+            timestamp,
+        })
+    }
+
+    fn order(side: Side, order_type: OrderType, price: Option<&str>) -> NewOrder {
+        NewOrder::builder(SYMBOL)
+            .side(side)
+            .order_type(order_type)
+            .trade_mode(TradeMode::Cross)
+            .quantity(dec("1"))
+            .maybe_price(price.map(dec))
+            .build()
+    }
+
+    async fn place(exchange: &mut SimExchange, order: NewOrder) {
+        exchange.place_order(order).await.unwrap();
+        exchange.process_pending(exchange.now());
+    }
+
+    fn fills(exchange: &SimExchange) -> Vec<(Timestamp, Price, Quantity)> {
+        exchange
+            .trades()
+            .iter()
+            .map(|trade| (trade.timestamp, trade.price, trade.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn slippage_moves_the_price_against_the_taker() {
+        let price = dec("100");
+        let fixed = Slippage::Fixed(dec("0.5"));
+        assert_eq!(fixed.apply(Side::Buy, price), dec("100.5"));
+        assert_eq!(fixed.apply(Side::Sell, price), dec("99.5"));
+        assert_eq!(
+            Slippage::Fixed(dec("200")).apply(Side::Sell, price),
+            Price::ZERO
+        );
+
+        let percent = Slippage::Percent(0.001);
+        assert_eq!(percent.apply(Side::Buy, price), dec("100.1"));
+        assert_eq!(percent.apply(Side::Sell, price), dec("99.9"));
+        assert_eq!(Slippage::None.apply(Side::Buy, price), price);
+    }
+
+    #[tokio::test]
+    async fn slippage_does_not_cross_the_limit_price() {
+        let mut exchange = sim(FillModel {
+            slippage: Slippage::Fixed(dec("0.5")),
+            ..Default::default()
+        });
+        exchange.on_data(&book(1_000, ("100", "5"), ("101", "5")));
+
+        place(&mut exchange, order(Side::Buy, OrderType::Market, None)).await;
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Ioc, Some("101.2")),
+        )
+        .await;
+        place(&mut exchange, order(Side::Sell, OrderType::Market, None)).await;
+
+        let prices: Vec<_> = fills(&exchange)
+            .into_iter()
+            .map(|(_, price, _)| price)
+            .collect();
+        assert_eq!(prices, vec![dec("101.5"), dec("101.2"), dec("99.5")]);
+    }
+
+    #[tokio::test]
+    async fn estimate_waits_for_the_volume_ahead() {
+        let mut exchange = sim(FillModel::default());
+        exchange.on_data(&book(1_000, ("100", "3"), ("101", "5")));
+        // 排在同价位的 3 个之后
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Limit, Some("100")),
+        )
+        .await;
+
+        exchange.on_data(&trade(2_000, "100", "2", Side::Sell));
+        assert!(exchange.trades().is_empty());
+        // 买方主动成交不消耗买单的队列
+        exchange.on_data(&trade(3_000, "100", "5", Side::Buy));
+        assert!(exchange.trades().is_empty());
+        exchange.on_data(&trade(4_000, "100", "1", Side::Sell));
+        assert!(exchange.trades().is_empty());
+        exchange.on_data(&trade(5_000, "100", "2", Side::Sell));
+        assert_eq!(fills(&exchange), vec![(5_000, dec("100"), dec("1"))]);
+
+        // 价格触及即成交
+        let mut exchange = sim(FillModel {
+            queue: QueueModel::Touch,
+            ..Default::default()
+        });
+        exchange.on_data(&book(1_000, ("100", "3"), ("101", "5")));
+        place(
+            &mut exchange,
+            order(Side::Buy, OrderType::Limit, Some("100")),
+        )
+        .await;
+        exchange.on_data(&trade(2_000, "100", "2", Side::Sell));
+        assert_eq!(fills(&exchange), vec![(2_000, dec("100"), dec("1"))]);
+    }
+
+    #[tokio::test]
+    async fn orders_arrive_after_the_submit_latency() {
+        let mut exchange = sim(FillModel {
+            submit_latency: Duration::from_millis(500),
+            ..Default::default()
+        });
+        exchange.on_data(&book(1_000, ("100", "5"), ("101", "5")));
+        place(&mut exchange, order(Side::Buy, OrderType::Market, None)).await;
+        assert!(exchange.trades().is_empty());
+        assert_eq!(exchange.next_pending(), Some(1_500));
+
+        // 到达前的行情仍会更新订单簿，但不会撮合该订单
+        exchange.on_data(&book(1_200, ("104", "5"), ("105", "5")));
+        assert!(exchange.trades().is_empty());
+
+        // 订单在 1500 到达，按当时的订单簿成交，之后的行情不影响成交价
+        exchange.on_data(&book(1_600, ("101", "5"), ("102", "5")));
+        assert_eq!(fills(&exchange), vec![(1_500, dec("105"), dec("1"))]);
+    }
+}
@@ -0,0 +1,503 @@
+use crate::{
+    Timestamp,
+    analytics::{Analyzer, FiniteOrNull, Performance, Report},
+    book::OrderBook,
+    data::DataEnum,
+    order::OrderUpdate,
+    strategy::{Context, Strategy, execute, on_data},
+};
+use bon::Builder;
+use bytestring::ByteString;
+use eyre::{Result, WrapErr};
+use serde::Serialize;
+use serde_with::serde_as;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+pub mod exchange;
+pub mod fill;
+pub mod source;
+pub mod sweep;
+
+pub use exchange::{SimExchange, SimPosition, TradeRecord};
+pub use fill::{FeeSchedule, FillModel, QueueModel, Slippage};
+
+/// 某一时刻的账户权益
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EquityPoint {
+    pub timestamp: Timestamp,
+    #[serde_as(as = "FiniteOrNull")]
+    pub cash: f64,
+    /// 现金加上持仓市值
+    #[serde_as(as = "FiniteOrNull")]
+    pub equity: f64,
+}
+
+/// 回测结果
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    pub initial_cash: f64,
+    /// 所有成交，按时间排序
+    pub trades: Vec<TradeRecord>,
+    /// 每个行情时间点处理完后的权益，同一时间点只保留最后一个
+    pub equity: Vec<EquityPoint>,
+    /// 回测结束时的持仓，不含从未持仓的产品
+    pub positions: Vec<SimPosition>,
+    pub cash: f64,
+}
+
+impl BacktestReport {
+    pub fn final_equity(&self) -> f64 {
+        self.equity
+            .last()
+            .map_or(self.initial_cash, |point| point.equity)
+    }
+
+    pub fn performance(&self, analyzer: &Analyzer) -> Performance {
+        analyzer.analyze(self.initial_cash, &self.trades, &self.equity)
+    }
+
+    /// 生成可以写为 JSON 或 HTML 的报告
+    pub fn report<'a>(&'a self, name: &'a str, analyzer: &Analyzer) -> Report<'a> {
+        Report {
+            name,
+            performance: self.performance(analyzer),
+            equity: &self.equity,
+            trades: &self.trades,
+        }
+    }
+}
+
+/// 事件驱动的回测
+///
+/// 按时间顺序回放行情，模拟时钟取行情完整可见的时间（[`DataEnum::available_at`]），K线在收盘时送达。策略的回调、[`Context`] 与订单更新和实盘运行时一致，
+/// 指令由 [`SimExchange`] 按 [`FillModel`] 撮合。行情与订单更新经过 [`FillModel::market_data_latency`]
+/// 后才送达策略，策略看到行情时交易所可能已经处理了之后的行情。
+#[derive(Builder)]
+pub struct Backtest {
+    /// 策略实例名，用于错误信息
+    #[builder(start_fn, into)]
+    name: ByteString,
+    #[builder(start_fn)]
+    strategy: Box<dyn Strategy>,
+    /// 初始现金
+    #[builder(default = 10_000.0)]
+    initial_cash: f64,
+    /// 现金与手续费的币种
+    #[builder(default = "USDT", into)]
+    quote_currency: ByteString,
+    /// 撮合、滑点、手续费与延迟模型
+    #[builder(default)]
+    fill_model: FillModel,
+}
+
+impl Backtest {
+    /// 回放 `events`，事件会先按可见时间排序，时间相同时保持原有顺序
+    pub async fn run(self, mut events: Vec<DataEnum>) -> Result<BacktestReport> {
+        events.sort_by_key(DataEnum::available_at);
+
+        let Backtest {
+            name,
+            strategy,
+            initial_cash,
+            quote_currency,
+            fill_model,
+        } = self;
+        let start = events.first().map_or(0, DataEnum::available_at);
+        let mut engine = Engine {
+            strategy,
+            ctx: Context::new(start),
+            exchange: SimExchange::new(initial_cash, quote_currency, fill_model),
+            books: HashMap::new(),
+            inbox: VecDeque::new(),
+            timer: None,
+            equity: Vec::new(),
+        };
+
+        engine
+            .run(start, events)
+            .await
+            .wrap_err_with(|| format!("Backtest of strategy '{name}' failed"))?;
+
+        let Engine {
+            exchange, equity, ..
+        } = engine;
+        let mut positions: Vec<_> = exchange
+            .positions()
+            .filter(|position| !position.quantity.is_zero() || position.realized_pnl != 0.0)
+            .cloned()
+            .collect();
+        positions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+        Ok(BacktestReport {
+            initial_cash,
+            trades: exchange.trades().to_vec(),
+            equity,
+            positions,
+            cash: exchange.cash(),
+        })
+    }
+}
+
+/// 经过行情延迟后送达策略的事件
+#[allow(clippy::large_enum_variant)]
+enum Inbound {
+    Data(DataEnum),
+    Update(OrderUpdate),
+}
+
+struct Engine {
+    strategy: Box<dyn Strategy>,
+    ctx: Context,
+    exchange: SimExchange,
+    books: HashMap<ByteString, OrderBook>,
+    /// 按送达时间排序，同一时间按产生的先后顺序
+    inbox: VecDeque<(Timestamp, Inbound)>,
+    /// 定时器的周期与下一次触发的时间
+    timer: Option<(Duration, Timestamp)>,
+    equity: Vec<EquityPoint>,
+}
+
+impl Engine {
+    async fn run(&mut self, start: Timestamp, events: Vec<DataEnum>) -> Result<()> {
+        self.exchange.advance_to(start);
+        self.ctx.set_now(start);
+        self.strategy.on_start(&mut self.ctx)?;
+        self.after_callback(start).await?;
+
+        let mut end = start;
+        for data in events {
+            let now = data.available_at();
+            self.advance(now).await?;
+
+            self.exchange.on_data(&data);
+            self.collect_updates();
+            self.deliver(now, Inbound::Data(data));
+            self.advance(now).await?;
+
+            self.record(now);
+            end = now;
+        }
+
+        // 送达最后一条行情，以及策略收到后下单引发的订单更新
+        let model = self.exchange.model();
+        end += (model.market_data_latency * 2 + model.submit_latency).as_millis();
+        self.advance(end).await?;
+
+        self.ctx.set_now(self.exchange.now().max(start));
+        self.strategy.on_stop(&mut self.ctx)?;
+        execute(self.strategy.as_mut(), &mut self.ctx, &mut self.exchange).await?;
+        self.exchange.process_pending(Timestamp::MAX);
+
+        Ok(())
+    }
+
+    /// 按时间顺序处理不晚于 `until` 的请求、推送与定时器，时间相同时依次处理
+    async fn advance(&mut self, until: Timestamp) -> Result<()> {
+        loop {
+            let pending = self.exchange.next_pending().filter(|&due| due <= until);
+            let inbound = self
+                .inbox
+                .front()
+                .map(|(due, _)| *due)
+                .filter(|&due| due <= until);
+            let timer = self.timer.map(|(_, due)| due).filter(|&due| due <= until);
+
+            match (pending, inbound, timer) {
+                (Some(due), _, _)
+                    if inbound.is_none_or(|t| due <= t) && timer.is_none_or(|t| due <= t) =>
+                {
+                    self.exchange.process_pending(due);
+                    self.collect_updates();
+                }
+                (_, Some(due), _) if timer.is_none_or(|t| due <= t) => {
+                    let Some((_, inbound)) = self.inbox.pop_front() else {
+                        break;
+                    };
+                    self.exchange.advance_to(due);
+                    self.ctx.set_now(due);
+                    match inbound {
+                        Inbound::Data(data) => on_data(
+                            self.strategy.as_mut(),
+                            &mut self.ctx,
+                            &mut self.books,
+                            &data,
+                        )?,
+                        Inbound::Update(update) => {
+                            self.strategy.on_order_update(&mut self.ctx, &update)?
+                        }
+                    }
+                    self.after_callback(due).await?;
+                }
+                (_, _, Some(due)) => {
+                    self.exchange.advance_to(due);
+                    self.ctx.set_now(due);
+                    self.strategy.on_timer(&mut self.ctx)?;
+                    self.advance_timer(due);
+                    self.after_callback(due).await?;
+                }
+                _ => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 执行指令，之后按策略的设置重置定时器
+    async fn after_callback(&mut self, now: Timestamp) -> Result<()> {
+        execute(self.strategy.as_mut(), &mut self.ctx, &mut self.exchange).await?;
+
+        let period = self.ctx.timer();
+        if self.timer.map(|(period, _)| period) != period {
+            self.timer = period.map(|period| (period, now + period.as_millis().max(1)));
+        }
+
+        Ok(())
+    }
+
+    /// 周期未变时将下一次触发的时间推进一个周期
+    fn advance_timer(&mut self, due: Timestamp) {
+        if let Some((period, next)) = &mut self.timer
+            && self.ctx.timer() == Some(*period)
+        {
+            *next = due + period.as_millis().max(1);
+        }
+    }
+
+    /// 交易所产生的订单更新经过行情延迟后送达
+    fn collect_updates(&mut self) {
+        for update in self.exchange.take_updates() {
+            self.deliver(update.timestamp, Inbound::Update(update));
+        }
+    }
+
+    fn deliver(&mut self, timestamp: Timestamp, inbound: Inbound) {
+        let due = timestamp + self.exchange.model().market_data_latency.as_millis();
+        let index = self.inbox.partition_point(|(t, _)| *t <= due);
+        self.inbox.insert(index, (due, inbound));
+    }
+
+    fn record(&mut self, timestamp: Timestamp) {
+        let point = EquityPoint {
+            timestamp,
+            cash: self.exchange.cash(),
+            equity: self.exchange.equity(),
+        };
+        match self.equity.last_mut() {
+            Some(last) if last.timestamp == timestamp => *last = point,
+            _ => self.equity.push(point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{CandleData, CandleInterval, TradeData},
+        order::{NewOrder, OrderType, Side, TradeMode},
+    };
+    use std::sync::{Arc, Mutex};
+
+    type Seen = Arc<Mutex<Vec<(Timestamp, ByteString)>>>;
+
+    /// 记录收到的行情，第一次收到 `trigger` 的行情时市价买入 `target`
+    struct Probe {
+        seen: Seen,
+        trigger: &'static str,
+        target: &'static str,
+        done: bool,
+    }
+
+    impl Probe {
+        fn on_data(&mut self, ctx: &mut Context, symbol: &ByteString) {
+            self.seen.lock().unwrap().push((ctx.now(), symbol.clone()));
+            if !self.done && symbol == self.trigger {
+                self.done = true;
+                ctx.place_order(
+                    NewOrder::builder(self.target)
+                        .side(Side::Buy)
+                        .order_type(OrderType::Market)
+                        .trade_mode(TradeMode::Cash)
+                        .quantity("1".parse().unwrap())
+                        .build(),
+                );
+            }
+        }
+    }
+
+    impl Strategy for Probe {
+        fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+            self.on_data(ctx, &candle.symbol);
+            Ok(())
+        }
+
+        fn on_trade(&mut self, ctx: &mut Context, trade: &TradeData) -> Result<()> {
+            self.on_data(ctx, &trade.symbol);
+            Ok(())
+        }
+    }
+
+    fn candle(symbol: &str, timestamp: Timestamp, open: &str, close: &str) -> DataEnum {
+        let (open, close) = (open.parse().unwrap(), close.parse().unwrap());
+        DataEnum::Candle(CandleData {
+            symbol: symbol.into(),
+            interval: CandleInterval::M1,
+            open,
+            high: std::cmp::max(open, close),
+            low: std::cmp::min(open, close),
+            close,
+            volume: "1".parse().unwrap(),
+            timestamp,
+        })
+    }
+
+    fn trade(symbol: &str, timestamp: Timestamp, price: &str) -> DataEnum {
+        DataEnum::Trade(TradeData {
+            trade_id: timestamp.to_string().into(),
+            symbol: symbol.into(),
+            price: price.parse().unwrap(),
+            quantity: "1".parse().unwrap(),
+            side: Side::Buy,
+            timestamp,
+        })
+    }
+
+    async fn run(
+        trigger: &'static str,
+        target: &'static str,
+        events: Vec<DataEnum>,
+    ) -> (BacktestReport, Seen) {
+        let seen = Seen::default();
+        let probe = Probe {
+            seen: seen.clone(),
+            trigger,
+            target,
+            done: false,
+        };
+        let fill_model = FillModel {
+            fees: FeeSchedule::ZERO,
+            ..Default::default()
+        };
+        let report = Backtest::builder("probe", Box::new(probe))
+            .fill_model(fill_model)
+            .build()
+            .run(events)
+            .await
+            .unwrap();
+        (report, seen)
+    }
+
+    #[tokio::test]
+    async fn candles_are_delivered_at_the_close() {
+        let (report, seen) = run(
+            "BTC-USDT",
+            "BTC-USDT",
+            vec![
+                candle("BTC-USDT", 0, "100", "110"),
+                candle("BTC-USDT", 60_000, "111", "120"),
+            ],
+        )
+        .await;
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(60_000, "BTC-USDT".into()), (120_000, "BTC-USDT".into())]
+        );
+        // 在第 N 根K线收盘时下单，按第 N+1 根的开盘价成交，而不是第 N 根的开盘价
+        let [trade] = report.trades.as_slice() else {
+            panic!("expected one trade, got {:?}", report.trades);
+        };
+        assert_eq!(trade.price.to_string(), "111");
+    }
+
+    #[tokio::test]
+    async fn order_cannot_fill_at_the_open_of_the_bar_it_was_placed_on() {
+        // BTC 与 ETH 的第 N 根K线同时收盘，收到 BTC 的K线后买入 ETH
+        let (report, _) = run(
+            "BTC-USDT",
+            "ETH-USDT",
+            vec![
+                candle("BTC-USDT", 0, "100", "110"),
+                candle("ETH-USDT", 0, "10", "11"),
+                candle("BTC-USDT", 60_000, "110", "120"),
+                candle("ETH-USDT", 60_000, "12", "13"),
+            ],
+        )
+        .await;
+
+        let [trade] = report.trades.as_slice() else {
+            panic!("expected one trade, got {:?}", report.trades);
+        };
+        assert_eq!(trade.symbol, "ETH-USDT");
+        assert_eq!(trade.price.to_string(), "12");
+    }
+
+    #[tokio::test]
+    async fn replay_order_is_deterministic() {
+        let events = vec![
+            trade("ETH-USDT", 30_000, "10"),
+            candle("BTC-USDT", 0, "100", "110"),
+            trade("BTC-USDT", 60_000, "110"),
+            trade("ETH-USDT", 1_000, "10"),
+            trade("ETH-USDT", 60_000, "11"),
+        ];
+
+        let (first, seen) = run("ETH-USDT", "ETH-USDT", events.clone()).await;
+        let seen = seen.lock().unwrap().clone();
+        // 按可见时间排序，K线在收盘时送达，时间相同时保持输入顺序
+        assert_eq!(
+            seen,
+            vec![
+                (1_000, "ETH-USDT".into()),
+                (30_000, "ETH-USDT".into()),
+                (60_000, "BTC-USDT".into()),
+                (60_000, "BTC-USDT".into()),
+                (60_000, "ETH-USDT".into()),
+            ]
+        );
+
+        let (second, again) = run("ETH-USDT", "ETH-USDT", events).await;
+        assert_eq!(*again.lock().unwrap(), seen);
+        assert_eq!(
+            format!("{:?}", first.trades),
+            format!("{:?}", second.trades)
+        );
+        assert_eq!(first.equity, second.equity);
+    }
+
+    #[tokio::test]
+    async fn report_matches_the_exchange_books() {
+        let (report, _) = run(
+            "ETH-USDT",
+            "ETH-USDT",
+            vec![
+                trade("ETH-USDT", 1_000, "10"),
+                trade("ETH-USDT", 2_000, "12"),
+                trade("ETH-USDT", 3_000, "15"),
+            ],
+        )
+        .await;
+
+        // 第一笔成交后买入，按下一笔成交价 12 成交，最后按 15 估值
+        assert_eq!(report.cash, 10_000.0 - 12.0);
+        let [position] = report.positions.as_slice() else {
+            panic!("expected one position, got {:?}", report.positions);
+        };
+        assert_eq!(position.quantity.to_string(), "1");
+        assert_eq!(position.average_price, 12.0);
+        assert_eq!(position.mark_price, 15.0);
+        assert_eq!(report.final_equity(), report.cash + 15.0);
+        assert_eq!(
+            report
+                .equity
+                .iter()
+                .map(|point| (point.timestamp, point.equity))
+                .collect::<Vec<_>>(),
+            vec![(1_000, 10_000.0), (2_000, 10_000.0), (3_000, 10_003.0)]
+        );
+    }
+}
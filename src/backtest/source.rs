@@ -0,0 +1,91 @@
+//! 回测数据的来源：JSON Lines 文件与K线下载流
+
+use crate::{client::okx::history::HistoryItem, data::DataEnum, recorder::read_recording};
+use eyre::{Result, WrapErr};
+use futures_util::{Stream, StreamExt};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// 读取每行一条 [`DataEnum`] 的 JSON Lines 文件，空行被忽略
+///
+/// 以 `.gz` 结尾的文件按 [`read_recorded_events`] 读取。
+pub fn read_events(path: impl AsRef<Path>) -> Result<Vec<DataEnum>> {
+    let path = path.as_ref();
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        return read_recorded_events(path);
+    }
+    let file = File::open(path).wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+    let mut events = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = simd_json::serde::from_slice::<DataEnum>(&mut line.into_bytes())
+            .wrap_err_with(|| format!("Invalid event at {}:{}", path.display(), i + 1))?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// 读取 [`Recorder`](crate::recorder::Recorder) 录制的 `.jsonl.gz` 文件，按交易所时间戳排序，
+/// 时间相同时保持收到的顺序
+pub fn read_recorded_events(path: impl AsRef<Path>) -> Result<Vec<DataEnum>> {
+    let mut events: Vec<_> = read_recording(path)?
+        .into_iter()
+        .map(|event| event.data)
+        .collect();
+    events.sort_by_key(DataEnum::timestamp);
+
+    Ok(events)
+}
+
+/// 将行情写为 JSON Lines 文件，可以由 [`read_events`] 读回
+pub fn write_events<'a>(
+    path: impl AsRef<Path>,
+    events: impl IntoIterator<Item = &'a DataEnum>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let file =
+        File::create(path).wrap_err_with(|| format!("Failed to create {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    for event in events {
+        simd_json::to_writer(&mut writer, event)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// 收集K线流，例如 [`OkxClientV5::history_candles`](crate::client::okx::OkxClientV5::history_candles)
+///
+/// 缺口被忽略，错误会中断收集。结果按时间从旧到新排序。
+pub async fn collect_candles(
+    items: impl Stream<Item = Result<HistoryItem>>,
+) -> Result<Vec<DataEnum>> {
+    let mut items = std::pin::pin!(items);
+
+    let mut events = Vec::new();
+    while let Some(item) = items.next().await {
+        if let Some(candle) = item?.into_candle() {
+            events.push(DataEnum::Candle(candle));
+        }
+    }
+    events.sort_by_key(DataEnum::timestamp);
+
+    Ok(events)
+}
+
+/// 合并多个来源的行情并按时间排序，时间相同时保持来源的先后顺序
+pub fn merge(sources: impl IntoIterator<Item = Vec<DataEnum>>) -> Vec<DataEnum> {
+    let mut events: Vec<_> = sources.into_iter().flatten().collect();
+    events.sort_by_key(DataEnum::timestamp);
+    events
+}
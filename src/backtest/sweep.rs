@@ -0,0 +1,678 @@
+//! 参数优化与前向分析
+
+use super::{Backtest, FillModel};
+use crate::{
+    Timestamp,
+    analytics::{Analyzer, Metric, Performance},
+    conf::{StrategyConfig, SweepConfig},
+    data::DataEnum,
+    strategy::StrategyRegistry,
+};
+use bon::Builder;
+use config::Value;
+use eyre::{ContextCompat, Report, Result, WrapErr};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+/// 一组参数的回测结果
+#[derive(Debug, Clone)]
+pub struct SweepRun {
+    pub params: BTreeMap<String, Value>,
+    pub performance: Performance,
+    /// 按 [`SweepConfig::metric`] 计算的得分，越大越好
+    pub score: f64,
+}
+
+/// 前向分析的一个窗口，时间区间均为左闭右开
+#[derive(Debug, Clone)]
+pub struct WalkForwardWindow {
+    pub in_sample: (Timestamp, Timestamp),
+    pub out_of_sample: (Timestamp, Timestamp),
+    /// 样本内得分最高的参数
+    pub params: BTreeMap<String, Value>,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub performance: Performance,
+}
+
+/// 创建策略失败（例如参数之间不满足约束）而被跳过的参数组合
+#[derive(Debug)]
+pub struct Rejected {
+    pub params: BTreeMap<String, Value>,
+    pub error: Report,
+}
+
+/// 参数优化的结果
+#[derive(Debug)]
+pub struct SweepOutcome {
+    /// 按得分从高到低排序，得分相同时保持组合的顺序
+    pub runs: Vec<SweepRun>,
+    /// 按组合的顺序排列
+    pub rejected: Vec<Rejected>,
+}
+
+/// 按策略配置中的 `sweep` 回测所有参数组合
+///
+/// 每个组合在独立的线程中回测，创建策略失败的组合被跳过并记录在 [`SweepOutcome::rejected`]，
+/// 所有组合都失败时返回错误。
+#[derive(Builder)]
+pub struct Sweep<'a> {
+    #[builder(start_fn)]
+    registry: &'a StrategyRegistry,
+    #[builder(start_fn)]
+    config: &'a StrategyConfig,
+    #[builder(default)]
+    analyzer: Analyzer,
+    /// 默认取 [`SweepConfig::fill_model`]
+    fill_model: Option<FillModel>,
+    #[builder(default = 10_000.0)]
+    initial_cash: f64,
+    /// 并行的线程数，默认为 CPU 核数
+    threads: Option<usize>,
+}
+
+impl Sweep<'_> {
+    fn sweep(&self) -> Result<&SweepConfig> {
+        self.config
+            .sweep
+            .as_ref()
+            .wrap_err_with(|| format!("Strategy '{}' has no sweep config", self.config.name))
+    }
+
+    /// 回放 `events` 回测所有参数组合
+    pub fn run(&self, events: &[DataEnum]) -> Result<SweepOutcome> {
+        let sweep = self.sweep()?;
+        self.run_all(sweep.combinations(), sweep.metric, events)
+    }
+
+    /// 在每个样本内窗口选出得分最高的参数，再用样本外窗口检验
+    ///
+    /// 行情按可见时间（[`DataEnum::available_at`]）划分窗口，
+    /// 收盘时间晚于样本内窗口的K线属于之后的窗口。
+    pub fn walk_forward(&self, mut events: Vec<DataEnum>) -> Result<Vec<WalkForwardWindow>> {
+        let sweep = self.sweep()?;
+        let walk_forward = sweep.walk_forward.wrap_err_with(|| {
+            format!("Strategy '{}' has no walk_forward config", self.config.name)
+        })?;
+        events.sort_by_key(DataEnum::available_at);
+
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(Vec::new());
+        };
+        let (first, last) = (first.available_at(), last.available_at());
+        let in_sample = walk_forward.in_sample.as_millis();
+        let out_of_sample = walk_forward.out_of_sample.as_millis();
+        let slice = |start: Timestamp, end: Timestamp| {
+            let from = events.partition_point(|data| data.available_at() < start);
+            let to = events.partition_point(|data| data.available_at() < end);
+            &events[from..to]
+        };
+
+        let combinations = sweep.combinations();
+        let mut windows = Vec::new();
+        let mut start = first;
+        while start + in_sample <= last {
+            let split = start + in_sample;
+            let end = split + out_of_sample;
+            let (train, test) = (slice(start, split), slice(split, end));
+            start += out_of_sample;
+            if train.is_empty() || test.is_empty() {
+                continue;
+            }
+
+            let best = self
+                .run_all(combinations.clone(), sweep.metric, train)?
+                .runs
+                .into_iter()
+                .next()
+                .wrap_err("No valid parameter combination")?;
+            let test = self
+                .run_all(vec![best.params], sweep.metric, test)?
+                .runs
+                .into_iter()
+                .next()
+                .wrap_err("No valid parameter combination")?;
+
+            windows.push(WalkForwardWindow {
+                in_sample: (split - in_sample, split),
+                out_of_sample: (split, end),
+                params: test.params,
+                in_sample_score: best.score,
+                out_of_sample_score: test.score,
+                performance: test.performance,
+            });
+        }
+
+        Ok(windows)
+    }
+
+    fn run_all(
+        &self,
+        combinations: Vec<BTreeMap<String, Value>>,
+        metric: Metric,
+        events: &[DataEnum],
+    ) -> Result<SweepOutcome> {
+        let threads = self
+            .threads
+            .or_else(|| thread::available_parallelism().ok().map(Into::into))
+            .unwrap_or(1)
+            .clamp(1, combinations.len().max(1));
+        let next = AtomicUsize::new(0);
+        let fill_model = self
+            .fill_model
+            .or_else(|| self.config.sweep.as_ref().map(|sweep| sweep.fill_model))
+            .unwrap_or_default();
+
+        let results = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    // 每个组合的序号与结果，创建策略失败时为 `Err`
+                    scope.spawn(|| -> Result<Vec<(usize, Result<SweepRun, Rejected>)>> {
+                        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+                        let mut runs = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(params) = combinations.get(index) else {
+                                break;
+                            };
+                            let config = self.config.with_params(params);
+                            let strategy = match self.registry.create(&config) {
+                                Ok(strategy) => strategy,
+                                Err(error) => {
+                                    let params = params.clone();
+                                    runs.push((index, Err(Rejected { params, error })));
+                                    continue;
+                                }
+                            };
+                            let report = runtime.block_on(
+                                Backtest::builder(config.name, strategy)
+                                    .initial_cash(self.initial_cash)
+                                    .fill_model(fill_model)
+                                    .build()
+                                    .run(events.to_vec()),
+                            )?;
+                            let performance = report.performance(&self.analyzer);
+                            let run = SweepRun {
+                                params: params.clone(),
+                                score: metric.score(&performance),
+                                performance,
+                            };
+                            runs.push((index, Ok(run)));
+                        }
+                        Ok(runs)
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("sweep worker panicked"))
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        let mut results: Vec<_> = results.into_iter().flatten().collect();
+        results.sort_by_key(|&(index, _)| index);
+        let (mut runs, mut rejected) = (Vec::new(), Vec::new());
+        for (_, result) in results {
+            match result {
+                Ok(run) => runs.push(run),
+                Err(err) => rejected.push(err),
+            }
+        }
+        // 所有组合都失败时多半是配置错误，返回第一个组合的错误
+        if runs.is_empty() && !rejected.is_empty() {
+            let count = rejected.len();
+            return Err(rejected.swap_remove(0).error.wrap_err(format!(
+                "All {count} parameter combinations of strategy '{}' were rejected",
+                self.config.name
+            )));
+        }
+        // 稳定排序，得分相同时保持组合的顺序，与线程的调度无关
+        runs.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Ok(SweepOutcome { runs, rejected })
+    }
+}
+
+/// 将参数优化的结果写为 CSV，每行一组参数
+pub fn write_sweep_csv(path: impl AsRef<Path>, runs: &[SweepRun]) -> Result<()> {
+    let params: Vec<_> = runs
+        .first()
+        .map(|run| run.params.keys().cloned().collect())
+        .unwrap_or_default();
+
+    write_csv(
+        path,
+        ["rank", "score"]
+            .into_iter()
+            .map(str::to_owned)
+            .chain(params.iter().cloned())
+            .chain(SUMMARY_COLUMNS.iter().map(|&column| column.to_owned())),
+        runs.iter().enumerate().map(|(i, run)| {
+            [(i + 1).to_string(), format_number(Some(run.score))]
+                .into_iter()
+                .chain(params.iter().map(|key| param(&run.params, key)))
+                .chain(summary(&run.performance))
+                .collect()
+        }),
+    )
+}
+
+/// 将前向分析的结果写为 CSV，每行一个窗口
+pub fn write_walk_forward_csv(path: impl AsRef<Path>, windows: &[WalkForwardWindow]) -> Result<()> {
+    let params: Vec<_> = windows
+        .first()
+        .map(|window| window.params.keys().cloned().collect())
+        .unwrap_or_default();
+
+    write_csv(
+        path,
+        [
+            "in_sample_start",
+            "out_of_sample_start",
+            "out_of_sample_end",
+            "in_sample_score",
+            "out_of_sample_score",
+        ]
+        .into_iter()
+        .map(str::to_owned)
+        .chain(params.iter().cloned())
+        .chain(SUMMARY_COLUMNS.iter().map(|&column| column.to_owned())),
+        windows.iter().map(|window| {
+            [
+                window.in_sample.0.to_string(),
+                window.out_of_sample.0.to_string(),
+                window.out_of_sample.1.to_string(),
+                format_number(Some(window.in_sample_score)),
+                format_number(Some(window.out_of_sample_score)),
+            ]
+            .into_iter()
+            .chain(params.iter().map(|key| param(&window.params, key)))
+            .chain(summary(&window.performance))
+            .collect()
+        }),
+    )
+}
+
+const SUMMARY_COLUMNS: [&str; 8] = [
+    "total_return",
+    "annualized_return",
+    "sharpe_ratio",
+    "sortino_ratio",
+    "max_drawdown",
+    "round_trips",
+    "win_rate",
+    "profit_factor",
+];
+
+fn summary(performance: &Performance) -> [String; 8] {
+    [
+        format_number(Some(performance.total_return)),
+        format_number(performance.annualized_return),
+        format_number(performance.sharpe_ratio),
+        format_number(performance.sortino_ratio),
+        format_number(Some(performance.max_drawdown)),
+        performance.round_trips.to_string(),
+        format_number(performance.win_rate),
+        format_number(performance.profit_factor),
+    ]
+}
+
+/// 无法计算的值留空
+fn format_number(value: Option<f64>) -> String {
+    value
+        .filter(|value| value.is_finite())
+        .map_or_else(String::new, |value| format!("{value:.6}"))
+}
+
+fn param(params: &BTreeMap<String, Value>, key: &str) -> String {
+    params
+        .get(key)
+        .map_or_else(String::new, ToString::to_string)
+}
+
+fn write_csv(
+    path: impl AsRef<Path>,
+    header: impl IntoIterator<Item = String>,
+    rows: impl IntoIterator<Item = Vec<String>>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let file =
+        File::create(path).wrap_err_with(|| format!("Failed to create {}", path.display()))?;
+
+    let mut writer = BufWriter::new(file);
+    for row in std::iter::once(header.into_iter().collect()).chain(rows) {
+        let row: Vec<_> = row.iter().map(|field: &String| escape(field)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backtest::FeeSchedule,
+        data::{CandleData, CandleInterval},
+        strategy::{Context, Strategy},
+    };
+    use std::sync::{Arc, Mutex};
+
+    const MINUTE: Timestamp = 60_000;
+
+    /// `sweep` 为 YAML 中 `sweep:` 之下的内容
+    fn strategy(kind: &str, sweep: &str) -> StrategyConfig {
+        let yaml = format!(
+            r#"
+name: "test"
+type: "{kind}"
+data_source: []
+params:
+  max_retries: 0
+  retry_delay: 1
+  timeout: 1
+  backoff_factor: 1.0
+  jitter: false
+  quantity: 1
+sweep:
+{sweep}
+"#
+        );
+        config::Config::builder()
+            .add_source(config::File::from_str(&yaml, config::FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    fn candles(count: usize) -> Vec<DataEnum> {
+        (0..count)
+            .map(|i| {
+                // 周期约 19 根K线的正弦波
+                let close = format!("{:.2}", 100.0 + 10.0 * (i as f64 / 3.0).sin());
+                let close = close.parse().unwrap();
+                DataEnum::Candle(CandleData {
+                    symbol: "BTC-USDT".into(),
+                    interval: CandleInterval::M1,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    volume: "1".parse().unwrap(),
+                    timestamp: i as Timestamp * MINUTE,
+                })
+            })
+            .collect()
+    }
+
+    fn values(runs: &[SweepRun], key: &str) -> Vec<String> {
+        runs.iter().map(|run| param(&run.params, key)).collect()
+    }
+
+    #[test]
+    fn combines_every_param_value() {
+        let config = strategy(
+            "default",
+            r#"
+  params:
+    fast: [2, 3]
+    slow: { start: 4, end: 8, step: 2 }
+"#,
+        );
+        let combinations = config.sweep.unwrap().combinations();
+
+        let pairs: Vec<_> = combinations
+            .iter()
+            .map(|params| format!("{}/{}", params["fast"], params["slow"]))
+            .collect();
+        assert_eq!(pairs, ["2/4", "2/6", "2/8", "3/4", "3/6", "3/8"]);
+    }
+
+    #[test]
+    fn ranks_runs_and_reports_rejected_params() {
+        let registry = StrategyRegistry::builtin();
+        let config = strategy(
+            "default",
+            r#"
+  metric: total_return
+  params:
+    fast: [2, 3, 4]
+    slow: [3, 5, 8, 80]
+"#,
+        );
+        let fill_model = FillModel {
+            fees: FeeSchedule::ZERO,
+            ..Default::default()
+        };
+        let run = |threads| {
+            Sweep::builder(&registry, &config)
+                .fill_model(fill_model)
+                .threads(threads)
+                .build()
+                .run(&candles(60))
+                .unwrap()
+        };
+        let outcome = run(4);
+
+        // fast 必须小于 slow
+        let rejected: Vec<_> = outcome
+            .rejected
+            .iter()
+            .map(|rejected| format!("{}/{}", rejected.params["fast"], rejected.params["slow"]))
+            .collect();
+        assert_eq!(rejected, ["3/3", "4/3"]);
+        assert_eq!(outcome.runs.len(), 10);
+
+        for pair in outcome.runs.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+        for run in &outcome.runs {
+            assert_eq!(run.score, run.performance.total_return);
+        }
+        assert!(outcome.runs[0].score > 0.0);
+        // 慢线周期超过K线根数时不会交易，得分同为 0，按组合的顺序排列
+        let idle: Vec<_> = outcome
+            .runs
+            .iter()
+            .filter(|run| run.performance.fills == 0)
+            .map(|run| format!("{}/{}", run.params["fast"], run.params["slow"]))
+            .collect();
+        assert_eq!(idle, ["2/80", "3/80", "4/80"]);
+
+        // 排名与线程数无关
+        let single = run(1);
+        assert_eq!(values(&single.runs, "fast"), values(&outcome.runs, "fast"));
+        assert_eq!(values(&single.runs, "slow"), values(&outcome.runs, "slow"));
+    }
+
+    #[test]
+    fn fails_when_every_combination_is_rejected() {
+        let registry = StrategyRegistry::builtin();
+        let config = strategy(
+            "default",
+            r#"
+  params:
+    fast: [5, 8]
+    slow: [3]
+"#,
+        );
+        let err = Sweep::builder(&registry, &config)
+            .build()
+            .run(&candles(10))
+            .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("All 2 parameter combinations of strategy 'test' were rejected"),
+            "{err:#}"
+        );
+        assert!(format!("{err:#}").contains("params.fast"), "{err:#}");
+    }
+
+    type Seen = Arc<Mutex<Vec<Vec<Timestamp>>>>;
+
+    /// 记录每个实例收到的K线的可见时间
+    struct Probe {
+        seen: Seen,
+        index: usize,
+    }
+
+    impl Strategy for Probe {
+        fn on_candle(&mut self, ctx: &mut Context, _candle: &CandleData) -> Result<()> {
+            self.seen.lock().unwrap()[self.index].push(ctx.now());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn walk_forward_keeps_out_of_sample_data_out_of_the_fit() {
+        let seen = Seen::default();
+        let mut registry = StrategyRegistry::new();
+        let instances = seen.clone();
+        registry.register("probe", move |_| {
+            let mut seen = instances.lock().unwrap();
+            seen.push(Vec::new());
+            Ok(Box::new(Probe {
+                seen: instances.clone(),
+                index: seen.len() - 1,
+            }))
+        });
+        // 样本内窗口不是K线周期的整数倍
+        let config = strategy(
+            "probe",
+            r#"
+  params:
+    n: [1, 2]
+  walk_forward: { in_sample: 150, out_of_sample: 120 }
+"#,
+        );
+        let windows = Sweep::builder(&registry, &config)
+            .threads(1)
+            .build()
+            .walk_forward(candles(10))
+            .unwrap();
+
+        // K线的可见时间为 1..=10 分钟，窗口从第一根K线收盘开始，每次滚动 2 分钟
+        let bounds: Vec<_> = windows
+            .iter()
+            .map(|window| (window.in_sample, window.out_of_sample))
+            .collect();
+        let second = 1_000;
+        assert_eq!(
+            bounds,
+            [
+                ((60 * second, 210 * second), (210 * second, 330 * second)),
+                ((180 * second, 330 * second), (330 * second, 450 * second)),
+                ((300 * second, 450 * second), (450 * second, 570 * second)),
+                ((420 * second, 570 * second), (570 * second, 690 * second)),
+            ]
+        );
+
+        // 单线程时每个窗口依次创建两个样本内实例与一个样本外实例
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), windows.len() * 3);
+        for (window, seen) in windows.iter().zip(seen.chunks(3)) {
+            let (start, split) = window.in_sample;
+            for fit in &seen[..2] {
+                assert!(!fit.is_empty());
+                assert!(
+                    fit.iter().all(|&now| start <= now && now < split),
+                    "{fit:?}"
+                );
+            }
+            let (split, end) = window.out_of_sample;
+            assert!(seen[2].iter().all(|&now| split <= now && now < end));
+            // 得分相同时取第一个组合
+            assert_eq!(param(&window.params, "n"), "1");
+        }
+        assert_eq!(seen[0], [MINUTE, 2 * MINUTE, 3 * MINUTE]);
+        assert_eq!(seen[2], [4 * MINUTE, 5 * MINUTE]);
+    }
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("squant-sweep-{}.csv", uuid::Uuid::new_v4()))
+    }
+
+    fn read(path: &Path) -> Vec<String> {
+        let content = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        content.lines().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn writes_csv() {
+        let params = |fast: i64, label: &str| {
+            BTreeMap::from([
+                ("fast".to_owned(), Value::from(fast)),
+                ("label".to_owned(), Value::from(label)),
+            ])
+        };
+        let performance = Performance {
+            total_return: 0.1,
+            sharpe_ratio: Some(1.5),
+            max_drawdown: 0.05,
+            round_trips: 3,
+            win_rate: Some(2.0 / 3.0),
+            ..Default::default()
+        };
+        let runs = [
+            SweepRun {
+                params: params(2, "a,\"b\""),
+                performance,
+                score: 1.5,
+            },
+            SweepRun {
+                params: params(3, "c"),
+                performance: Performance::default(),
+                score: f64::NEG_INFINITY,
+            },
+        ];
+        let path = temp_path();
+        write_sweep_csv(&path, &runs).unwrap();
+        assert_eq!(
+            read(&path),
+            [
+                "rank,score,fast,label,total_return,annualized_return,sharpe_ratio,\
+                 sortino_ratio,max_drawdown,round_trips,win_rate,profit_factor",
+                r#"1,1.500000,2,"a,""b""",0.100000,,1.500000,,0.050000,3,0.666667,"#,
+                "2,,3,c,0.000000,,,,0.000000,0,,",
+            ]
+        );
+
+        let window = WalkForwardWindow {
+            in_sample: (0, 100),
+            out_of_sample: (100, 150),
+            params: params(2, "a"),
+            in_sample_score: 2.0,
+            out_of_sample_score: -0.5,
+            performance,
+        };
+        write_walk_forward_csv(&path, &[window]).unwrap();
+        assert_eq!(
+            read(&path),
+            [
+                "in_sample_start,out_of_sample_start,out_of_sample_end,in_sample_score,\
+                 out_of_sample_score,fast,label,total_return,annualized_return,sharpe_ratio,\
+                 sortino_ratio,max_drawdown,round_trips,win_rate,profit_factor",
+                "0,100,150,2.000000,-0.500000,2,a,0.100000,,1.500000,,0.050000,3,0.666667,",
+            ]
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! 参数优化：`sweep [--config <path>]... <strategy> <events.jsonl | recording.jsonl.gz>...`
+//!
+//! 按配置文件（默认为 conf.yaml，多个文件按顺序叠加）中该策略的 `sweep` 回测所有参数组合，
+//! 撮合模型取 `sweep.fill_model`，结果写入 `sweep-<strategy>.csv`；
+//! 配置了 `walk_forward` 时再做前向分析，结果写入 `walk-forward-<strategy>.csv`。
+
+use config::Value;
+use eyre::{ContextCompat, Result, bail};
+use squant::{
+    analytics::Analyzer,
+    backtest::{
+        source::{merge, read_events},
+        sweep::{Rejected, Sweep, SweepOutcome, write_sweep_csv, write_walk_forward_csv},
+    },
+    conf::AppConfig,
+    strategy::StrategyRegistry,
+};
+use std::{collections::BTreeMap, time::Duration};
+
+/// 终端中显示的结果数量
+const TOP: usize = 10;
+
+fn main() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    let mut configs = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-c" | "--config" => configs.push(args.next().wrap_err("--config requires a path")?),
+            _ => positional.push(arg),
+        }
+    }
+    if configs.is_empty() {
+        configs.push("conf.yaml".to_owned());
+    }
+    let mut positional = positional.into_iter();
+    let Some(name) = positional.next() else {
+        bail!("Usage: sweep [--config <path>]... <strategy> <events.jsonl>...");
+    };
+    let paths: Vec<_> = positional.collect();
+    if paths.is_empty() {
+        bail!("At least one event file is required");
+    }
+
+    let config = AppConfig::load(&configs)?;
+    let strategy = config
+        .strategy(&name)
+        .wrap_err_with(|| format!("Unknown strategy '{name}'"))?;
+    let events = merge(paths.iter().map(read_events).collect::<Result<Vec<_>>>()?);
+
+    let registry = StrategyRegistry::builtin();
+    // 样本外窗口可能只有一天，按小时采样才能计算夏普等比率
+    let sweep = Sweep::builder(&registry, strategy)
+        .analyzer(
+            Analyzer::builder()
+                .period(Duration::from_secs(3600))
+                .build(),
+        )
+        .build();
+
+    let SweepOutcome { runs, rejected } = sweep.run(&events)?;
+    let path = format!("sweep-{name}.csv");
+    write_sweep_csv(&path, &runs)?;
+    println!("{} combinations, results written to {path}", runs.len());
+    for (i, run) in runs.iter().take(TOP).enumerate() {
+        println!(
+            "{:>3}. {:>10.4}  {}",
+            i + 1,
+            run.score,
+            format_params(&run.params)
+        );
+    }
+    for Rejected { params, error } in &rejected {
+        println!("Skipped {}: {error:#}", format_params(params));
+    }
+
+    if strategy
+        .sweep
+        .as_ref()
+        .is_some_and(|sweep| sweep.walk_forward.is_some())
+    {
+        let windows = sweep.walk_forward(events)?;
+        let path = format!("walk-forward-{name}.csv");
+        write_walk_forward_csv(&path, &windows)?;
+        println!(
+            "{} walk-forward windows, results written to {path}",
+            windows.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn format_params(params: &BTreeMap<String, Value>) -> String {
+    let params: Vec<_> = params
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    params.join(" ")
+}
@@ -0,0 +1,157 @@
+use crate::{
+    Timestamp,
+    data::{BookAction, BookData},
+    decimal::{Price, Quantity},
+    order::Side,
+};
+use bytestring::ByteString;
+use std::{cmp::Reverse, collections::BTreeMap};
+
+/// 订单簿中的一个价格档位
+#[derive(Debug, Clone)]
+pub struct BookLevel {
+    /// 保留交易所推送的原始精度，校验和需要使用原始文本计算
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+/// 本地维护的 L2 订单簿
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    symbol: ByteString,
+    bids: BTreeMap<Reverse<Price>, BookLevel>,
+    asks: BTreeMap<Price, BookLevel>,
+    /// 最近一次更新的序列号，`None` 表示尚未收到全量快照
+    sequence: Option<i64>,
+    timestamp: Timestamp,
+}
+
+impl OrderBook {
+    pub fn new(symbol: impl Into<ByteString>) -> Self {
+        Self {
+            symbol: symbol.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn symbol(&self) -> &ByteString {
+        &self.symbol
+    }
+
+    /// 清空所有档位，等待新的全量快照
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.sequence = None;
+    }
+
+    /// 更新一个档位，数量为 0 时删除该档位
+    pub fn update_level(&mut self, side: Side, level: BookLevel) {
+        let key = level.price;
+        match side {
+            Side::Buy if level.quantity.is_zero() => {
+                self.bids.remove(&Reverse(key));
+            }
+            Side::Buy => {
+                self.bids.insert(Reverse(key), level);
+            }
+            Side::Sell if level.quantity.is_zero() => {
+                self.asks.remove(&key);
+            }
+            Side::Sell => {
+                self.asks.insert(key, level);
+            }
+        }
+    }
+
+    /// 应用一次标准化的深度推送，快照会替换所有档位
+    pub fn apply(&mut self, data: &BookData) {
+        if data.action == BookAction::Snapshot {
+            self.clear();
+        }
+        for (side, levels) in [(Side::Buy, &data.bids), (Side::Sell, &data.asks)] {
+            for &(price, quantity) in levels {
+                self.update_level(side, BookLevel { price, quantity });
+            }
+        }
+        self.set_timestamp(data.timestamp);
+    }
+
+    pub fn sequence(&self) -> Option<i64> {
+        self.sequence
+    }
+
+    pub fn set_sequence(&mut self, sequence: i64) {
+        self.sequence = Some(sequence);
+    }
+
+    pub fn timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: Timestamp) {
+        self.timestamp = timestamp;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// 买盘，从最优价开始
+    pub fn bids(&self) -> impl Iterator<Item = &BookLevel> {
+        self.bids.values()
+    }
+
+    /// 卖盘，从最优价开始
+    pub fn asks(&self) -> impl Iterator<Item = &BookLevel> {
+        self.asks.values()
+    }
+
+    pub fn best_bid(&self) -> Option<&BookLevel> {
+        self.bids.values().next()
+    }
+
+    pub fn best_ask(&self) -> Option<&BookLevel> {
+        self.asks.values().next()
+    }
+
+    pub fn spread(&self) -> Option<Price> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    /// 中间价 (bid + ask) / 2
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.best_bid()?.price + self.best_ask()?.price).to_f64() / 2.0)
+    }
+
+    /// 按最优档挂单量加权的微观价格
+    /// (bid * ask_qty + ask * bid_qty) / (bid_qty + ask_qty)
+    pub fn microprice(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        let total = bid.quantity + ask.quantity;
+        if total.is_zero() {
+            return None;
+        }
+
+        Some((bid.price * ask.quantity + ask.price * bid.quantity).to_f64() / total.to_f64())
+    }
+
+    /// 前 `depth` 档的快照
+    pub fn depth(&self, depth: usize) -> BookData {
+        let levels = |levels: &mut dyn Iterator<Item = &BookLevel>| {
+            levels
+                .take(depth)
+                .map(|level| (level.price, level.quantity))
+                .collect()
+        };
+
+        BookData {
+            symbol: self.symbol.clone(),
+            action: BookAction::Snapshot,
+            bids: levels(&mut self.bids()),
+            asks: levels(&mut self.asks()),
+            timestamp: self.timestamp,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use serde::Deserialize;
+use serde_with::{DurationSecondsWithFrac, serde_as};
+use std::time::Duration;
+
+/// 指数退避参数，字段与 conf.yaml 中策略的 `params` 一致
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Backoff {
+    /// 最大重试次数
+    pub max_retries: usize,
+    /// 首次重试前的等待时间（秒）
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub retry_delay: Duration,
+    /// 每次重试等待时间的放大倍数
+    pub backoff_factor: f64,
+    /// 是否在等待时间上加入 ±50% 的随机抖动
+    pub jitter: bool,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            retry_delay: Duration::from_secs(1),
+            backoff_factor: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl Backoff {
+    /// 第 `attempt` 次重试（从 0 开始）前的等待时间，超过最大重试次数时返回 `None`
+    pub fn delay(&self, attempt: usize) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        let exp = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let mut delay = self.retry_delay.as_secs_f64() * self.backoff_factor.powi(exp);
+        if self.jitter {
+            delay *= rand::random_range(0.5..1.5);
+        }
+
+        Some(Duration::try_from_secs_f64(delay).unwrap_or(Duration::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_exponentially_until_max_retries() {
+        let backoff = Backoff {
+            max_retries: 3,
+            retry_delay: Duration::from_millis(100),
+            backoff_factor: 2.0,
+            jitter: false,
+        };
+        let delays: Vec<_> = (0..5).map(|attempt| backoff.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(100)),
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                None,
+                None
+            ]
+        );
+
+        // 等待时间溢出时取最大值
+        let backoff = Backoff {
+            max_retries: usize::MAX,
+            backoff_factor: 1e10,
+            ..backoff
+        };
+        assert_eq!(backoff.delay(1_000), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn jitters_within_half_of_the_delay() {
+        let backoff = Backoff {
+            max_retries: 10,
+            retry_delay: Duration::from_secs(1),
+            backoff_factor: 2.0,
+            jitter: true,
+        };
+        for _ in 0..100 {
+            let delay = backoff.delay(1).unwrap();
+            assert!(
+                (Duration::from_secs(1)..Duration::from_secs(3)).contains(&delay),
+                "{delay:?}"
+            );
+        }
+    }
+}
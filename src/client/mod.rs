@@ -1,8 +1,12 @@
+use crate::order::{AmendOrder, CancelOrder, NewOrder, OrderAck};
 use eyre::Result;
 use futures_util::StreamExt;
 use std::future::Future;
 
+pub mod backoff;
 pub mod okx;
+pub mod rate_limit;
+pub mod retry;
 
 // 通常你需要为每个Request和未标准化的交易所数据实现该trait
 pub trait RawData {
@@ -25,3 +29,49 @@ pub trait DataSubscriber<D: DataResponse + RawData> {
         params: D::Request,
     ) -> impl Future<Output = Result<impl StreamExt<Item = D::Data> + Send>> + Send;
 }
+
+/// 与交易所无关的下单接口
+pub trait OrderExecutor {
+    fn place_order(&mut self, order: NewOrder) -> impl Future<Output = Result<OrderAck>> + Send;
+
+    fn cancel_order(
+        &mut self,
+        cancel: CancelOrder,
+    ) -> impl Future<Output = Result<OrderAck>> + Send;
+
+    fn amend_order(&mut self, amend: AmendOrder) -> impl Future<Output = Result<OrderAck>> + Send;
+
+    /// 批量下单，返回与 `orders` 一一对应的结果
+    fn place_orders(
+        &mut self,
+        orders: Vec<NewOrder>,
+    ) -> impl Future<Output = Vec<Result<OrderAck>>> + Send
+    where
+        Self: Send,
+    {
+        async move {
+            let mut results = Vec::with_capacity(orders.len());
+            for order in orders {
+                results.push(self.place_order(order).await);
+            }
+            results
+        }
+    }
+
+    /// 批量撤单，返回与 `cancels` 一一对应的结果
+    fn cancel_orders(
+        &mut self,
+        cancels: Vec<CancelOrder>,
+    ) -> impl Future<Output = Vec<Result<OrderAck>>> + Send
+    where
+        Self: Send,
+    {
+        async move {
+            let mut results = Vec::with_capacity(cancels.len());
+            for cancel in cancels {
+                results.push(self.cancel_order(cancel).await);
+            }
+            results
+        }
+    }
+}
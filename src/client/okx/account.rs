@@ -0,0 +1,101 @@
+use super::{OkxClientV5, model::*, ws::parse_data};
+use crate::{
+    client::{DataGetter, DataResponse, DataSubscriber, RawData},
+    data::{Balance, Position},
+};
+use bytestring::ByteString;
+use eyre::Result;
+use futures_util::StreamExt;
+use itertools::Itertools;
+
+const BALANCE_PATH: &str = "api/v5/account/balance";
+const POSITIONS_PATH: &str = "api/v5/account/positions";
+
+/// 解析 account 频道的推送，展开为各币种的余额
+fn parse_balances(text: ByteString) -> Result<Vec<Balance>> {
+    let resp = simd_json::serde::from_slice::<OkxWebSocketDataResponse<OkxBalanceData>>(
+        &mut text.as_bytes().to_vec(),
+    )?;
+
+    resp.data
+        .into_iter()
+        .map(OkxBalanceData::into_balances)
+        .flatten_ok()
+        .collect()
+}
+
+impl DataGetter<OkxHttpResponse<OkxHttpBalanceRequest>> for OkxClientV5 {
+    async fn get_data(
+        &mut self,
+        params: <OkxHttpResponse<OkxHttpBalanceRequest> as DataResponse>::Request,
+    ) -> Result<<OkxHttpResponse<OkxHttpBalanceRequest> as RawData>::Data> {
+        let ccy = params.ccy.map(|ccy| ccy.iter().join(","));
+        let query = ccy
+            .as_deref()
+            .map(|ccy| vec![("ccy", ccy)])
+            .unwrap_or_default();
+
+        let resp = self
+            .get_private::<OkxBalanceData>(BALANCE_PATH, &query)
+            .await?;
+
+        resp.data
+            .into_iter()
+            .map(OkxBalanceData::into_balances)
+            .flatten_ok()
+            .collect()
+    }
+}
+
+impl DataGetter<OkxHttpResponse<OkxHttpPositionsRequest>> for OkxClientV5 {
+    async fn get_data(
+        &mut self,
+        params: <OkxHttpResponse<OkxHttpPositionsRequest> as DataResponse>::Request,
+    ) -> Result<<OkxHttpResponse<OkxHttpPositionsRequest> as RawData>::Data> {
+        let OkxHttpPositionsRequest {
+            inst_type,
+            inst_id,
+            pos_id,
+        } = &params;
+
+        let mut query = Vec::with_capacity(3);
+        if let Some(inst_type) = inst_type {
+            query.push(("instType", <&str>::from(inst_type)));
+        }
+        if let Some(inst_id) = inst_id {
+            query.push(("instId", inst_id.as_ref()));
+        }
+        if let Some(pos_id) = pos_id {
+            query.push(("posId", pos_id.as_ref()));
+        }
+
+        let resp = self
+            .get_private::<OkxPositionData>(POSITIONS_PATH, &query)
+            .await?;
+
+        resp.data.into_iter().map(Position::try_from).collect()
+    }
+}
+
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxBalanceData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxBalanceData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxBalanceData> as RawData>::Data> + Send,
+    > {
+        self.subscribe_channels(params, parse_balances).await
+    }
+}
+
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxPositionData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxPositionData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxPositionData> as RawData>::Data> + Send,
+    > {
+        self.subscribe_channels(params, parse_data::<OkxPositionData>)
+            .await
+    }
+}
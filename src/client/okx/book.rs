@@ -0,0 +1,341 @@
+use super::ws::{Frame, OkxWsConnection, OkxWsEndpoint};
+use super::{OkxClientV5, model::*};
+use crate::{
+    book::{BookLevel, OrderBook},
+    data::{BookAction, BookData, StreamEvent},
+    order::Side,
+};
+use bytestring::ByteString;
+use eyre::{Context, Result, bail};
+use futures_util::{Stream, StreamExt};
+use std::fmt::Write;
+
+/// 校验和使用的档位数
+const OKX_CHECKSUM_DEPTH: usize = 25;
+
+/// 按 OKX 规则计算订单簿前 25 档的 CRC32 校验和
+///
+/// 将买卖盘交替拼接为 `bid1px:bid1sz:ask1px:ask1sz:bid2px:...`，
+/// 某一侧档位不足时直接跳过该侧。
+pub fn okx_book_checksum(book: &OrderBook) -> i32 {
+    crc32fast::hash(okx_checksum_string(book).as_bytes()) as i32
+}
+
+fn okx_checksum_string(book: &OrderBook) -> String {
+    let mut bids = book.bids().take(OKX_CHECKSUM_DEPTH);
+    let mut asks = book.asks().take(OKX_CHECKSUM_DEPTH);
+    let mut buf = String::with_capacity(OKX_CHECKSUM_DEPTH * 2 * 24);
+
+    loop {
+        let (bid, ask) = (bids.next(), asks.next());
+        if bid.is_none() && ask.is_none() {
+            break;
+        }
+        for level in [bid, ask].into_iter().flatten() {
+            if !buf.is_empty() {
+                buf.push(':');
+            }
+            let _ = write!(buf, "{}:{}", level.price, level.quantity);
+        }
+    }
+
+    buf
+}
+
+impl OrderBook {
+    /// 应用 books 频道的一次推送。
+    ///
+    /// 返回 `Ok(false)` 表示尚未收到全量快照，增量被忽略；
+    /// 序列号不连续或校验和不一致时返回错误，此时需要重新订阅。
+    pub fn apply_okx(&mut self, action: BookAction, data: &OkxBookData) -> Result<bool> {
+        match action {
+            BookAction::Snapshot => self.clear(),
+            BookAction::Update => match (self.sequence(), data.prev_seq_id) {
+                (None, _) => return Ok(false),
+                (Some(seq_id), Some(prev_seq_id)) if seq_id != prev_seq_id => {
+                    bail!("Order book sequence gap: expected prevSeqId {seq_id}, got {prev_seq_id}")
+                }
+                _ => {}
+            },
+        }
+
+        for (side, levels) in [(Side::Buy, &data.bids), (Side::Sell, &data.asks)] {
+            for (price, quantity, _, _) in levels {
+                self.update_level(side, okx_book_level(price, quantity)?);
+            }
+        }
+
+        self.set_timestamp(data.ts.parse().wrap_err("Failed to parse book timestamp")?);
+        if let Some(seq_id) = data.seq_id {
+            self.set_sequence(seq_id);
+        }
+
+        if let Some(expected) = data.checksum {
+            let actual = okx_book_checksum(self);
+            if actual != expected {
+                bail!("Order book checksum mismatch: expected {expected}, got {actual}");
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+fn okx_book_level(price: &ByteString, quantity: &ByteString) -> Result<BookLevel> {
+    Ok(BookLevel {
+        price: price
+            .parse()
+            .wrap_err_with(|| format!("Failed to parse book price: '{price}'"))?,
+        quantity: quantity
+            .parse()
+            .wrap_err_with(|| format!("Failed to parse book size: '{quantity}'"))?,
+    })
+}
+
+struct OrderBookSubscription {
+    conn: OkxWsConnection,
+    inst_id: ByteString,
+    arg: OkxArg,
+    book: OrderBook,
+    depth: usize,
+}
+
+impl OrderBookSubscription {
+    fn handle_frame(&mut self, frame: Frame) -> Result<Option<StreamEvent<BookData>>> {
+        let text = match frame? {
+            StreamEvent::Data(text) => text,
+            // 断线期间的增量已丢失，重连后服务端会重新推送全量快照
+            StreamEvent::Disconnected => {
+                self.book.clear();
+                return Ok(Some(StreamEvent::Disconnected));
+            }
+            StreamEvent::Reconnected => return Ok(Some(StreamEvent::Reconnected)),
+        };
+        let resp = simd_json::serde::from_slice::<OkxWebSocketDataResponse<OkxBookData>>(
+            &mut text.as_bytes().to_vec(),
+        )?;
+
+        let action = resp.action.unwrap_or(BookAction::Update);
+        let mut applied = false;
+        for data in &resp.data {
+            match self.book.apply_okx(action, data) {
+                Ok(ok) => applied |= ok,
+                Err(err) => {
+                    // 取消订阅后重新订阅，服务端会重新推送全量快照
+                    self.book.clear();
+                    self.conn.resubscribe(self.arg.clone())?;
+                    return Err(err.wrap_err(format!("Resubscribing to {}", self.inst_id)));
+                }
+            }
+        }
+
+        Ok(applied.then(|| StreamEvent::Data(self.book.depth(self.depth))))
+    }
+}
+
+impl OkxClientV5 {
+    /// 订阅 books 频道并在本地维护订单簿，每次更新后产出前 `depth` 档的快照。
+    ///
+    /// 每次推送都会校验序列号与校验和，不一致时自动重新订阅以获取新的全量快照，
+    /// 并在流中产出一个错误。
+    pub async fn subscribe_order_book(
+        &mut self,
+        inst_id: impl Into<ByteString>,
+        depth: usize,
+    ) -> Result<impl Stream<Item = Result<StreamEvent<BookData>>> + Send> {
+        let conn = self.ws_connection(OkxWsEndpoint::Public).await?;
+        let inst_id = inst_id.into();
+        let arg = OkxArg::new(OkxChannel::Books, inst_id.clone());
+        let frames = conn.subscribe_raw(arg.clone())?.into_stream();
+
+        let mut subscription = OrderBookSubscription {
+            conn,
+            book: OrderBook::new(inst_id.clone()),
+            inst_id,
+            arg,
+            depth,
+        };
+
+        Ok(frames.filter_map(move |frame| {
+            std::future::ready(subscription.handle_frame(frame).transpose())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::okx::ws::Command;
+
+    fn okx_book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> OrderBook {
+        let mut book = OrderBook::new("BTC-USDT");
+        for (side, levels) in [(Side::Buy, bids), (Side::Sell, asks)] {
+            for (price, quantity) in levels {
+                book.update_level(
+                    side,
+                    okx_book_level(&(*price).into(), &(*quantity).into()).unwrap(),
+                );
+            }
+        }
+        book
+    }
+
+    fn frame(action: &str, data: &str) -> Frame {
+        Ok(StreamEvent::Data(
+            format!(
+                r#"{{"arg":{{"channel":"books","instId":"BTC-USDT"}},"action":"{action}","data":[{data}]}}"#
+            )
+            .into(),
+        ))
+    }
+
+    fn subscription() -> (OrderBookSubscription, flume::Receiver<Command>) {
+        let (conn, commands) = OkxWsConnection::detached(OkxWsEndpoint::Public);
+        let subscription = OrderBookSubscription {
+            conn,
+            inst_id: "BTC-USDT".into(),
+            arg: OkxArg::new(OkxChannel::Books, "BTC-USDT"),
+            book: OrderBook::new("BTC-USDT"),
+            depth: 5,
+        };
+        (subscription, commands)
+    }
+
+    /// 快照 seqId 为 123456，买卖盘各 2 档与 3 档
+    const SNAPSHOT: &str = r#"{"asks":[["8476.98","415","0","13"],["8477","7","0","2"],["8477.34","85","0","1"]],"bids":[["8476.97","256","0","12"],["8475.55","101","0","1"]],"ts":"1597026383085","checksum":1332633458,"prevSeqId":-1,"seqId":123456}"#;
+    /// 删除卖一、新增一档卖盘并修改买一
+    const UPDATE: &str = r#"{"asks":[["8476.98","0","0","0"],["8478.1","12","0","1"]],"bids":[["8476.97","300","0","13"]],"ts":"1597026383137","checksum":114111972,"prevSeqId":123456,"seqId":123457}"#;
+
+    #[test]
+    fn checksum_interleaves_bids_and_asks() {
+        // OKX 文档中的示例
+        let book = okx_book(
+            &[("3366.1", "7"), ("3366", "6")],
+            &[("3366.8", "9"), ("3368", "8")],
+        );
+        assert_eq!(
+            okx_checksum_string(&book),
+            "3366.1:7:3366.8:9:3366:6:3368:8"
+        );
+        // CRC32 为 2413953002，超出 i32 的范围
+        assert_eq!(okx_book_checksum(&book), -1881014294);
+    }
+
+    #[test]
+    fn checksum_skips_the_shorter_side() {
+        let book = okx_book(
+            &[("3366.1", "7")],
+            &[("3366.8", "9"), ("3368", "8"), ("3372", "8")],
+        );
+        assert_eq!(
+            okx_checksum_string(&book),
+            "3366.1:7:3366.8:9:3368:8:3372:8"
+        );
+        assert_eq!(okx_book_checksum(&book), 831078360);
+
+        let book = okx_book(&[("3366.1", "7"), ("3366", "6")], &[]);
+        assert_eq!(okx_checksum_string(&book), "3366.1:7:3366:6");
+    }
+
+    #[test]
+    fn checksum_keeps_exchange_precision() {
+        let book = okx_book(&[("0.10", "1.500")], &[("0.11", "2")]);
+        assert_eq!(okx_checksum_string(&book), "0.10:1.500:0.11:2");
+    }
+
+    #[test]
+    fn applies_snapshot_then_update() {
+        let (mut subscription, commands) = subscription();
+
+        let Some(StreamEvent::Data(snapshot)) = subscription
+            .handle_frame(frame("snapshot", SNAPSHOT))
+            .unwrap()
+        else {
+            panic!("expected a snapshot");
+        };
+        assert_eq!(snapshot.bids.len(), 2);
+        assert_eq!(snapshot.asks.len(), 3);
+
+        let Some(StreamEvent::Data(book)) =
+            subscription.handle_frame(frame("update", UPDATE)).unwrap()
+        else {
+            panic!("expected an update");
+        };
+        let level =
+            |price: &str, quantity: &str| (price.parse().unwrap(), quantity.parse().unwrap());
+        assert_eq!(
+            book.bids,
+            vec![level("8476.97", "300"), level("8475.55", "101")]
+        );
+        assert_eq!(
+            book.asks,
+            vec![
+                level("8477", "7"),
+                level("8477.34", "85"),
+                level("8478.1", "12")
+            ]
+        );
+        assert_eq!(book.timestamp, 1597026383137);
+        assert_eq!(subscription.book.sequence(), Some(123457));
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn ignores_updates_before_snapshot() {
+        let (mut subscription, commands) = subscription();
+
+        assert!(
+            subscription
+                .handle_frame(frame("update", UPDATE))
+                .unwrap()
+                .is_none()
+        );
+        assert!(subscription.book.is_empty());
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn sequence_gap_resubscribes() {
+        let (mut subscription, commands) = subscription();
+        subscription
+            .handle_frame(frame("snapshot", SNAPSHOT))
+            .unwrap();
+
+        let gap = UPDATE.replace(r#""prevSeqId":123456"#, r#""prevSeqId":123455"#);
+        let err = subscription
+            .handle_frame(frame("update", &gap))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("sequence gap"), "{err:#}");
+        assert!(subscription.book.is_empty());
+        assert!(matches!(
+            commands.try_recv(),
+            Ok(Command::Resubscribe { arg }) if arg == subscription.arg
+        ));
+
+        // 重新订阅后的快照恢复订单簿
+        assert!(
+            subscription
+                .handle_frame(frame("snapshot", SNAPSHOT))
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn checksum_mismatch_resubscribes() {
+        let (mut subscription, commands) = subscription();
+        subscription
+            .handle_frame(frame("snapshot", SNAPSHOT))
+            .unwrap();
+
+        let corrupt = UPDATE.replace("114111972", "114111973");
+        let err = subscription
+            .handle_frame(frame("update", &corrupt))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("checksum mismatch"), "{err:#}");
+        assert!(matches!(
+            commands.try_recv(),
+            Ok(Command::Resubscribe { .. })
+        ));
+    }
+}
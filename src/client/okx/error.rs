@@ -0,0 +1,143 @@
+use super::model::{OKX_CODE_SUCCESS, OkxOrderResult};
+use crate::client::rate_limit::RateLimited;
+use bytestring::ByteString;
+use reqwest::StatusCode;
+use std::time::Duration;
+
+/// 服务暂时不可用、接口请求超时、系统繁忙、系统错误，稍后重试可能成功
+const RETRYABLE_CODES: &[&str] = &["50001", "50004", "50013", "50026"];
+
+/// OKX 客户端的错误
+///
+/// 以 `eyre::Report` 返回，调用方可以通过 `downcast_ref::<OkxError>()` 区分处理，
+/// 并用 [`OkxError::is_retryable`] 判断是否值得重试。
+#[derive(Debug)]
+pub enum OkxError {
+    /// 网络错误，例如连接失败或超时
+    Transport(reqwest::Error),
+    /// 非 2xx 的 HTTP 状态码，且响应体不是 OKX 的错误格式
+    Status {
+        status: StatusCode,
+        body: ByteString,
+    },
+    /// 无法解析响应
+    Decode(simd_json::Error),
+    /// `code` 非零的业务错误。下单类接口的 `data` 中携带每个订单的 `sCode`/`sMsg`
+    Api {
+        code: ByteString,
+        msg: ByteString,
+        data: Vec<OkxOrderResult>,
+    },
+    /// 单个订单被拒绝
+    Order {
+        s_code: ByteString,
+        s_msg: ByteString,
+        ord_id: ByteString,
+        cl_ord_id: ByteString,
+    },
+    /// 请求被限流，包括客户端限流与 OKX 返回的 50011
+    RateLimited(RateLimited),
+    /// 鉴权失败，例如缺少 API Key、签名错误、时间戳过期。本地检查失败时 `code` 为空
+    Auth { code: ByteString, msg: ByteString },
+}
+
+impl OkxError {
+    /// 按错误码区分鉴权错误与其他业务错误
+    pub(super) fn from_code(code: ByteString, msg: ByteString, data: Vec<OkxOrderResult>) -> Self {
+        if is_auth_code(&code) {
+            return Self::Auth { code, msg };
+        }
+
+        Self::Api { code, msg, data }
+    }
+
+    /// OKX 的错误码，订单错误为 `sCode`
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Self::Api { code, .. } | Self::Auth { code, .. } if !code.is_empty() => Some(code),
+            Self::Order { s_code, .. } => Some(s_code),
+            _ => None,
+        }
+    }
+
+    /// 是否为暂时性错误，原样重试可能成功
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(err) => err.is_timeout() || err.is_connect(),
+            Self::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::Api { code, .. } => RETRYABLE_CODES.contains(&code.as_ref()),
+            Self::Order { s_code, .. } => RETRYABLE_CODES.contains(&s_code.as_ref()),
+            Self::RateLimited(_) => true,
+            Self::Decode(_) | Self::Auth { .. } => false,
+        }
+    }
+
+    /// 被限流时建议的重试等待时间
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited(err) => Some(err.retry_after),
+            _ => None,
+        }
+    }
+}
+
+/// 501xx 为 API Key、签名、时间戳等鉴权相关的错误
+fn is_auth_code(code: &str) -> bool {
+    code.parse::<u32>()
+        .is_ok_and(|code| (50100..=50119).contains(&code))
+}
+
+impl From<OkxOrderResult> for OkxError {
+    fn from(value: OkxOrderResult) -> Self {
+        Self::Order {
+            s_code: value.s_code,
+            s_msg: value.s_msg,
+            ord_id: value.ord_id,
+            cl_ord_id: value.cl_ord_id,
+        }
+    }
+}
+
+impl From<RateLimited> for OkxError {
+    fn from(value: RateLimited) -> Self {
+        Self::RateLimited(value)
+    }
+}
+
+impl std::fmt::Display for OkxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Transport(err) => write!(f, "OKX request failed: {err}"),
+            Self::Status { status, body } => write!(f, "OKX returned HTTP {status}: {body}"),
+            Self::Decode(err) => write!(f, "Failed to decode OKX response: {err}"),
+            Self::Api { code, msg, data } => {
+                write!(f, "OKX error {code}: {msg}")?;
+                for result in data
+                    .iter()
+                    .filter(|result| result.s_code != OKX_CODE_SUCCESS)
+                {
+                    write!(f, "; sCode {}: {}", result.s_code, result.s_msg)?;
+                }
+                Ok(())
+            }
+            Self::Order {
+                s_code,
+                s_msg,
+                ord_id,
+                cl_ord_id,
+            } => write!(
+                f,
+                "OKX rejected order (ordId: '{ord_id}', clOrdId: '{cl_ord_id}'): sCode {s_code}: {s_msg}"
+            ),
+            Self::RateLimited(err) => err.fmt(f),
+            Self::Auth { code, msg } if code.is_empty() => {
+                write!(f, "OKX authentication failed: {msg}")
+            }
+            Self::Auth { code, msg } => write!(f, "OKX authentication failed {code}: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for OkxError {}
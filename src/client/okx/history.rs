@@ -0,0 +1,307 @@
+use super::{OkxClientV5, model::*, rest::RestClient};
+use crate::{Timestamp, data::CandleData};
+use eyre::Result;
+use futures_util::{Stream, StreamExt, stream};
+use tokio::time::{Instant, sleep_until};
+use url::Url;
+
+const HISTORY_CANDLES_PATH: &str = "api/v5/market/history-candles";
+/// history-candles 每页最多返回 100 条
+pub const OKX_HISTORY_CANDLES_LIMIT: usize = 100;
+
+/// 相邻两根K线之间缺少数据，`newer` 与 `older` 为缺口两侧K线的开始时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CandleGap {
+    pub newer: Timestamp,
+    pub older: Timestamp,
+}
+
+impl std::fmt::Display for CandleGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Missing candles between {} and {}",
+            self.older, self.newer
+        )
+    }
+}
+
+/// 历史K线下载流中的一项
+#[derive(Debug, Clone)]
+pub enum HistoryItem {
+    Candle(CandleData),
+    /// 与上一根产出的K线之间缺少数据，不影响后续下载
+    Gap(CandleGap),
+}
+
+impl HistoryItem {
+    pub fn into_candle(self) -> Option<CandleData> {
+        match self {
+            HistoryItem::Candle(candle) => Some(candle),
+            HistoryItem::Gap(_) => None,
+        }
+    }
+}
+
+/// 分页的进度，用 `after` 游标从新到旧逐页推进
+struct HistoryCursor {
+    request: OkxHistoryCandlesRequest,
+    /// 下一页请求的 `after` 参数，只返回早于该时间的K线
+    cursor: Option<Timestamp>,
+    /// 上一根产出的K线的开始时间，用于去重与缺口检测
+    last: Option<Timestamp>,
+    done: bool,
+}
+
+impl HistoryCursor {
+    fn new(request: OkxHistoryCandlesRequest) -> Self {
+        Self {
+            cursor: request.end,
+            last: None,
+            done: false,
+            request,
+        }
+    }
+
+    /// 处理一页数据并推进游标，空页表示没有更早的数据
+    fn advance(&mut self, page: Vec<OkxCandleData>) -> Vec<Result<HistoryItem>> {
+        if page.is_empty() {
+            self.done = true;
+            return Vec::new();
+        }
+
+        let prev_cursor = self.cursor;
+        let mut items = Vec::with_capacity(page.len());
+        for raw in page {
+            let timestamp = match raw.timestamp() {
+                Ok(timestamp) => timestamp,
+                Err(err) => {
+                    // 无法确定下一页的游标，只能结束下载
+                    self.done = true;
+                    items.push(Err(err));
+                    break;
+                }
+            };
+            self.cursor = Some(
+                self.cursor
+                    .map_or(timestamp, |cursor| cursor.min(timestamp)),
+            );
+
+            if self.request.start.is_some_and(|start| timestamp < start) {
+                self.done = true;
+                break;
+            }
+            // 相邻两页可能重叠，未完结的K线也不产出
+            if self.last.is_some_and(|last| timestamp >= last)
+                || self.request.end.is_some_and(|end| timestamp >= end)
+                || !raw.is_confirmed()
+            {
+                continue;
+            }
+
+            if let (Some(last), Some(duration)) = (self.last, self.request.bar.duration())
+                && last - timestamp > duration.as_millis()
+            {
+                items.push(Ok(HistoryItem::Gap(CandleGap {
+                    newer: last,
+                    older: timestamp,
+                })));
+            }
+            self.last = Some(timestamp);
+            items.push(
+                raw.into_candle_data(self.request.inst_id.clone(), self.request.bar)
+                    .map(HistoryItem::Candle),
+            );
+        }
+
+        // 游标没有前进说明已经没有更早的数据
+        if self.cursor == prev_cursor {
+            self.done = true;
+        }
+
+        items
+    }
+}
+
+struct HistoryPager {
+    rest: RestClient,
+    url: Url,
+    state: HistoryCursor,
+    next_request: Instant,
+}
+
+impl HistoryPager {
+    async fn fetch_page(&mut self) -> Result<Vec<OkxCandleData>> {
+        sleep_until(self.next_request).await;
+        let request = &self.state.request;
+        self.next_request = Instant::now() + request.request_interval;
+
+        let mut url = self.url.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("instId", &request.inst_id);
+            query.append_pair("bar", request.bar.as_ref());
+            query.append_pair("limit", &OKX_HISTORY_CANDLES_LIMIT.to_string());
+            if let Some(cursor) = self.state.cursor {
+                query.append_pair("after", &cursor.to_string());
+            }
+        }
+
+        let resp = self
+            .rest
+            .send::<OkxCandleData>(HISTORY_CANDLES_PATH, "", 1, self.rest.client.get(url))
+            .await?;
+
+        Ok(resp.data)
+    }
+
+    /// 下载并处理下一页，返回 `None` 表示下载已结束
+    async fn next_page(&mut self) -> Option<Vec<Result<HistoryItem>>> {
+        if self.state.done {
+            return None;
+        }
+
+        match self.fetch_page().await {
+            Ok(page) => Some(self.state.advance(page)),
+            Err(err) => {
+                self.state.done = true;
+                Some(vec![Err(err)])
+            }
+        }
+    }
+}
+
+impl OkxClientV5 {
+    /// 通过 history-candles 接口下载 `[start, end)` 内的历史K线
+    ///
+    /// K线按时间从新到旧产出，相邻页重叠的K线会被去重，未完结的K线会被跳过。
+    /// 发现缺口时产出 [`HistoryItem::Gap`] 并继续下载；请求失败时产出错误并结束。
+    /// 中断后使用 [`OkxHistoryCandlesRequest::from_last`] 从中断处继续。
+    pub fn history_candles(
+        &self,
+        request: OkxHistoryCandlesRequest,
+    ) -> Result<impl Stream<Item = Result<HistoryItem>> + Send + use<>> {
+        let pager = HistoryPager {
+            rest: self.rest.clone(),
+            url: self.base_http_url.join(HISTORY_CANDLES_PATH)?,
+            state: HistoryCursor::new(request),
+            next_request: Instant::now(),
+        };
+
+        Ok(stream::unfold(pager, |mut pager| async move {
+            let items = pager.next_page().await?;
+            Some((stream::iter(items), pager))
+        })
+        .flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::CandleInterval;
+
+    const MINUTE: Timestamp = 60_000;
+
+    fn candle(timestamp: Timestamp, confirmed: bool) -> OkxCandleData {
+        let row = format!(
+            r#"["{timestamp}","1","2","0.5","1.5","10","15","15","{}"]"#,
+            u8::from(confirmed)
+        );
+        simd_json::serde::from_slice(&mut row.into_bytes()).unwrap()
+    }
+
+    fn page(timestamps: &[Timestamp]) -> Vec<OkxCandleData> {
+        timestamps.iter().map(|&ts| candle(ts, true)).collect()
+    }
+
+    fn cursor(start: Option<Timestamp>, end: Option<Timestamp>) -> HistoryCursor {
+        HistoryCursor::new(
+            OkxHistoryCandlesRequest::builder("BTC-USDT")
+                .bar(CandleInterval::M1)
+                .maybe_start(start)
+                .maybe_end(end)
+                .build(),
+        )
+    }
+
+    fn timestamps(items: Vec<Result<HistoryItem>>) -> Vec<Result<Timestamp, CandleGap>> {
+        items
+            .into_iter()
+            .map(|item| match item.unwrap() {
+                HistoryItem::Candle(candle) => Ok(candle.timestamp),
+                HistoryItem::Gap(gap) => Err(gap),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deduplicates_overlapping_pages_and_skips_unconfirmed() {
+        let mut cursor = cursor(None, None);
+
+        let mut first = vec![candle(10 * MINUTE, false)];
+        first.extend(page(&[9 * MINUTE, 8 * MINUTE]));
+        assert_eq!(
+            timestamps(cursor.advance(first)),
+            [Ok(9 * MINUTE), Ok(8 * MINUTE)]
+        );
+        assert_eq!(cursor.cursor, Some(8 * MINUTE));
+
+        let second = page(&[8 * MINUTE, 7 * MINUTE]);
+        assert_eq!(timestamps(cursor.advance(second)), [Ok(7 * MINUTE)]);
+        assert!(!cursor.done);
+
+        assert!(cursor.advance(Vec::new()).is_empty());
+        assert!(cursor.done);
+    }
+
+    #[test]
+    fn reports_gaps_without_stopping() {
+        let mut cursor = cursor(None, None);
+
+        let items = timestamps(cursor.advance(page(&[9 * MINUTE, 6 * MINUTE, 5 * MINUTE])));
+        assert_eq!(
+            items,
+            [
+                Ok(9 * MINUTE),
+                Err(CandleGap {
+                    newer: 9 * MINUTE,
+                    older: 6 * MINUTE
+                }),
+                Ok(6 * MINUTE),
+                Ok(5 * MINUTE),
+            ]
+        );
+        assert!(!cursor.done);
+    }
+
+    #[test]
+    fn stops_at_start() {
+        let mut cursor = cursor(Some(5 * MINUTE), None);
+
+        let items = timestamps(cursor.advance(page(&[6 * MINUTE, 5 * MINUTE, 4 * MINUTE])));
+        assert_eq!(items, [Ok(6 * MINUTE), Ok(5 * MINUTE)]);
+        assert!(cursor.done);
+    }
+
+    #[test]
+    fn resumes_before_last_stored_candle() {
+        let request = OkxHistoryCandlesRequest::builder("BTC-USDT")
+            .end(10 * MINUTE)
+            .build()
+            .from_last(7 * MINUTE);
+        assert_eq!(request.end, Some(7 * MINUTE));
+
+        let mut cursor = HistoryCursor::new(request);
+        assert_eq!(cursor.cursor, Some(7 * MINUTE));
+        let items = timestamps(cursor.advance(page(&[7 * MINUTE, 6 * MINUTE])));
+        assert_eq!(items, [Ok(6 * MINUTE)]);
+
+        // 已保存的数据比请求的 `end` 更新时不影响下载范围
+        let request = OkxHistoryCandlesRequest::builder("BTC-USDT")
+            .end(10 * MINUTE)
+            .build()
+            .from_last(12 * MINUTE);
+        assert_eq!(request.end, Some(10 * MINUTE));
+    }
+}
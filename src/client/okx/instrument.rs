@@ -0,0 +1,147 @@
+use super::{OkxClientV5, model::*, rest::RestClient, ws::parse_data};
+use crate::{
+    client::{DataGetter, DataResponse, DataSubscriber, RawData},
+    data::StreamEvent,
+    instrument::{Instrument, InstrumentKind, InstrumentRegistry},
+};
+use eyre::Result;
+use futures_util::{Stream, StreamExt, stream};
+use itertools::Itertools;
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior, interval_at};
+use url::Url;
+
+const INSTRUMENTS_PATH: &str = "api/v5/public/instruments";
+
+async fn fetch_instruments(
+    rest: &RestClient,
+    url: &Url,
+    request: &OkxHttpInstrumentsRequest,
+) -> Result<Vec<Instrument>> {
+    let mut url = url.clone();
+    {
+        let mut query = url.query_pairs_mut();
+        query.append_pair("instType", request.inst_type.into());
+        if let Some(inst_family) = &request.inst_family {
+            query.append_pair("instFamily", inst_family);
+        }
+        if let Some(inst_id) = &request.inst_id {
+            query.append_pair("instId", inst_id);
+        }
+    }
+
+    let resp = rest
+        .send::<OkxInstrumentData>(INSTRUMENTS_PATH, "", 1, rest.client.get(url))
+        .await?;
+
+    resp.data.into_iter().map(Instrument::try_from).collect()
+}
+
+/// 下载所有请求的产品，成功后按产品类型替换注册表中的产品
+async fn load_instruments(
+    registry: &InstrumentRegistry,
+    rest: &RestClient,
+    url: &Url,
+    requests: &[OkxHttpInstrumentsRequest],
+) -> Result<Vec<Instrument>> {
+    let mut snapshots: Vec<(InstrumentKind, Vec<Instrument>)> = Vec::new();
+    for (inst_type, requests) in requests
+        .iter()
+        .into_group_map_by(|request| request.inst_type)
+    {
+        let kind = InstrumentKind::try_from(inst_type)?;
+        let mut instruments = Vec::new();
+        for request in requests {
+            instruments.extend(fetch_instruments(rest, url, request).await?);
+        }
+        snapshots.push((kind, instruments));
+    }
+
+    let mut all = Vec::new();
+    for (kind, instruments) in snapshots {
+        registry.replace(kind, instruments.iter().cloned());
+        all.extend(instruments);
+    }
+
+    Ok(all)
+}
+
+impl DataGetter<OkxHttpResponse<OkxHttpInstrumentsRequest>> for OkxClientV5 {
+    async fn get_data(
+        &mut self,
+        params: <OkxHttpResponse<OkxHttpInstrumentsRequest> as DataResponse>::Request,
+    ) -> Result<<OkxHttpResponse<OkxHttpInstrumentsRequest> as RawData>::Data> {
+        let url = self.base_http_url.join(INSTRUMENTS_PATH)?;
+
+        fetch_instruments(&self.rest, &url, &params).await
+    }
+}
+
+/// instruments 频道按产品类型订阅，产品信息变化时推送
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxInstrumentData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxInstrumentData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxInstrumentData> as RawData>::Data>
+        + Send,
+    > {
+        self.subscribe_channels(params, parse_data::<OkxInstrumentData>)
+            .await
+    }
+}
+
+impl OkxClientV5 {
+    /// 从 REST 接口加载产品到 `registry`，之后通过 instruments 频道与每隔 `refresh`
+    /// 的全量刷新保持更新。全量刷新按产品类型替换注册表中的产品，已下线的产品会被删除。
+    ///
+    /// 返回前会完成首次加载。返回的流产出每次更新的产品，需要持续轮询
+    /// （例如放到 `tokio::spawn` 中）注册表才会更新；刷新失败时产出错误并继续。
+    pub async fn sync_instruments(
+        &mut self,
+        registry: &InstrumentRegistry,
+        requests: Vec<OkxHttpInstrumentsRequest>,
+        refresh: Duration,
+    ) -> Result<impl Stream<Item = Result<StreamEvent<Vec<Instrument>>>> + Send + use<>> {
+        let rest = self.rest.clone();
+        let url = self.base_http_url.join(INSTRUMENTS_PATH)?;
+        load_instruments(registry, &rest, &url, &requests).await?;
+
+        let args: Vec<_> = requests
+            .iter()
+            .map(|request| request.inst_type)
+            .unique()
+            .map(|inst_type| OkxArg::with_inst_type(OkxChannel::Instruments, inst_type))
+            .collect();
+        let pushes = {
+            let registry = registry.clone();
+            self.subscribe_channels(
+                OkxWebSocketSubscribeRequest::<OkxInstrumentData>::builder("subscribe", args)
+                    .build(),
+                parse_data::<OkxInstrumentData>,
+            )
+            .await?
+            .map(move |event| {
+                if let Ok(StreamEvent::Data(instruments)) = &event {
+                    registry.extend(instruments.iter().cloned());
+                }
+                event
+            })
+        };
+
+        let mut ticker = interval_at(Instant::now() + refresh, refresh);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let refreshes = stream::unfold(
+            (ticker, registry.clone(), rest, url, requests),
+            |(mut ticker, registry, rest, url, requests)| async move {
+                ticker.tick().await;
+                let instruments = load_instruments(&registry, &rest, &url, &requests)
+                    .await
+                    .map(StreamEvent::Data);
+                Some((instruments, (ticker, registry, rest, url, requests)))
+            },
+        );
+
+        Ok(stream::select(pushes, refreshes))
+    }
+}
@@ -0,0 +1,247 @@
+use super::{OkxClientV5, model::*, ws::parse_data};
+use crate::{
+    client::{DataResponse, DataSubscriber, RawData},
+    data::{BookAction, BookData, CandleData, DataEnum},
+};
+use bytestring::ByteString;
+use eyre::{Result, bail, eyre};
+use futures_util::StreamExt;
+use serde::Deserialize;
+
+/// 只解析推送的频道，用于混合订阅时选择解析方式
+#[derive(Debug, Deserialize)]
+struct ChannelHeader {
+    arg: OkxArg,
+}
+
+/// 订阅前检查频道是否能被解析为目标数据类型
+fn ensure_channels<D>(
+    params: &OkxWebSocketSubscribeRequest<D>,
+    supported: fn(OkxChannel) -> bool,
+) -> Result<()> {
+    if let Some(arg) = params.args.iter().find(|arg| !supported(arg.channel)) {
+        bail!(
+            "Unsupported channel for this subscription: '{}'",
+            arg.channel
+        );
+    }
+
+    Ok(())
+}
+
+/// 解析 candle 频道的推送，产品ID与时间粒度取自推送的 `arg`
+///
+/// 未完结的K线会在收盘前反复推送，只保留已完结的K线，与历史K线一致
+fn parse_candles(text: ByteString) -> Result<Vec<CandleData>> {
+    let resp = simd_json::serde::from_slice::<OkxWebSocketDataResponse<OkxCandleData>>(
+        &mut text.as_bytes().to_vec(),
+    )?;
+    let interval = resp
+        .arg
+        .channel
+        .candle_interval()
+        .ok_or_else(|| eyre!("Unsupported candle channel: '{}'", resp.arg.channel))?;
+    let symbol = resp.arg.inst_id.unwrap_or_default();
+
+    resp.data
+        .into_iter()
+        .filter(OkxCandleData::is_confirmed)
+        .map(|data| data.into_candle_data(symbol.clone(), interval))
+        .collect()
+}
+
+/// 解析深度频道的推送，没有 `action` 的频道每次推送的都是全量快照
+fn parse_books(text: ByteString) -> Result<Vec<BookData>> {
+    let resp = simd_json::serde::from_slice::<OkxWebSocketDataResponse<OkxBookData>>(
+        &mut text.as_bytes().to_vec(),
+    )?;
+    let symbol = resp.arg.inst_id.unwrap_or_default();
+    let action = resp.action.unwrap_or(BookAction::Snapshot);
+
+    resp.data
+        .into_iter()
+        .map(|data| data.into_book_data(symbol.clone(), action))
+        .collect()
+}
+
+/// 按推送的频道选择解析方式
+fn parse_market_data(text: ByteString) -> Result<Vec<DataEnum>> {
+    let header = simd_json::serde::from_slice::<ChannelHeader>(&mut text.as_bytes().to_vec())?;
+    let channel = header.arg.channel;
+
+    Ok(if channel.is_trade() {
+        parse_data::<OkxTradeData>(text)?
+            .into_iter()
+            .map(DataEnum::Trade)
+            .collect()
+    } else if channel.candle_interval().is_some() {
+        parse_candles(text)?
+            .into_iter()
+            .map(DataEnum::Candle)
+            .collect()
+    } else if channel.is_book() {
+        parse_books(text)?.into_iter().map(DataEnum::Book).collect()
+    } else {
+        bail!("Unsupported market data channel: '{channel}'")
+    })
+}
+
+/// 只产出已完结的K线，未完结K线的推送被丢弃
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxCandleData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxCandleData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxCandleData> as RawData>::Data> + Send,
+    > {
+        ensure_channels(&params, |channel| channel.candle_interval().is_some())?;
+        self.subscribe_channels(params, parse_candles).await
+    }
+}
+
+/// trades 频道在 public 地址，trades-all 频道在 business 地址
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxTradeData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxTradeData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxTradeData> as RawData>::Data> + Send,
+    > {
+        ensure_channels(&params, OkxChannel::is_trade)?;
+        self.subscribe_channels(params, parse_data::<OkxTradeData>)
+            .await
+    }
+}
+
+/// 产出交易所推送的原始深度数据，增量需要自行合并；
+/// 需要本地订单簿时使用 [`OkxClientV5::subscribe_order_book`]。
+///
+/// books50-l2-tbt 与 books-l2-tbt 需要登录，客户端配置了 API Key 时 public 连接会自动登录。
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxBookData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxBookData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxBookData> as RawData>::Data> + Send,
+    > {
+        ensure_channels(&params, OkxChannel::is_book)?;
+        self.subscribe_channels(params, parse_books).await
+    }
+}
+
+/// 混合订阅成交、K线与深度频道，所有推送合并为一个 [`DataEnum`] 流
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxMarketData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxMarketData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxMarketData> as RawData>::Data> + Send,
+    > {
+        ensure_channels(&params, |channel| {
+            channel.is_trade() || channel.candle_interval().is_some() || channel.is_book()
+        })?;
+        self.subscribe_channels(params, parse_market_data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Timestamp,
+        backtest::{Backtest, SimExchange},
+        data::StreamEvent,
+        decimal::Price,
+        recorder::{DEFAULT_FLUSH_INTERVAL, Recorder, read_recording},
+        strategy::{Context, Strategy, runtime::StrategyRuntime},
+    };
+    use futures_util::stream;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    type Seen = Arc<Mutex<Vec<(Timestamp, Timestamp, Price)>>>;
+
+    /// 记录每次K线回调的 [`Context::now`]、K线时间戳与收盘价
+    struct Probe(Seen);
+
+    impl Strategy for Probe {
+        fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push((ctx.now(), candle.timestamp, candle.close));
+            Ok(())
+        }
+    }
+
+    fn push(close: &str, confirm: &str) -> ByteString {
+        format!(
+            r#"{{"arg":{{"channel":"candle1m","instId":"BTC-USDT"}},"data":[["60000","100","103","99","{close}","5","500","500","{confirm}"]]}}"#
+        )
+        .into()
+    }
+
+    #[tokio::test]
+    async fn live_candles_match_the_replay() {
+        // 同一根K线先推送未完结的中间状态，收盘后推送完结状态
+        let frames = [push("101", "0"), push("102", "1")];
+        let live = Seen::default();
+        let market = frames
+            .into_iter()
+            .map(|text| parse_market_data(text).map(StreamEvent::Data))
+            .collect::<Vec<_>>();
+        StrategyRuntime::builder(
+            "live",
+            Box::new(Probe(live.clone())),
+            SimExchange::new(10_000.0, "USDT", Default::default()),
+        )
+        .reorder_window(Duration::ZERO)
+        .build()
+        .run(
+            stream::iter(market),
+            stream::empty(),
+            std::future::pending(),
+        )
+        .await
+        .unwrap();
+
+        let replay = Seen::default();
+        let bar = parse_candles(push("102", "1")).unwrap();
+        Backtest::builder("replay", Box::new(Probe(replay.clone())))
+            .build()
+            .run(bar.into_iter().map(DataEnum::Candle).collect())
+            .await
+            .unwrap();
+
+        let expected = vec![(120_000, 60_000, "102".parse().unwrap())];
+        assert_eq!(*live.lock().unwrap(), expected);
+        assert_eq!(*replay.lock().unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn records_only_confirmed_candles() {
+        let dir = std::env::temp_dir().join(format!("squant-market-{}", uuid::Uuid::new_v4()));
+        let recorder = Recorder::spawn(&dir, DEFAULT_FLUSH_INTERVAL);
+        let market = [push("101", "0"), push("102", "1")]
+            .map(|text| parse_market_data(text).map(StreamEvent::Data));
+        let forwarded = recorder
+            .tee("candle1m".into(), stream::iter(market))
+            .count()
+            .await;
+        recorder.close().unwrap();
+
+        assert_eq!(forwarded, 2);
+        let recorded: Vec<_> = read_recording(dir.join("BTC-USDT/candle1m/1970-01-01.jsonl.gz"))
+            .unwrap()
+            .into_iter()
+            .map(|event| match event.data {
+                DataEnum::Candle(candle) => candle.close,
+                data => panic!("unexpected {data:?}"),
+            })
+            .collect();
+        assert_eq!(recorded, ["102".parse::<Price>().unwrap()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
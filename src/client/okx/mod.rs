@@ -1,29 +1,56 @@
 #![allow(dead_code)]
 
+mod account;
+pub mod book;
+pub mod error;
+pub mod history;
+mod instrument;
+mod market;
 pub mod model;
+mod rate_limit;
+mod rest;
+pub mod sign;
+mod time;
+mod trade;
+pub mod ws;
 
 use super::*;
 use crate::{
-    client::{DataGetter, DataSubscriber},
-    data::{CandleData, DataStream},
+    client::{
+        DataGetter, DataSubscriber, backoff::Backoff, rate_limit::RateLimiter, retry::RetryPolicy,
+    },
+    clock::Clock,
+    data::StreamEvent,
 };
 use bytestring::ByteString;
-use eyre::{Result, bail, eyre};
-use futures_util::{SinkExt, StreamExt};
-use http::Uri;
+use error::OkxError;
+use eyre::{Result, bail};
+use futures_util::{StreamExt, stream};
+use http::{Method, Uri};
 use model::*;
-use reqwest::Client;
-use tokio_websockets::Message;
+use rest::RestClient;
+use serde::{Serialize, de::DeserializeOwned};
+use std::collections::HashMap;
 use url::Url;
+use ws::{Heartbeat, OkxWsConnection, OkxWsEndpoint, OkxWsOptions, parse_data};
+
+const CANDLES_PATH: &str = "api/v5/market/candles";
 
 // TODO: 支持不使用TLS
 pub struct OkxClientV5 {
     base_http_url: Url,
     base_ws_uri: Uri,
 
-    client: Client,
+    rest: RestClient,                   // HTTP 客户端、限流器与重试策略
     api_key: Option<ByteString>,        // 即 OK_ACCESS_KEY
     api_passphrase: Option<ByteString>, // 即 OK_ACCESS_PASSPHRASE
+    api_secret: Option<ByteString>,     // 即 OK_ACCESS_SECRET
+    simulated_trading: bool,            // 是否为模拟盘请求
+    clock: Clock,                       // 按服务器时间校正的时钟
+
+    ws_backoff: Backoff,     // WebSocket 断线重连的退避参数
+    ws_heartbeat: Heartbeat, // WebSocket 心跳参数
+    ws_connections: HashMap<OkxWsEndpoint, OkxWsConnection>, // 每个服务地址共享一个连接
 
     itoa_buffer: itoa::Buffer, // 高效的整数转为字符串的缓冲区
     bytes_buffer: Vec<u8>,
@@ -35,17 +62,48 @@ impl OkxClientV5 {
     pub fn new(
         #[builder(default = "https://www.okx.com/")] base_http_url: &str,
         #[builder(default = "wss://wspap.okx.com/")] base_ws_uri: &str,
+        /// 未指定时从环境变量 OK_ACCESS_KEY 读取
+        #[builder(into)]
+        api_key: Option<ByteString>,
+        /// 未指定时从环境变量 OK_ACCESS_PASSPHRASE 读取
+        #[builder(into)]
+        api_passphrase: Option<ByteString>,
+        /// 未指定时从环境变量 OK_ACCESS_SECRET 读取
+        #[builder(into)]
+        api_secret: Option<ByteString>,
+        /// 私有请求是否携带 `x-simulated-trading: 1`（模拟盘）
+        #[builder(default)]
+        simulated_trading: bool,
+        /// 签名使用的时钟，可以与其他组件共享
+        #[builder(default)]
+        clock: Clock,
+        /// 所有 REST 请求与 WebSocket 操作共用的限流器，同一 IP 或账户下的多个客户端应共享同一个
+        #[builder(default)]
+        rate_limiter: RateLimiter,
+        /// REST 请求的重试策略与单次请求的超时时间。下单只在指定了客户自定义订单ID时重试，撤单与改单不重试
+        #[builder(default)]
+        retry: RetryPolicy,
+        /// WebSocket 断线重连的退避参数
+        #[builder(default)]
+        ws_backoff: Backoff,
+        /// WebSocket 心跳参数
+        #[builder(default)]
+        ws_heartbeat: Heartbeat,
     ) -> Result<Self> {
-        let ok_access_key = std::env::var("OK_ACCESS_KEY").ok();
-
-        let ok_access_passphrase = std::env::var("OK_ACCESS_PASSPHRASE").ok();
+        let from_env = |key: &str| std::env::var(key).ok().map(ByteString::from);
 
         Ok(OkxClientV5 {
             base_http_url: base_http_url.parse::<Url>()?,
             base_ws_uri: base_ws_uri.parse::<Uri>()?,
-            client: Client::new(),
-            api_key: ok_access_key.map(Into::into),
-            api_passphrase: ok_access_passphrase.map(Into::into),
+            rest: RestClient::new(rate_limiter, retry)?,
+            api_key: api_key.or_else(|| from_env("OK_ACCESS_KEY")),
+            api_passphrase: api_passphrase.or_else(|| from_env("OK_ACCESS_PASSPHRASE")),
+            api_secret: api_secret.or_else(|| from_env("OK_ACCESS_SECRET")),
+            simulated_trading,
+            clock,
+            ws_backoff,
+            ws_heartbeat,
+            ws_connections: HashMap::new(),
             itoa_buffer: itoa::Buffer::new(),
             bytes_buffer: Vec::new(),
         })
@@ -61,6 +119,115 @@ impl OkxClientV5 {
     }
 
     // fn buffer_json() {}
+
+    fn ws_uri(&self, path: &str) -> String {
+        format!("{}{}", self.base_ws_uri, path)
+    }
+
+    /// 获取指定服务地址的共享连接，连接不存在或已关闭时新建
+    pub async fn ws_connection(&mut self, endpoint: OkxWsEndpoint) -> Result<OkxWsConnection> {
+        if let Some(conn) = self.ws_connections.get(&endpoint)
+            && !conn.is_closed()
+        {
+            return Ok(conn.clone());
+        }
+
+        let options = OkxWsOptions {
+            backoff: self.ws_backoff,
+            heartbeat: self.ws_heartbeat,
+            clock: self.clock.clone(),
+            rate_limiter: self.rest.rate_limiter.clone(),
+            // public 地址上的 l2-tbt 深度频道也需要登录，配置了 API Key 时一并登录
+            credentials: match endpoint {
+                OkxWsEndpoint::Private => Some(self.credentials()?),
+                OkxWsEndpoint::Public => self.credentials().ok(),
+                OkxWsEndpoint::Business => None,
+            },
+        };
+        let conn =
+            OkxWsConnection::connect(&self.ws_uri(endpoint.path()), endpoint, options).await?;
+        self.ws_connections.insert(endpoint, conn.clone());
+
+        Ok(conn)
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rest.rate_limiter
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.rest.retry
+    }
+
+    /// 发送签名的 GET 请求，按用户限流，可重试的错误按重试策略重试
+    pub(super) async fn get_private<D>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<OkxHttpResponse<D>>
+    where
+        D: RawData + DeserializeOwned,
+    {
+        let mut url = self.base_http_url.join(path)?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+
+        self.rest
+            .retry(|| self.send_private(Method::GET, path, url.clone(), String::new(), "", 1))
+            .await
+    }
+
+    /// 以 JSON 请求体发送签名的 POST 请求，在 `scope` 范围内消耗 `cost` 个令牌。
+    /// POST 请求不一定幂等，是否重试由调用方决定
+    pub(super) async fn post_private<D, B>(
+        &self,
+        path: &str,
+        body: &B,
+        scope: &str,
+        cost: u32,
+    ) -> Result<OkxHttpResponse<D>>
+    where
+        D: RawData + DeserializeOwned,
+        B: Serialize + ?Sized,
+    {
+        let url = self.base_http_url.join(path)?;
+        let body = simd_json::serde::to_string(body)?;
+
+        self.send_private(Method::POST, path, url, body, scope, cost)
+            .await
+    }
+
+    /// 发送需要签名的私有请求
+    ///
+    /// `url` 需包含完整的查询参数，`body` 为 JSON 请求体（GET 请求传空字符串）。
+    /// 按 `path` 对应的限流规则在 `scope` 范围内消耗 `cost` 个令牌，签名在取得令牌后生成。
+    pub(super) async fn send_private<D>(
+        &self,
+        method: Method,
+        path: &str,
+        url: Url,
+        body: String,
+        scope: &str,
+        cost: u32,
+    ) -> Result<OkxHttpResponse<D>>
+    where
+        D: RawData + DeserializeOwned,
+    {
+        rate_limit::acquire(&self.rest.rate_limiter, path, scope, cost).await?;
+
+        let request_path = &url[url::Position::BeforePath..];
+        let headers = self.generate_okx_headers(&method, request_path, &body)?;
+
+        let mut request = self.rest.client.request(method, url).headers(headers);
+        if !body.is_empty() {
+            request = request.body(body);
+        }
+
+        let resp = request.send().await.map_err(OkxError::Transport)?;
+
+        Ok(rest::read_response(&self.rest.rate_limiter, path, scope, resp).await?)
+    }
 }
 
 impl DataGetter<OkxHttpResponse<OkxHttpCandleDataRequest>> for OkxClientV5 {
@@ -76,16 +243,14 @@ impl DataGetter<OkxHttpResponse<OkxHttpCandleDataRequest>> for OkxClientV5 {
             limit,
         } = params;
 
-        let mut url = Url::parse("https://www.okx.com/api/v5/market/candles")?;
+        let mut url = self.base_http_url.join(CANDLES_PATH)?;
 
         {
             let mut query = url.query_pairs_mut();
 
             let buffer = &mut self.itoa_buffer;
             query.append_pair("instId", &inst_id);
-            if let Some(bar) = bar {
-                query.append_pair("bar", bar.as_ref());
-            }
+            query.append_pair("bar", bar.as_ref());
             if let Some(after) = after {
                 query.append_pair("after", buffer.format(after));
             }
@@ -98,73 +263,57 @@ impl DataGetter<OkxHttpResponse<OkxHttpCandleDataRequest>> for OkxClientV5 {
         }
 
         let resp = self
-            .client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<OkxHttpResponse<OkxCandleData>>()
+            .rest
+            .send::<OkxCandleData>(CANDLES_PATH, "", 1, self.rest.client.get(url))
             .await?;
 
-        resp.data.into_iter().map(CandleData::try_from).collect()
+        resp.data
+            .into_iter()
+            .map(|data| data.into_candle_data(inst_id.clone(), bar))
+            .collect()
     }
 }
 
-impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxCandleData>> for OkxClientV5 {
-    async fn subscribe_data(
+impl OkxClientV5 {
+    /// 在各频道对应的共享连接上订阅，用 `parse` 解析推送，并将所有频道的推送合并为一个流
+    async fn subscribe_channels<D, T>(
         &mut self,
-        params: <OkxWebSocketSubscribeResponse<OkxCandleData> as DataResponse>::Request,
-    ) -> Result<
-        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxCandleData> as RawData>::Data> + Send,
-    > {
-        let (mut client, _) = tokio_websockets::client::Builder::new()
-            .uri("wss://wspap.okx.com/ws/v5/business")?
-            .connect()
-            .await?;
-
-        client
-            .send(Message::text(simd_json::serde::to_string(&params)?))
-            .await?;
+        params: OkxWebSocketSubscribeRequest<D>,
+        parse: fn(ByteString) -> Result<T>,
+    ) -> Result<impl StreamExt<Item = Result<StreamEvent<T>>> + Send + use<D, T>>
+    where
+        T: Send + 'static,
+    {
+        if params.op != "subscribe" {
+            bail!(
+                "Unsupported WebSocket operation for subscription: '{}'",
+                params.op
+            );
+        }
 
-        let _resp = if let Some(Ok(msg)) = client.next().await
-            && let Some(text) = msg.as_text()
-        {
-            simd_json::serde::from_slice::<OkxWebSocketSubscribeResponse<OkxCandleData>>(
-                &mut text.to_string().into_bytes(),
-            )?
-        } else {
-            bail!("Failed to subscribe to OKX WebSocket channel")
-        };
+        let mut streams = Vec::with_capacity(params.args.len());
+        for arg in params.args {
+            let conn = self
+                .ws_connection(OkxWsEndpoint::for_channel(arg.channel))
+                .await?;
+            streams.push(Box::pin(conn.subscribe_with(arg, parse)?));
+        }
 
-        let stream: DataStream<Result<Vec<CandleData>>, _, _, _> =
-            DataStream::new(client, |msg| -> Result<_> {
-                let msg = msg?;
-                let text = msg
-                    .as_text()
-                    .ok_or_else(|| eyre!("Received non-text message from WebSocket: {:?}", msg))?;
-                let resp = simd_json::serde::from_slice::<OkxWebSocketDataResponse<OkxCandleData>>(
-                    &mut text.to_string().into_bytes(),
-                )?;
-
-                resp.data
-                    .into_iter()
-                    .map(CandleData::try_from)
-                    .collect::<Result<_>>()
-            });
-
-        Ok(stream)
+        Ok(stream::select_all(streams))
     }
 }
 
-// #[cfg(test)]
-// mod test {
-//     use super::*;
-//
-//     // #[test]
-//     // fn create_okx_client_v5() {
-//     //     OkxClientV5::new();
-//     // }
-// }
+impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxOrderData>> for OkxClientV5 {
+    async fn subscribe_data(
+        &mut self,
+        params: <OkxWebSocketSubscribeResponse<OkxOrderData> as DataResponse>::Request,
+    ) -> Result<
+        impl StreamExt<Item = <OkxWebSocketSubscribeResponse<OkxOrderData> as RawData>::Data> + Send,
+    > {
+        self.subscribe_channels(params, parse_data::<OkxOrderData>)
+            .await
+    }
+}
 
 //
 //     // async fn get_datas(
@@ -230,90 +379,3 @@ impl DataSubscriber<OkxWebSocketSubscribeResponse<OkxCandleData>> for OkxClientV
 //     //         .try_collect()?)
 //     // }
 // }
-
-// fn generate_okx_headers(
-//     okx: &OkxClientV5,
-//     method: &str,
-//     request_path: &str,
-//     body_str: &str,
-// ) -> Result<HeaderMap, Box<dyn std::error::Error>> {
-//     let mut headers = HeaderMap::new();
-//
-//     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-//     headers.insert(
-//         HeaderName::from_static("ok-access-key"),
-//         HeaderValue::from_str(&okx.api_key)?,
-//     );
-//     headers.insert(
-//         HeaderName::from_static("ok-access-passphrase"),
-//         HeaderValue::from_str(&okx.api_passphrase)?,
-//     );
-//
-//     // 1. 创建时间戳 (timestamp)
-//     // 格式要求：ISO8601 格式，带毫秒，例如 2020-12-08T09:08:57.715Z
-//     let now: DateTime<Utc> = Utc::now();
-//     let timestamp = now.to_rfc3339_opts(SecondsFormat::Millis, true);
-//
-//     headers.insert(
-//         HeaderName::from_static("ok-access-timestamp"),
-//         HeaderValue::from_str(&timestamp)?,
-//     );
-//
-//     // 2. 创建签名
-//     let secret_key = std::env::var("OK_ACCESS_SECRET")
-//         .context("OK_ACCESS_SECRET environment variable not set")?;
-//     let signature = generate_okx_signature(method, request_path, body_str, &secret_key)?;
-//
-//     headers.insert(
-//         HeaderName::from_static("ok-access-sign"),
-//         HeaderValue::from_str(&signature)?,
-//     );
-//
-//     Ok(headers)
-// }
-//
-// /// 根据 OKX API 规则生成签名
-// ///
-// /// # Arguments
-// /// * `method` - HTTP 请求方法，大写形式，例如 "GET", "POST"。
-// /// * `request_path` - 请求的路径，包含查询参数（如果存在）。
-// ///   例如："/api/v5/account/balance?ccy=BTC" 或 "/api/v5/trade/order"。
-// /// * `body_str` - 请求体字符串。对于 GET 请求或没有请求体的 POST 请求，应为空字符串 ""。
-// ///   对于有请求体的 POST 请求，这是 JSON 请求体的字符串形式。
-// /// * `secret_key` - 你的 API SecretKey。
-// ///
-// /// # Returns
-// /// * `Ok(OkxAuthHeaders)` 包含生成的时间戳和签名。
-// /// * `Err` 如果在过程中发生错误。
-// fn generate_okx_signature(
-//     method: &str,
-//     request_path: &str,
-//     body_str: &str,
-//     secret_key: &str,
-// ) -> Result<String, Box<dyn std::error::Error>> {
-//     // 1. 创建时间戳 (timestamp)
-//     // 格式要求：ISO8601 格式，带毫秒，例如 2020-12-08T09:08:57.715Z
-//     let now: DateTime<Utc> = Utc::now();
-//     let timestamp = now.to_rfc3339_opts(SecondsFormat::Millis, true);
-//
-//     // 2. 创建预签名字符串 (pre-hash string)
-//     // 格式: timestamp + method + requestPath + body
-//     // 注意：method 需要是大写
-//     let prehash_string = format!(
-//         "{}{}{}{}",
-//         timestamp,
-//         method.to_uppercase(),
-//         request_path,
-//         body_str
-//     );
-//
-//     // 3. 使用 HMAC SHA256 和 SecretKey 对预签名字符串进行签名
-//     type HmacSha256 = Hmac<Sha256>;
-//     let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
-//         .context("Failed to create HMAC SHA256 instance")?;
-//     mac.update(prehash_string.as_bytes());
-//     let signature_bytes = mac.finalize().into_bytes();
-//
-//     // 4. 将签名进行 Base64 编码
-//     Ok(BASE64_STANDARD_ENGINE.encode(signature_bytes))
-// }
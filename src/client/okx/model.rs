@@ -1,12 +1,27 @@
 #![allow(dead_code)]
 
-use super::*;
-use crate::{Timestamp, order::Side};
+use super::{error::OkxError, *};
+use crate::{
+    Timestamp,
+    conf::DataSourceConfig,
+    data::{
+        Balance, BookAction, BookData, CandleData, CandleInterval, DataEnum, MarketDataType,
+        Position, PositionSide, StreamEvent, TradeData,
+    },
+    decimal::{Decimal, Price, Quantity},
+    instrument::{Instrument, InstrumentKind, InstrumentState},
+    order::{
+        AmendOrder, CancelOrder, Fill, NewOrder, OrderAck, OrderRef, OrderStatus, OrderType,
+        OrderUpdate, Side, TradeMode,
+    },
+};
 use bon::Builder;
 use bytestring::ByteString;
 use eyre::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::{marker::PhantomData, time::Duration};
+use strum::IntoStaticStr;
 
 pub(super) const OKX_CODE_SUCCESS: &str = "0";
 
@@ -31,10 +46,8 @@ pub struct OkxHttpCandleDataRequest {
     #[builder(start_fn, into)]
     pub inst_id: ByteString,
     /// 时间粒度，默认值1m
-    /// 如 [1m/3m/5m/15m/30m/1H/2H/4H]
-    /// 香港时间开盘价k线：[6H/12H/1D/2D/3D/1W/1M/3M]
-    /// UTC时间开盘价k线：[/6Hutc/12Hutc/1Dutc/2Dutc/3Dutc/1Wutc/1Mutc/3Mutc]
-    pub bar: Option<ByteString>,
+    #[builder(default)]
+    pub bar: CandleInterval,
     /// 请求此时间戳之前（更旧的数据）的分页内容，传的值为对应接口的ts
     pub after: Option<Timestamp>,
     /// 请求此时间戳之后（更新的数据）的分页内容，传的值为对应接口的ts, 单独使用时，会返回最新的数据。
@@ -44,53 +57,215 @@ pub struct OkxHttpCandleDataRequest {
 }
 
 impl RawData for OkxHttpCandleDataRequest {
-    type Data = super::CandleData;
+    type Data = CandleData;
+}
+
+/// 分页下载 GET /api/v5/market/history-candles，见 [`OkxClientV5::history_candles`]
+#[derive(Debug, Clone, Builder)]
+pub struct OkxHistoryCandlesRequest {
+    /// 产品ID，如 BTC-USDT
+    #[builder(start_fn, into)]
+    pub inst_id: ByteString,
+    /// 时间粒度，默认值1m
+    #[builder(default)]
+    pub bar: CandleInterval,
+    /// 最早一根K线的开始时间（包含），不填时下载到没有更早的数据为止
+    pub start: Option<Timestamp>,
+    /// 最晚一根K线的开始时间（不包含），不填时从最新的K线开始
+    pub end: Option<Timestamp>,
+    /// 两次请求之间的最小间隔，该接口限速为 20次/2s
+    #[builder(default = Duration::from_millis(100))]
+    pub request_interval: Duration,
+}
+
+impl OkxHistoryCandlesRequest {
+    /// 从上次中断处继续下载
+    ///
+    /// 下载从新到旧进行，`last` 为上次最后保存的（即最早的）一根K线的开始时间，
+    /// 之后只下载早于它的K线。
+    pub fn from_last(mut self, last: Timestamp) -> Self {
+        self.end = Some(self.end.map_or(last, |end| end.min(last)));
+        self
+    }
+}
+
+/// WebSocket 频道，K线频道的时间粒度由 [`CandleInterval`] 给出，不存在无效的频道名
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub enum OkxChannel {
+    Trades,
+    TradesAll,
+    Books,
+    Books5,
+    BboTbt,
+    Books50L2Tbt,
+    BooksL2Tbt,
+    /// candle1m、candle1D 等
+    Candle(CandleInterval),
+    Instruments,
+    Orders,
+    Fills,
+    Account,
+    Positions,
+    BalanceAndPosition,
+}
+
+impl OkxChannel {
+    const NAMES: [(OkxChannel, &'static str); 13] = [
+        (OkxChannel::Trades, "trades"),
+        (OkxChannel::TradesAll, "trades-all"),
+        (OkxChannel::Books, "books"),
+        (OkxChannel::Books5, "books5"),
+        (OkxChannel::BboTbt, "bbo-tbt"),
+        (OkxChannel::Books50L2Tbt, "books50-l2-tbt"),
+        (OkxChannel::BooksL2Tbt, "books-l2-tbt"),
+        (OkxChannel::Instruments, "instruments"),
+        (OkxChannel::Orders, "orders"),
+        (OkxChannel::Fills, "fills"),
+        (OkxChannel::Account, "account"),
+        (OkxChannel::Positions, "positions"),
+        (OkxChannel::BalanceAndPosition, "balance_and_position"),
+    ];
+
+    pub fn is_trade(self) -> bool {
+        matches!(self, OkxChannel::Trades | OkxChannel::TradesAll)
+    }
+
+    /// books5、bbo-tbt 每次推送全量，books、books50-l2-tbt、books-l2-tbt 先推送全量再推送增量
+    pub fn is_book(self) -> bool {
+        matches!(
+            self,
+            OkxChannel::Books
+                | OkxChannel::Books5
+                | OkxChannel::BboTbt
+                | OkxChannel::Books50L2Tbt
+                | OkxChannel::BooksL2Tbt
+        )
+    }
+
+    pub fn candle_interval(self) -> Option<CandleInterval> {
+        match self {
+            OkxChannel::Candle(interval) => Some(interval),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for OkxChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OkxChannel::Candle(interval) => write!(f, "candle{interval}"),
+            channel => {
+                let (_, name) = Self::NAMES
+                    .iter()
+                    .find(|(c, _)| c == channel)
+                    .expect("every channel has a name");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for OkxChannel {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(interval) = s.strip_prefix("candle") {
+            return interval
+                .parse()
+                .map(OkxChannel::Candle)
+                .wrap_err_with(|| format!("Invalid candle channel: '{s}'"));
+        }
+
+        Self::NAMES
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|&(channel, _)| channel)
+            .ok_or_else(|| eyre::eyre!("Unsupported channel: '{s}'"))
+    }
 }
 
 /// 订阅的频道
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OkxArg {
-    /// 频道名
-    /// candle3M
-    /// candle1M
-    /// candle1W
-    /// candle1D
-    /// candle2D
-    /// candle3D
-    /// candle5D
-    /// candle12H
-    /// candle6H
-    /// candle4H
-    /// candle2H
-    /// candle1H
-    /// candle30m
-    /// candle15m
-    /// candle5m
-    /// candle3m
-    /// candle1m
-    /// candle1s
-    /// candle3Mutc
-    /// candle1Mutc
-    /// candle1Wutc
-    /// candle1Dutc
-    /// candle2Dutc
-    /// candle3Dutc
-    /// candle5Dutc
-    /// candle12Hutc
-    /// candle6Hutc
-    pub channel: ByteString,
+    /// 频道，K线频道请使用 [`OkxArg::candle`] 构造
+    pub channel: OkxChannel,
     /// 产品ID，例如 "BTC-USDT"。
-    pub inst_id: ByteString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inst_id: Option<ByteString>,
+    /// 产品类型，orders 等私有频道按产品类型订阅
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inst_type: Option<OkxInstType>,
+    /// 交易品种，例如 "BTC-USD"，仅适用于交割、永续与期权
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inst_family: Option<ByteString>,
+    /// 币种，仅 account 频道使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<ByteString>,
 }
 
 impl OkxArg {
-    pub fn new(channel: impl Into<ByteString>, inst_id: impl Into<ByteString>) -> Self {
+    /// 不带任何参数的频道，例如 account
+    pub fn channel(channel: OkxChannel) -> Self {
+        Self {
+            channel,
+            inst_id: None,
+            inst_type: None,
+            inst_family: None,
+            ccy: None,
+        }
+    }
+
+    pub fn new(channel: OkxChannel, inst_id: impl Into<ByteString>) -> Self {
         Self {
-            channel: channel.into(),
-            inst_id: inst_id.into(),
+            inst_id: Some(inst_id.into()),
+            ..Self::channel(channel)
         }
     }
+
+    /// K线频道，频道名由时间粒度生成，例如 candle1D
+    pub fn candle(interval: CandleInterval, inst_id: impl Into<ByteString>) -> Self {
+        Self::new(OkxChannel::Candle(interval), inst_id)
+    }
+
+    /// 订阅某一产品类型下的所有产品
+    pub fn with_inst_type(channel: OkxChannel, inst_type: OkxInstType) -> Self {
+        Self {
+            inst_type: Some(inst_type),
+            ..Self::channel(channel)
+        }
+    }
+}
+
+/// 配置中的行情订阅对应的频道，深度使用 5 档快照 books5
+impl From<&DataSourceConfig> for OkxArg {
+    fn from(value: &DataSourceConfig) -> Self {
+        let symbol = value.symbol.clone();
+        match value.data_type {
+            MarketDataType::Trade => Self::new(OkxChannel::Trades, symbol),
+            MarketDataType::Candle => Self::candle(value.interval.unwrap_or_default(), symbol),
+            MarketDataType::Book => Self::new(OkxChannel::Books5, symbol),
+        }
+    }
+}
+
+/// 产品类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, IntoStaticStr)]
+#[serde(rename_all = "UPPERCASE")]
+#[strum(serialize_all = "UPPERCASE")]
+pub enum OkxInstType {
+    /// 币币
+    Spot,
+    /// 币币杠杆
+    Margin,
+    /// 永续合约
+    Swap,
+    /// 交割合约
+    Futures,
+    /// 期权
+    Option,
+    /// 全部，仅用于订阅
+    Any,
 }
 
 #[derive(Builder, Serialize)]
@@ -144,20 +319,60 @@ pub struct OkxWebSocketSubscribeResponse<D> {
 }
 
 impl<D: RawData> RawData for OkxWebSocketSubscribeResponse<D> {
-    type Data = Result<Vec<D::Data>>;
+    type Data = Result<StreamEvent<Vec<D::Data>>>;
 }
 
 impl<D> DataResponse for OkxWebSocketSubscribeResponse<D> {
     type Request = OkxWebSocketSubscribeRequest<D>;
 }
 
+/// WebSocket 登录请求，私有频道需要先登录
+#[derive(Debug, Serialize)]
+pub struct OkxWebSocketLoginRequest {
+    pub op: ByteString,
+    pub args: Vec<OkxLoginArg>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxLoginArg {
+    pub api_key: ByteString,
+    pub passphrase: ByteString,
+    /// Unix 时间戳的秒数
+    pub timestamp: ByteString,
+    pub sign: ByteString,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OkxWebSocketDataResponse<D> {
     pub arg: OkxArg,
+    /// 推送数据动作，仅深度频道有，snapshot：全量，update：增量
+    #[serde(default)]
+    pub action: Option<BookAction>,
     pub data: Vec<D>,
 }
 
+/// 订阅、取消订阅、错误等事件推送
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxWebSocketEvent {
+    /// 事件类型，subscribe/unsubscribe/error
+    pub event: ByteString,
+    pub code: Option<ByteString>,
+    pub msg: Option<ByteString>,
+    pub arg: Option<OkxArg>,
+    pub conn_id: Option<ByteString>,
+}
+
+/// WebSocket 收到的文本消息，可能是事件也可能是数据推送
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum OkxWebSocketMessage<D> {
+    Event(OkxWebSocketEvent),
+    Data(OkxWebSocketDataResponse<D>),
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OkxTradeData {
@@ -169,7 +384,11 @@ pub struct OkxTradeData {
     pub ts: ByteString,
 }
 
-impl TryFrom<OkxTradeData> for crate::data::TradeData {
+impl RawData for OkxTradeData {
+    type Data = TradeData;
+}
+
+impl TryFrom<OkxTradeData> for TradeData {
     type Error = eyre::Report;
 
     fn try_from(value: OkxTradeData) -> Result<Self> {
@@ -179,11 +398,11 @@ impl TryFrom<OkxTradeData> for crate::data::TradeData {
             .wrap_err("Failed to parse trade timestamp")?;
         let price = value
             .px
-            .parse::<f64>()
+            .parse::<Price>()
             .wrap_err_with(|| format!("Failed to parse trade price: '{}'", value.px))?;
         let quantity = value
             .sz
-            .parse::<f64>()
+            .parse::<Quantity>()
             .wrap_err_with(|| format!("Failed to parse trade volume: '{}'", value.sz))?;
         let side = Side::try_from(value.side.as_ref())?;
 
@@ -210,35 +429,46 @@ pub struct OkxBookData {
     pub asks: Vec<Level>,
     pub bids: Vec<Level>,
     pub ts: ByteString,
+    /// 检验和，仅 books 等深度频道推送
+    #[serde(default)]
+    pub checksum: Option<i32>,
+    /// 上一个推送的序列号，全量快照时为 -1
+    #[serde(default)]
+    pub prev_seq_id: Option<i64>,
+    /// 推送的序列号
+    #[serde(default)]
+    pub seq_id: Option<i64>,
 }
 
-impl TryFrom<OkxBookData> for crate::data::BookData {
-    type Error = eyre::Report;
+impl RawData for OkxBookData {
+    type Data = BookData;
+}
 
-    fn try_from(value: OkxBookData) -> Result<Self> {
-        let parse_levels = |levels: &Vec<Level>| -> Result<Vec<(f64, f64)>> {
+impl OkxBookData {
+    /// 推送数据中不含产品ID与推送类型，需要从推送的 `arg` 与 `action` 中取得
+    pub fn into_book_data(self, symbol: ByteString, action: BookAction) -> Result<BookData> {
+        let parse_levels = |levels: &Vec<Level>| -> Result<Vec<(Price, Quantity)>> {
             levels
                 .iter()
                 .map(|(price_str, size_str, _, _)| {
                     let price = price_str
-                        .parse::<f64>()
+                        .parse::<Price>()
                         .wrap_err("Failed to parse book price")?;
                     let size = size_str
-                        .parse::<f64>()
+                        .parse::<Quantity>()
                         .wrap_err("Failed to parse book size")?;
                     Ok((price, size))
                 })
                 .collect()
         };
 
-        let timestamp = value
-            .ts
-            .parse()
-            .wrap_err("Failed to parse book timestamp")?;
-        let bids = parse_levels(&value.bids).wrap_err("Failed to parse bids")?;
-        let asks = parse_levels(&value.asks).wrap_err("Failed to parse asks")?;
+        let timestamp = self.ts.parse().wrap_err("Failed to parse book timestamp")?;
+        let bids = parse_levels(&self.bids).wrap_err("Failed to parse bids")?;
+        let asks = parse_levels(&self.asks).wrap_err("Failed to parse asks")?;
 
-        Ok(Self {
+        Ok(BookData {
+            symbol,
+            action,
             timestamp,
             bids,
             asks,
@@ -269,36 +499,51 @@ pub struct OkxCandleData(
 );
 
 impl RawData for OkxCandleData {
-    type Data = super::CandleData;
+    type Data = CandleData;
 }
 
-impl TryFrom<OkxCandleData> for super::CandleData {
-    type Error = eyre::Report;
+impl OkxCandleData {
+    /// K线的开始时间
+    pub fn timestamp(&self) -> Result<Timestamp> {
+        self.0.parse().wrap_err("Failed to parse candle timestamp")
+    }
 
-    fn try_from(value: OkxCandleData) -> Result<Self> {
-        let timestamp = value
-            .0
-            .parse()
-            .wrap_err("Failed to parse candle timestamp")?;
-        let open = value
+    /// K线是否已经完结，最新一根K线在完结前会持续更新
+    pub fn is_confirmed(&self) -> bool {
+        self.8 == "1"
+    }
+
+    /// 推送数据中不含产品ID与时间粒度，需要从请求参数或推送的 `arg` 中取得
+    pub fn into_candle_data(
+        self,
+        symbol: ByteString,
+        interval: CandleInterval,
+    ) -> Result<CandleData> {
+        let timestamp = self.timestamp()?;
+        let open = self
             .1
-            .parse::<f64>()
+            .parse::<Price>()
             .wrap_err("Failed to parse open price")?;
-        let high = value
+        let high = self
             .2
-            .parse::<f64>()
+            .parse::<Price>()
             .wrap_err("Failed to parse high price")?;
-        let low = value
+        let low = self
             .3
-            .parse::<f64>()
+            .parse::<Price>()
             .wrap_err("Failed to parse low price")?;
-        let close = value
+        let close = self
             .4
-            .parse::<f64>()
+            .parse::<Price>()
             .wrap_err("Failed to parse close price")?;
-        let volume = value.5.parse::<f64>().wrap_err("Failed to parse volume")?;
+        let volume = self
+            .5
+            .parse::<Quantity>()
+            .wrap_err("Failed to parse volume")?;
 
-        Ok(Self {
+        Ok(CandleData {
+            symbol,
+            interval,
             timestamp,
             open,
             high,
@@ -308,3 +553,804 @@ impl TryFrom<OkxCandleData> for super::CandleData {
         })
     }
 }
+
+/// 混合订阅的标记类型，推送按频道解析为 [`DataEnum`]
+///
+/// 支持 trades、trades-all、candle* 以及各深度频道。
+#[derive(Debug)]
+pub struct OkxMarketData;
+
+impl RawData for OkxMarketData {
+    type Data = DataEnum;
+}
+
+/// POST /api/v5/trade/order
+#[derive(Debug, Clone, Builder, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxPlaceOrderRequest {
+    /// 产品ID，如 BTC-USDT
+    #[builder(start_fn, into)]
+    pub inst_id: ByteString,
+    /// 交易模式，保证金模式 isolated/cross，非保证金模式 cash
+    pub td_mode: TradeMode,
+    /// 订单方向 buy/sell
+    pub side: Side,
+    /// 订单类型 market/limit/post_only/fok/ioc
+    pub ord_type: OrderType,
+    /// 委托数量
+    pub sz: Quantity,
+    /// 委托价格，仅适用于 limit、post_only、fok、ioc 类型的订单
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub px: Option<Price>,
+    /// 客户自定义订单ID，字母（区分大小写）与数字的组合，长度要在1-32位之间。
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<ByteString>,
+    /// 订单标签
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<ByteString>,
+    /// 保证金币种，仅适用于现货和合约模式下的全仓杠杆订单
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ccy: Option<ByteString>,
+    /// 是否只减仓
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    /// 委托数量的单位，仅适用于币币市价单
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tgt_ccy: Option<OkxTargetCurrency>,
+}
+
+/// 币币市价单 `sz` 的单位，默认买单为计价货币，卖单为交易货币
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OkxTargetCurrency {
+    /// 交易货币
+    BaseCcy,
+    /// 计价货币
+    QuoteCcy,
+}
+
+impl TryFrom<NewOrder> for OkxPlaceOrderRequest {
+    type Error = eyre::Report;
+
+    fn try_from(value: NewOrder) -> Result<Self> {
+        let px = match (value.order_type, value.price) {
+            (OrderType::Unknown, _) => {
+                eyre::bail!("Cannot place an order of unknown type on {}", value.symbol)
+            }
+            (OrderType::Market, _) => None,
+            (_, Some(price)) => Some(price),
+            (order_type, None) => {
+                eyre::bail!(
+                    "Price is required for {:?} order on {}",
+                    order_type,
+                    value.symbol
+                )
+            }
+        };
+
+        // `NewOrder::quantity` 总是以交易货币计，币币市价买单默认以计价货币计，需要显式指定
+        let tgt_ccy = (value.order_type == OrderType::Market
+            && value.trade_mode == TradeMode::Cash)
+            .then_some(OkxTargetCurrency::BaseCcy);
+
+        Ok(Self {
+            inst_id: value.symbol,
+            td_mode: value.trade_mode,
+            side: value.side,
+            ord_type: value.order_type,
+            sz: value.quantity,
+            px,
+            cl_ord_id: value.client_order_id,
+            tag: None,
+            ccy: None,
+            reduce_only: value.reduce_only,
+            tgt_ccy,
+        })
+    }
+}
+
+/// POST /api/v5/trade/cancel-order
+/// `ord_id` 与 `cl_ord_id` 必须传一个，若传两个，以 `ord_id` 为主。
+#[derive(Debug, Clone, Builder, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxCancelOrderRequest {
+    #[builder(start_fn, into)]
+    pub inst_id: ByteString,
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_id: Option<ByteString>,
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<ByteString>,
+}
+
+impl From<CancelOrder> for OkxCancelOrderRequest {
+    fn from(value: CancelOrder) -> Self {
+        let (ord_id, cl_ord_id) = split_order_ref(value.order);
+
+        Self {
+            inst_id: value.symbol,
+            ord_id,
+            cl_ord_id,
+        }
+    }
+}
+
+/// POST /api/v5/trade/amend-order
+/// `ord_id` 与 `cl_ord_id` 必须传一个；`new_sz` 与 `new_px` 至少传一个。
+#[derive(Debug, Clone, Builder, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxAmendOrderRequest {
+    #[builder(start_fn, into)]
+    pub inst_id: ByteString,
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ord_id: Option<ByteString>,
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cl_ord_id: Option<ByteString>,
+    /// 修改失败时是否自动撤单，默认为 false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cxl_on_fail: Option<bool>,
+    /// 用户自定义修改事件ID
+    #[builder(into)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub req_id: Option<ByteString>,
+    /// 修改的新数量（包含已成交数量）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_sz: Option<Quantity>,
+    /// 修改后的新价格
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_px: Option<Price>,
+}
+
+impl TryFrom<AmendOrder> for OkxAmendOrderRequest {
+    type Error = eyre::Report;
+
+    fn try_from(value: AmendOrder) -> Result<Self> {
+        if value.new_quantity.is_none() && value.new_price.is_none() {
+            eyre::bail!(
+                "Amend order on {} changes neither price nor size",
+                value.symbol
+            );
+        }
+        let (ord_id, cl_ord_id) = split_order_ref(value.order);
+
+        Ok(Self {
+            inst_id: value.symbol,
+            ord_id,
+            cl_ord_id,
+            cxl_on_fail: value.cancel_on_fail.then_some(true),
+            req_id: None,
+            new_sz: value.new_quantity,
+            new_px: value.new_price,
+        })
+    }
+}
+
+fn split_order_ref(order: OrderRef) -> (Option<ByteString>, Option<ByteString>) {
+    match order {
+        OrderRef::Exchange(ord_id) => (Some(ord_id), None),
+        OrderRef::Client(cl_ord_id) => (None, Some(cl_ord_id)),
+    }
+}
+
+/// 下单、撤单、改单接口返回的单个订单结果
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxOrderResult {
+    /// 订单ID
+    #[serde(default)]
+    pub ord_id: ByteString,
+    /// 客户自定义订单ID
+    #[serde(default)]
+    pub cl_ord_id: ByteString,
+    /// 订单标签
+    #[serde(default)]
+    pub tag: ByteString,
+    /// 用户自定义修改事件ID（仅改单）
+    #[serde(default)]
+    pub req_id: ByteString,
+    /// 事件执行结果的code，0代表成功
+    pub s_code: ByteString,
+    /// 事件执行失败或成功时的msg
+    #[serde(default)]
+    pub s_msg: ByteString,
+}
+
+impl RawData for OkxOrderResult {
+    type Data = OrderAck;
+}
+
+impl TryFrom<OkxOrderResult> for OrderAck {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxOrderResult) -> Result<Self> {
+        if value.s_code != OKX_CODE_SUCCESS {
+            return Err(OkxError::from(value).into());
+        }
+
+        Ok(Self {
+            order_id: value.ord_id,
+            client_order_id: (!value.cl_ord_id.is_empty()).then_some(value.cl_ord_id),
+        })
+    }
+}
+
+/// orders 频道推送的订单数据
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxOrderData {
+    pub inst_type: OkxInstType,
+    pub inst_id: ByteString,
+    pub ord_id: ByteString,
+    #[serde(default)]
+    pub cl_ord_id: ByteString,
+    /// 委托价格，市价单为空字符串
+    #[serde(default)]
+    pub px: ByteString,
+    /// 委托数量
+    pub sz: ByteString,
+    /// 订单类型，无法识别的类型解析为 [`OrderType::Unknown`]，不影响同一推送中的其它订单
+    pub ord_type: OrderType,
+    pub side: Side,
+    pub td_mode: TradeMode,
+    /// 最新成交价格，没有成交时为空字符串
+    #[serde(default)]
+    pub fill_px: ByteString,
+    /// 最新成交ID
+    #[serde(default)]
+    pub trade_id: ByteString,
+    /// 最新成交数量
+    #[serde(default)]
+    pub fill_sz: ByteString,
+    /// 最新成交时间
+    #[serde(default)]
+    pub fill_time: ByteString,
+    /// 最新一笔成交的手续费，平台收取为负数
+    #[serde(default)]
+    pub fill_fee: ByteString,
+    #[serde(default)]
+    pub fill_fee_ccy: ByteString,
+    /// 最新一笔成交的流动性方向 T：taker M：maker
+    #[serde(default)]
+    pub exec_type: ByteString,
+    /// 累计成交数量
+    #[serde(default)]
+    pub acc_fill_sz: ByteString,
+    /// 成交均价，没有成交时为空字符串
+    #[serde(default)]
+    pub avg_px: ByteString,
+    /// 订单状态 live/partially_filled/filled/canceled/mmp_canceled，
+    /// 无法识别的状态解析为 [`OrderStatus::Unknown`]
+    pub state: ByteString,
+    /// 累计手续费，平台收取为负数
+    #[serde(default)]
+    pub fee: ByteString,
+    #[serde(default)]
+    pub fee_ccy: ByteString,
+    /// 订单状态更新时间，Unix时间戳的毫秒数
+    pub u_time: ByteString,
+    /// 订单创建时间，Unix时间戳的毫秒数
+    pub c_time: ByteString,
+}
+
+impl RawData for OkxOrderData {
+    type Data = OrderUpdate;
+}
+
+/// OKX 以空字符串表示缺失的数值
+fn parse_optional_f64(value: &ByteString, field: &str) -> Result<Option<f64>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse::<f64>()
+        .map(Some)
+        .wrap_err_with(|| format!("Failed to parse {field}: '{value}'"))
+}
+
+fn parse_optional_decimal(value: &ByteString, field: &str) -> Result<Option<Decimal>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    value
+        .parse::<Decimal>()
+        .map(Some)
+        .wrap_err_with(|| format!("Failed to parse {field}: '{value}'"))
+}
+
+impl TryFrom<OkxOrderData> for OrderUpdate {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxOrderData) -> Result<Self> {
+        let status = match value.state.as_ref() {
+            "live" => OrderStatus::New,
+            "partially_filled" => OrderStatus::PartiallyFilled,
+            "filled" => OrderStatus::Filled,
+            "canceled" | "mmp_canceled" => OrderStatus::Canceled,
+            // 不影响同一推送中的其它订单
+            _ => OrderStatus::Unknown,
+        };
+
+        let fill_quantity =
+            parse_optional_decimal(&value.fill_sz, "fill size")?.unwrap_or_default();
+        let last_fill = if fill_quantity.is_positive() {
+            Some(Fill {
+                trade_id: value.trade_id,
+                price: parse_optional_decimal(&value.fill_px, "fill price")?.unwrap_or_default(),
+                quantity: fill_quantity,
+                fee: parse_optional_f64(&value.fill_fee, "fill fee")?.unwrap_or(0.0),
+                fee_currency: value.fill_fee_ccy,
+                is_maker: value.exec_type == "M",
+                timestamp: value
+                    .fill_time
+                    .parse()
+                    .wrap_err("Failed to parse fill time")?,
+            })
+        } else {
+            None
+        };
+
+        Ok(Self {
+            symbol: value.inst_id,
+            order_id: value.ord_id,
+            client_order_id: (!value.cl_ord_id.is_empty()).then_some(value.cl_ord_id),
+            side: value.side,
+            order_type: value.ord_type,
+            status,
+            price: parse_optional_decimal(&value.px, "order price")?,
+            quantity: value
+                .sz
+                .parse::<Quantity>()
+                .wrap_err_with(|| format!("Failed to parse order size: '{}'", value.sz))?,
+            filled_quantity: parse_optional_decimal(&value.acc_fill_sz, "accumulated fill size")?
+                .unwrap_or_default(),
+            average_price: parse_optional_decimal(&value.avg_px, "average price")?
+                .filter(|avg_px| avg_px.is_positive()),
+            fee: parse_optional_f64(&value.fee, "fee")?.unwrap_or(0.0),
+            fee_currency: value.fee_ccy,
+            last_fill,
+            timestamp: value
+                .u_time
+                .parse()
+                .wrap_err("Failed to parse order update time")?,
+        })
+    }
+}
+
+/// GET /api/v5/account/balance
+#[derive(Debug, Default, Builder)]
+pub struct OkxHttpBalanceRequest {
+    /// 币种，如 BTC，不填时返回所有资产不为 0 的币种，最多 20 个
+    pub ccy: Option<Vec<ByteString>>,
+}
+
+impl RawData for OkxHttpBalanceRequest {
+    type Data = Balance;
+}
+
+/// 账户余额，REST 接口与 account 频道格式相同
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxBalanceData {
+    /// 账户信息的更新时间，Unix时间戳的毫秒数
+    pub u_time: ByteString,
+    /// 美金层面权益
+    #[serde(default)]
+    pub total_eq: ByteString,
+    /// 各币种资产详细信息
+    pub details: Vec<OkxBalanceDetail>,
+}
+
+impl RawData for OkxBalanceData {
+    type Data = Balance;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxBalanceDetail {
+    pub ccy: ByteString,
+    /// 币种总权益
+    #[serde(default)]
+    pub eq: ByteString,
+    /// 币种余额
+    #[serde(default)]
+    pub cash_bal: ByteString,
+    /// 可用余额
+    #[serde(default)]
+    pub avail_bal: ByteString,
+    /// 币种占用金额
+    #[serde(default)]
+    pub frozen_bal: ByteString,
+    /// 未实现盈亏
+    #[serde(default)]
+    pub upl: ByteString,
+    /// 币种余额信息的更新时间，Unix时间戳的毫秒数
+    pub u_time: ByteString,
+}
+
+impl OkxBalanceData {
+    /// 展开为各币种的余额
+    pub fn into_balances(self) -> Result<Vec<Balance>> {
+        self.details.into_iter().map(Balance::try_from).collect()
+    }
+}
+
+impl TryFrom<OkxBalanceDetail> for Balance {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxBalanceDetail) -> Result<Self> {
+        let field = |value: &ByteString, field: &str| -> Result<f64> {
+            Ok(parse_optional_f64(value, field)?.unwrap_or(0.0))
+        };
+
+        Ok(Self {
+            equity: field(&value.eq, "equity")?,
+            cash: field(&value.cash_bal, "cash balance")?,
+            available: field(&value.avail_bal, "available balance")?,
+            frozen: field(&value.frozen_bal, "frozen balance")?,
+            unrealized_pnl: field(&value.upl, "unrealized pnl")?,
+            timestamp: value
+                .u_time
+                .parse()
+                .wrap_err("Failed to parse balance update time")?,
+            currency: value.ccy,
+        })
+    }
+}
+
+/// GET /api/v5/account/positions
+#[derive(Debug, Default, Builder)]
+pub struct OkxHttpPositionsRequest {
+    /// 产品类型 MARGIN/SWAP/FUTURES/OPTION
+    pub inst_type: Option<OkxInstType>,
+    /// 产品ID，支持多个（不超过10个），半角逗号分隔
+    #[builder(into)]
+    pub inst_id: Option<ByteString>,
+    /// 持仓ID，支持多个（不超过20个），半角逗号分隔
+    #[builder(into)]
+    pub pos_id: Option<ByteString>,
+}
+
+impl RawData for OkxHttpPositionsRequest {
+    type Data = Position;
+}
+
+/// 持仓信息，REST 接口与 positions 频道格式相同
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxPositionData {
+    pub inst_type: OkxInstType,
+    pub inst_id: ByteString,
+    /// 保证金模式 cross/isolated
+    pub mgn_mode: TradeMode,
+    pub pos_id: ByteString,
+    /// 持仓方向 long/short/net
+    pub pos_side: PositionSide,
+    /// 持仓数量
+    pub pos: ByteString,
+    /// 可平仓数量
+    #[serde(default)]
+    pub avail_pos: ByteString,
+    /// 开仓均价
+    #[serde(default)]
+    pub avg_px: ByteString,
+    /// 最新标记价格
+    #[serde(default)]
+    pub mark_px: ByteString,
+    /// 预估强平价
+    #[serde(default)]
+    pub liq_px: ByteString,
+    /// 未实现收益（以标记价格计算）
+    #[serde(default)]
+    pub upl: ByteString,
+    /// 已实现收益
+    #[serde(default)]
+    pub realized_pnl: ByteString,
+    /// 杠杆倍数
+    #[serde(default)]
+    pub lever: ByteString,
+    /// 最近一次持仓更新时间，Unix时间戳的毫秒数
+    pub u_time: ByteString,
+}
+
+impl RawData for OkxPositionData {
+    type Data = Position;
+}
+
+impl TryFrom<OkxPositionData> for Position {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxPositionData) -> Result<Self> {
+        Ok(Self {
+            side: value.pos_side,
+            trade_mode: value.mgn_mode,
+            quantity: parse_optional_decimal(&value.pos, "position size")?.unwrap_or_default(),
+            available_quantity: parse_optional_decimal(&value.avail_pos, "available position")?,
+            average_price: parse_optional_decimal(&value.avg_px, "average price")?,
+            mark_price: parse_optional_decimal(&value.mark_px, "mark price")?,
+            liquidation_price: parse_optional_decimal(&value.liq_px, "liquidation price")?,
+            unrealized_pnl: parse_optional_f64(&value.upl, "unrealized pnl")?.unwrap_or(0.0),
+            realized_pnl: parse_optional_f64(&value.realized_pnl, "realized pnl")?.unwrap_or(0.0),
+            leverage: parse_optional_f64(&value.lever, "leverage")?,
+            timestamp: value
+                .u_time
+                .parse()
+                .wrap_err("Failed to parse position update time")?,
+            symbol: value.inst_id,
+        })
+    }
+}
+
+/// GET /api/v5/public/instruments
+#[derive(Debug, Clone, Builder)]
+pub struct OkxHttpInstrumentsRequest {
+    /// 产品类型 SPOT/MARGIN/SWAP/FUTURES/OPTION
+    #[builder(start_fn)]
+    pub inst_type: OkxInstType,
+    /// 交易品种，如 BTC-USD，仅适用于交割/永续/期权，期权必填
+    #[builder(into)]
+    pub inst_family: Option<ByteString>,
+    /// 产品ID
+    #[builder(into)]
+    pub inst_id: Option<ByteString>,
+}
+
+impl RawData for OkxHttpInstrumentsRequest {
+    type Data = Instrument;
+}
+
+/// 产品信息，REST 接口与 instruments 频道格式相同
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OkxInstrumentData {
+    pub inst_type: OkxInstType,
+    pub inst_id: ByteString,
+    /// 交易货币币种，仅适用于币币/币币杠杆
+    #[serde(default)]
+    pub base_ccy: ByteString,
+    /// 计价货币币种，仅适用于币币/币币杠杆
+    #[serde(default)]
+    pub quote_ccy: ByteString,
+    /// 盈亏结算和保证金币种，仅适用于交割/永续/期权
+    #[serde(default)]
+    pub settle_ccy: ByteString,
+    /// 合约面值，仅适用于交割/永续/期权
+    #[serde(default)]
+    pub ct_val: ByteString,
+    /// 上线时间，Unix时间戳的毫秒数
+    #[serde(default)]
+    pub list_time: ByteString,
+    /// 交割/行权日期，Unix时间戳的毫秒数
+    #[serde(default)]
+    pub exp_time: ByteString,
+    /// 下单价格精度
+    pub tick_sz: ByteString,
+    /// 下单数量精度
+    pub lot_sz: ByteString,
+    /// 最小下单数量
+    pub min_sz: ByteString,
+    /// 产品状态 live/suspend/preopen/test
+    pub state: ByteString,
+}
+
+impl RawData for OkxInstrumentData {
+    type Data = Instrument;
+}
+
+impl TryFrom<OkxInstType> for InstrumentKind {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxInstType) -> Result<Self> {
+        Ok(match value {
+            OkxInstType::Spot => InstrumentKind::Spot,
+            OkxInstType::Margin => InstrumentKind::Margin,
+            OkxInstType::Swap => InstrumentKind::Swap,
+            OkxInstType::Futures => InstrumentKind::Futures,
+            OkxInstType::Option => InstrumentKind::Option,
+            OkxInstType::Any => eyre::bail!("Invalid instrument type: 'ANY'"),
+        })
+    }
+}
+
+impl TryFrom<OkxInstrumentData> for Instrument {
+    type Error = eyre::Report;
+
+    fn try_from(value: OkxInstrumentData) -> Result<Self> {
+        let kind = InstrumentKind::try_from(value.inst_type)?;
+        let state = match value.state.as_ref() {
+            "live" => InstrumentState::Live,
+            "suspend" => InstrumentState::Suspend,
+            "preopen" => InstrumentState::Preopen,
+            "test" => InstrumentState::Test,
+            state => eyre::bail!("Unknown instrument state: '{state}'"),
+        };
+        let non_empty = |value: ByteString| (!value.is_empty()).then_some(value);
+        let parse_time = |value: &ByteString, field: &str| -> Result<Option<Timestamp>> {
+            if value.is_empty() {
+                return Ok(None);
+            }
+            value
+                .parse()
+                .map(Some)
+                .wrap_err_with(|| format!("Failed to parse {field}: '{value}'"))
+        };
+
+        Ok(Self {
+            kind,
+            state,
+            tick_size: value
+                .tick_sz
+                .parse()
+                .wrap_err_with(|| format!("Failed to parse tick size: '{}'", value.tick_sz))?,
+            lot_size: value
+                .lot_sz
+                .parse()
+                .wrap_err_with(|| format!("Failed to parse lot size: '{}'", value.lot_sz))?,
+            min_size: value
+                .min_sz
+                .parse()
+                .wrap_err_with(|| format!("Failed to parse min size: '{}'", value.min_sz))?,
+            contract_value: parse_optional_f64(&value.ct_val, "contract value")?,
+            listed_at: parse_time(&value.list_time, "list time")?,
+            expires_at: parse_time(&value.exp_time, "expiry time")?,
+            base_currency: non_empty(value.base_ccy),
+            quote_currency: non_empty(value.quote_ccy),
+            settle_currency: non_empty(value.settle_ccy),
+            symbol: value.inst_id,
+        })
+    }
+}
+
+/// GET /api/v5/public/time
+#[derive(Debug, Deserialize)]
+pub struct OkxServerTimeData {
+    /// 系统时间，Unix时间戳的毫秒数
+    pub ts: ByteString,
+}
+
+impl RawData for OkxServerTimeData {
+    type Data = Timestamp;
+}
+
+impl OkxServerTimeData {
+    pub fn timestamp(&self) -> Result<Timestamp> {
+        self.ts.parse().wrap_err("Failed to parse server time")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_order(order_type: OrderType, trade_mode: TradeMode) -> NewOrder {
+        NewOrder::builder("BTC-USDT")
+            .side(Side::Buy)
+            .order_type(order_type)
+            .trade_mode(trade_mode)
+            .quantity("0.01".parse().unwrap())
+            .price("50000".parse().unwrap())
+            .build()
+    }
+
+    #[test]
+    fn spot_market_order_sizes_in_base_currency() {
+        let request =
+            OkxPlaceOrderRequest::try_from(new_order(OrderType::Market, TradeMode::Cash)).unwrap();
+        assert_eq!(request.tgt_ccy, Some(OkxTargetCurrency::BaseCcy));
+        assert_eq!(request.px, None);
+        let json = simd_json::to_string(&request).unwrap();
+        assert!(json.contains(r#""tgtCcy":"base_ccy""#), "{json}");
+        assert!(json.contains(r#""sz":"0.01""#), "{json}");
+    }
+
+    #[test]
+    fn other_orders_omit_target_currency() {
+        for (order_type, trade_mode) in [
+            (OrderType::Limit, TradeMode::Cash),
+            (OrderType::Market, TradeMode::Cross),
+        ] {
+            let request =
+                OkxPlaceOrderRequest::try_from(new_order(order_type, trade_mode)).unwrap();
+            assert_eq!(request.tgt_ccy, None);
+            assert!(!simd_json::to_string(&request).unwrap().contains("tgtCcy"));
+        }
+    }
+
+    #[test]
+    fn unknown_order_types_do_not_drop_the_frame() {
+        let order = |ord_id: &str, ord_type: &str| {
+            format!(
+                r#"{{"instType":"SPOT","instId":"BTC-USDT","ordId":"{ord_id}","px":"50000","sz":"0.01","ordType":"{ord_type}","side":"buy","tdMode":"cash","accFillSz":"0","state":"live","uTime":"1700000000000","cTime":"1700000000000"}}"#
+            )
+        };
+        let text = format!(
+            r#"{{"arg":{{"channel":"orders","instType":"ANY","uid":"1"}},"data":[{},{},{},{}]}}"#,
+            order("1", "limit"),
+            order("2", "optimal_limit_ioc"),
+            order("3", "mmp_and_post_only"),
+            order("4", "elp"),
+        );
+
+        let updates = super::super::ws::parse_data::<OkxOrderData>(text.into()).unwrap();
+        let types: Vec<_> = updates.iter().map(|update| update.order_type).collect();
+        assert_eq!(
+            types,
+            [
+                OrderType::Limit,
+                OrderType::Ioc,
+                OrderType::PostOnly,
+                OrderType::Unknown
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_order_states_do_not_drop_the_frame() {
+        let order = |ord_id: &str, state: &str| {
+            format!(
+                r#"{{"instType":"SPOT","instId":"BTC-USDT","ordId":"{ord_id}","px":"50000","sz":"0.01","ordType":"limit","side":"buy","tdMode":"cash","accFillSz":"0","state":"{state}","uTime":"1700000000000","cTime":"1700000000000"}}"#
+            )
+        };
+        let text = format!(
+            r#"{{"arg":{{"channel":"orders","instType":"ANY","uid":"1"}},"data":[{},{},{}]}}"#,
+            order("1", "live"),
+            order("2", "pending_amend"),
+            order("3", "mmp_canceled"),
+        );
+
+        let updates = super::super::ws::parse_data::<OkxOrderData>(text.into()).unwrap();
+        let statuses: Vec<_> = updates.iter().map(|update| update.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                OrderStatus::New,
+                OrderStatus::Unknown,
+                OrderStatus::Canceled
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_order_type_cannot_be_placed() {
+        assert!(
+            OkxPlaceOrderRequest::try_from(new_order(OrderType::Unknown, TradeMode::Cash)).is_err()
+        );
+    }
+
+    #[test]
+    fn channel_names_round_trip() {
+        for (channel, name) in OkxChannel::NAMES {
+            assert_eq!(channel.to_string(), name);
+            assert_eq!(name.parse::<OkxChannel>().unwrap(), channel);
+        }
+        for (interval, name) in [
+            (CandleInterval::M1, "candle1m"),
+            (CandleInterval::Month1, "candle1M"),
+            (CandleInterval::D1utc, "candle1Dutc"),
+        ] {
+            assert_eq!(OkxChannel::Candle(interval).to_string(), name);
+            assert_eq!(
+                name.parse::<OkxChannel>().unwrap(),
+                OkxChannel::Candle(interval)
+            );
+        }
+        assert!("candle1Dx".parse::<OkxChannel>().is_err());
+        assert!("tickers2".parse::<OkxChannel>().is_err());
+    }
+
+    #[test]
+    fn arg_serializes_channel_name() {
+        let arg = OkxArg::candle(CandleInterval::H4, "BTC-USDT");
+        let json = simd_json::to_string(&arg).unwrap();
+        assert_eq!(json, r#"{"channel":"candle4H","instId":"BTC-USDT"}"#);
+        let parsed: OkxArg = simd_json::serde::from_slice(&mut json.into_bytes()).unwrap();
+        assert_eq!(parsed, arg);
+    }
+}
@@ -0,0 +1,99 @@
+use super::error::OkxError;
+use crate::client::rate_limit::{RateLimit, RateLimitKey, RateLimited, RateLimiter};
+use std::time::Duration;
+
+/// REST 请求过于频繁
+pub(super) const OKX_CODE_TOO_MANY_REQUESTS: &str = "50011";
+/// WebSocket 请求过于频繁
+pub(super) const OKX_CODE_WS_TOO_MANY_REQUESTS: &str = "60014";
+
+const TWO_SECONDS: Duration = Duration::from_secs(2);
+
+/// 每个 IP 每秒最多建立 3 个 WebSocket 连接
+pub(super) const WS_CONNECT_RULE: &str = "ws/connect";
+pub(super) const WS_CONNECT_LIMIT: RateLimit = RateLimit::new(3, Duration::from_secs(1));
+/// 每个连接每小时最多 480 次 subscribe/unsubscribe/login 请求
+pub(super) const WS_OP_RULE: &str = "ws/op";
+pub(super) const WS_OP_LIMIT: RateLimit = RateLimit::new(480, Duration::from_secs(3600));
+/// 收到 60014 后暂停 WebSocket 请求的初始时长
+pub(super) const WS_OP_PAUSE: Duration = Duration::from_secs(1);
+
+/// OKX REST 接口的限流规则，未列出的接口按最严格的 10次/2s 处理
+///
+/// 下单、撤单与改单按产品ID限流，其余接口按用户或 IP 限流。
+pub(super) fn rest_limit(path: &str) -> RateLimit {
+    match path {
+        "api/v5/market/candles" => RateLimit::new(40, TWO_SECONDS),
+        "api/v5/market/history-candles" | "api/v5/public/instruments" => {
+            RateLimit::new(20, TWO_SECONDS)
+        }
+        "api/v5/trade/order" | "api/v5/trade/cancel-order" | "api/v5/trade/amend-order" => {
+            RateLimit::new(60, TWO_SECONDS)
+        }
+        // 按订单数计数
+        "api/v5/trade/batch-orders" | "api/v5/trade/cancel-batch-orders" => {
+            RateLimit::new(300, TWO_SECONDS)
+        }
+        _ => RateLimit::new(10, TWO_SECONDS),
+    }
+}
+
+/// 按 `path` 对应的限流规则取得 `cost` 个令牌
+pub(super) async fn acquire(
+    limiter: &RateLimiter,
+    path: &str,
+    scope: &str,
+    cost: u32,
+) -> Result<(), OkxError> {
+    limiter
+        .acquire(&RateLimitKey::new(path, scope), rest_limit(path), cost)
+        .await?;
+
+    Ok(())
+}
+
+/// 收到 HTTP 429 或 50011 时调用，暂停该规则下的请求一个周期，连续触发时翻倍
+pub(super) fn penalize(limiter: &RateLimiter, path: &str, scope: &str) -> OkxError {
+    let key = RateLimitKey::new(path, scope);
+    let limit = rest_limit(path);
+    let retry_after = limiter.penalize(&key, limit, limit.period);
+
+    RateLimited { key, retry_after }.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::rate_limit::RateLimitMode;
+
+    #[tokio::test(start_paused = true)]
+    async fn limits_rest_requests_by_path_and_scope() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        let path = "api/v5/trade/order";
+        assert_eq!(rest_limit(path), RateLimit::new(60, TWO_SECONDS));
+        assert_eq!(
+            rest_limit("api/v5/unknown"),
+            RateLimit::new(10, TWO_SECONDS)
+        );
+
+        for _ in 0..60 {
+            acquire(&limiter, path, "BTC-USDT", 1).await.unwrap();
+        }
+        let err = acquire(&limiter, path, "BTC-USDT", 1).await.unwrap_err();
+        assert!(matches!(err, OkxError::RateLimited(_)));
+        assert!(err.is_retryable());
+        acquire(&limiter, path, "ETH-USDT", 1).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn penalizes_for_one_period() {
+        let limiter = RateLimiter::default();
+        let path = "api/v5/market/candles";
+
+        let err = penalize(&limiter, path, "");
+        assert_eq!(err.retry_after(), Some(TWO_SECONDS));
+        let start = tokio::time::Instant::now();
+        acquire(&limiter, path, "", 1).await.unwrap();
+        assert_eq!(start.elapsed(), TWO_SECONDS);
+    }
+}
@@ -0,0 +1,383 @@
+use super::{error::OkxError, model::*, rate_limit};
+use crate::client::{
+    RawData,
+    rate_limit::{RateLimitMode, RateLimiter},
+    retry::RetryPolicy,
+};
+use bytestring::ByteString;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::future::Future;
+
+/// 只解析 `code`/`msg`，`data` 可能与成功时的格式不同
+#[derive(Debug, Deserialize)]
+struct OkxResponseHeader {
+    code: ByteString,
+    #[serde(default)]
+    msg: ByteString,
+}
+
+/// 解析响应，`code` 非零、HTTP 状态码非 2xx 或无法解析时返回 [`OkxError`]
+///
+/// 收到 HTTP 429 或 50011 时按 `path` 对应的限流规则暂停后续请求。
+pub(super) async fn read_response<D>(
+    limiter: &RateLimiter,
+    path: &str,
+    scope: &str,
+    resp: Response,
+) -> Result<OkxHttpResponse<D>, OkxError>
+where
+    D: RawData + DeserializeOwned,
+{
+    let status = resp.status();
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(rate_limit::penalize(limiter, path, scope));
+    }
+    let body = resp.bytes().await.map_err(OkxError::Transport)?;
+
+    let decoded = simd_json::serde::from_slice::<OkxHttpResponse<D>>(&mut body.to_vec());
+    if let Ok(resp) = &decoded
+        && resp.code == OKX_CODE_SUCCESS
+        && status.is_success()
+    {
+        return decoded.map_err(OkxError::Decode);
+    }
+
+    let header = match simd_json::serde::from_slice::<OkxResponseHeader>(&mut body.to_vec()) {
+        Ok(header) => header,
+        Err(_) if status == StatusCode::UNAUTHORIZED => {
+            return Err(OkxError::Auth {
+                code: ByteString::new(),
+                msg: body_text(&body),
+            });
+        }
+        Err(_) if !status.is_success() => {
+            return Err(OkxError::Status {
+                status,
+                body: body_text(&body),
+            });
+        }
+        Err(err) => return Err(OkxError::Decode(err)),
+    };
+
+    if header.code == rate_limit::OKX_CODE_TOO_MANY_REQUESTS {
+        return Err(rate_limit::penalize(limiter, path, scope));
+    }
+    if header.code != OKX_CODE_SUCCESS {
+        // 下单类接口失败时 data 中仍有每个订单的 sCode/sMsg，其他接口的 data 通常为空
+        let data =
+            simd_json::serde::from_slice::<OkxHttpResponse<OkxOrderResult>>(&mut body.to_vec())
+                .map(|resp| resp.data)
+                .unwrap_or_default();
+        return Err(OkxError::from_code(header.code, header.msg, data));
+    }
+    if !status.is_success() {
+        return Err(OkxError::Status {
+            status,
+            body: body_text(&body),
+        });
+    }
+
+    decoded.map_err(OkxError::Decode)
+}
+
+/// 发送 REST 请求所需的共享状态，可以克隆到后台任务中
+#[derive(Debug, Clone)]
+pub(super) struct RestClient {
+    pub client: Client,
+    pub rate_limiter: RateLimiter,
+    pub retry: RetryPolicy,
+}
+
+impl RestClient {
+    pub fn new(rate_limiter: RateLimiter, retry: RetryPolicy) -> eyre::Result<Self> {
+        let client = Client::builder().timeout(retry.timeout).build()?;
+
+        Ok(Self {
+            client,
+            rate_limiter,
+            retry,
+        })
+    }
+
+    /// 按限流规则发送不需要签名的请求并解析响应，可重试的错误按重试策略重试
+    pub async fn send<D>(
+        &self,
+        path: &str,
+        scope: &str,
+        cost: u32,
+        request: RequestBuilder,
+    ) -> Result<OkxHttpResponse<D>, OkxError>
+    where
+        D: RawData + DeserializeOwned,
+    {
+        self.retry(|| async {
+            let request = request
+                .try_clone()
+                .expect("requests without a streaming body can be cloned");
+            rate_limit::acquire(&self.rate_limiter, path, scope, cost).await?;
+            let resp = request.send().await.map_err(OkxError::Transport)?;
+
+            read_response(&self.rate_limiter, path, scope, resp).await
+        })
+        .await
+    }
+
+    /// 按重试策略执行 `op`。限流器为 [`RateLimitMode::FailFast`] 时被限流的请求不重试
+    pub async fn retry<T, E, F, Fut>(&self, op: F) -> Result<T, E>
+    where
+        E: AsOkxError,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.retry
+            .run(op, |err| {
+                let err = err.as_okx_error()?;
+                match err {
+                    OkxError::RateLimited(_)
+                        if self.rate_limiter.mode() == RateLimitMode::FailFast =>
+                    {
+                        None
+                    }
+                    err if err.is_retryable() => Some(err.retry_after().unwrap_or_default()),
+                    _ => None,
+                }
+            })
+            .await
+    }
+}
+
+/// 从错误中取出 [`OkxError`]，用于判断是否重试
+pub(super) trait AsOkxError {
+    fn as_okx_error(&self) -> Option<&OkxError>;
+}
+
+impl AsOkxError for OkxError {
+    fn as_okx_error(&self) -> Option<&OkxError> {
+        Some(self)
+    }
+}
+
+impl AsOkxError for eyre::Report {
+    fn as_okx_error(&self) -> Option<&OkxError> {
+        self.downcast_ref()
+    }
+}
+
+fn body_text(body: &[u8]) -> ByteString {
+    String::from_utf8_lossy(body).into_owned().into()
+}
+
+/// 按顺序返回 `responses` 的本地 HTTP 服务，每个连接只处理一个请求，全部返回后关闭
+///
+/// 返回服务地址与收到的请求（请求行与请求体）。
+#[cfg(test)]
+pub(super) async fn mock_server(
+    responses: Vec<(StatusCode, String)>,
+) -> (url::Url, flume::Receiver<String>) {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap())
+        .parse()
+        .unwrap();
+    let (requests, requests_rx) = flume::unbounded();
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut buf = Vec::new();
+            let mut chunk = [0; 4096];
+            let request = loop {
+                let n = stream.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                let text = String::from_utf8_lossy(&buf);
+                if let Some((head, rest)) = text.split_once("\r\n\r\n") {
+                    let len = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if rest.len() >= len || n == 0 {
+                        let line = head.lines().next().unwrap_or_default();
+                        break format!("{line}\n{rest}");
+                    }
+                }
+                if n == 0 {
+                    break text.into_owned();
+                }
+            };
+            let _ = requests.send(request);
+
+            let resp = format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(resp.as_bytes()).await.unwrap();
+            let _ = stream.shutdown().await;
+        }
+    });
+
+    (url, requests_rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "api/v5/trade/order";
+
+    async fn read(status: u16, body: &str) -> Result<OkxHttpResponse<OkxOrderResult>, OkxError> {
+        let resp = http::Response::builder()
+            .status(status)
+            .body(body.to_owned())
+            .unwrap();
+        read_response(&RateLimiter::default(), PATH, "BTC-USDT", resp.into()).await
+    }
+
+    /// 错误的种类，便于在表格中比较
+    fn kind(err: &OkxError) -> &'static str {
+        match err {
+            OkxError::Transport(_) => "transport",
+            OkxError::Status { .. } => "status",
+            OkxError::Decode(_) => "decode",
+            OkxError::Api { .. } => "api",
+            OkxError::Order { .. } => "order",
+            OkxError::RateLimited(_) => "rate_limited",
+            OkxError::Auth { .. } => "auth",
+        }
+    }
+
+    #[tokio::test]
+    async fn classifies_error_responses() {
+        // (HTTP 状态码, 响应体, 错误种类, 错误码, 是否可重试)
+        let cases: &[(u16, &str, &str, Option<&str>, bool)] = &[
+            (429, "", "rate_limited", None, true),
+            (
+                200,
+                r#"{"code":"50011","msg":"Too Many Requests","data":[]}"#,
+                "rate_limited",
+                None,
+                true,
+            ),
+            (
+                401,
+                r#"{"code":"50111","msg":"Invalid OK-ACCESS-KEY","data":[]}"#,
+                "auth",
+                Some("50111"),
+                false,
+            ),
+            (
+                200,
+                r#"{"code":"50113","msg":"Invalid Sign","data":[]}"#,
+                "auth",
+                Some("50113"),
+                false,
+            ),
+            (401, "Unauthorized", "auth", None, false),
+            (502, "<html>Bad Gateway</html>", "status", None, true),
+            (404, "Not Found", "status", None, false),
+            (
+                503,
+                r#"{"code":"50001","msg":"Service temporarily unavailable","data":[]}"#,
+                "api",
+                Some("50001"),
+                true,
+            ),
+            (
+                200,
+                r#"{"code":"51000","msg":"Parameter instId error","data":[]}"#,
+                "api",
+                Some("51000"),
+                false,
+            ),
+            (
+                200,
+                r#"{"code":"1","msg":"","data":[{"ordId":"","clOrdId":"a","sCode":"51008","sMsg":"Insufficient balance"}]}"#,
+                "api",
+                Some("1"),
+                false,
+            ),
+            (200, "not json", "decode", None, false),
+            (
+                200,
+                r#"{"code":"0","msg":"","data":[{}]}"#,
+                "decode",
+                None,
+                false,
+            ),
+        ];
+
+        for &(status, body, expected, code, retryable) in cases {
+            let err = read(status, body).await.unwrap_err();
+            assert_eq!(kind(&err), expected, "{status} {body}: {err}");
+            assert_eq!(err.code(), code, "{status} {body}");
+            assert_eq!(err.is_retryable(), retryable, "{status} {body}");
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_per_order_results_of_failed_requests() {
+        let err = read(
+            200,
+            r#"{"code":"2","msg":"","data":[{"ordId":"1","sCode":"0"},{"ordId":"","sCode":"51008","sMsg":"Insufficient balance"}]}"#,
+        )
+        .await
+        .unwrap_err();
+        let OkxError::Api { data, .. } = &err else {
+            panic!("expected api error, got {err}");
+        };
+        let s_codes: Vec<&str> = data.iter().map(|result| &*result.s_code).collect();
+        assert_eq!(s_codes, ["0", "51008"]);
+        assert_eq!(
+            err.to_string(),
+            "OKX error 2: ; sCode 51008: Insufficient balance"
+        );
+
+        let order = OkxError::from(data[1].clone());
+        assert_eq!(order.code(), Some("51008"));
+        assert!(!order.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn accepts_successful_responses() {
+        let resp = read(
+            200,
+            r#"{"code":"0","msg":"","data":[{"ordId":"1","clOrdId":"a","sCode":"0","sMsg":""}]}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.data.len(), 1);
+        assert_eq!(resp.data[0].ord_id, "1");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_responses_pause_the_rule() {
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        let resp = http::Response::builder()
+            .status(429)
+            .body(String::new())
+            .unwrap();
+        let err = read_response::<OkxOrderResult>(&limiter, PATH, "BTC-USDT", resp.into())
+            .await
+            .unwrap_err();
+        assert_eq!(err.retry_after(), Some(std::time::Duration::from_secs(2)));
+        assert!(
+            rate_limit::acquire(&limiter, PATH, "BTC-USDT", 1)
+                .await
+                .is_err()
+        );
+        assert!(
+            rate_limit::acquire(&limiter, PATH, "ETH-USDT", 1)
+                .await
+                .is_ok()
+        );
+    }
+}
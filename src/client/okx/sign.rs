@@ -0,0 +1,233 @@
+use super::{
+    OkxClientV5,
+    error::OkxError,
+    model::{OkxLoginArg, OkxWebSocketLoginRequest},
+};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64_STANDARD_ENGINE};
+use bytestring::ByteString;
+use chrono::{DateTime, SecondsFormat, Utc};
+use eyre::Result;
+use hmac::{Hmac, Mac};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, header::CONTENT_TYPE};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub(super) const OK_ACCESS_KEY: HeaderName = HeaderName::from_static("ok-access-key");
+pub(super) const OK_ACCESS_SIGN: HeaderName = HeaderName::from_static("ok-access-sign");
+pub(super) const OK_ACCESS_TIMESTAMP: HeaderName = HeaderName::from_static("ok-access-timestamp");
+pub(super) const OK_ACCESS_PASSPHRASE: HeaderName = HeaderName::from_static("ok-access-passphrase");
+pub(super) const X_SIMULATED_TRADING: HeaderName = HeaderName::from_static("x-simulated-trading");
+
+/// REST 请求签名所需的时间戳
+/// 格式要求：ISO8601 格式，带毫秒，例如 2020-12-08T09:08:57.715Z
+pub fn okx_rest_timestamp(now: DateTime<Utc>) -> String {
+    now.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 生成预签名字符串 (pre-hash string)
+/// 格式: timestamp + method + requestPath + body
+pub fn okx_prehash(timestamp: &str, method: &Method, request_path: &str, body: &str) -> String {
+    let mut prehash = String::with_capacity(timestamp.len() + 6 + request_path.len() + body.len());
+    prehash.push_str(timestamp);
+    prehash.push_str(method.as_str());
+    prehash.push_str(request_path);
+    prehash.push_str(body);
+    prehash
+}
+
+/// 根据 OKX API 规则生成签名
+///
+/// # Arguments
+/// * `timestamp` - 与 `OK-ACCESS-TIMESTAMP` 请求头相同的时间戳。
+/// * `method` - HTTP 请求方法，例如 GET, POST。
+/// * `request_path` - 请求的路径，包含查询参数（如果存在）。
+///   例如："/api/v5/account/balance?ccy=BTC" 或 "/api/v5/trade/order"。
+/// * `body` - 请求体字符串。对于 GET 请求或没有请求体的 POST 请求，应为空字符串 ""。
+/// * `secret_key` - 你的 API SecretKey。
+///
+/// # Returns
+/// HMAC SHA256 签名的 Base64 编码
+pub fn generate_okx_signature(
+    timestamp: &str,
+    method: &Method,
+    request_path: &str,
+    body: &str,
+    secret_key: &str,
+) -> String {
+    let prehash = okx_prehash(timestamp, method, request_path, body);
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret_key.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(prehash.as_bytes());
+
+    BASE64_STANDARD_ENGINE.encode(mac.finalize().into_bytes())
+}
+
+/// WebSocket 登录签名使用的固定请求路径
+const OKX_WS_LOGIN_PATH: &str = "/users/self/verify";
+
+/// 私有接口所需的 API Key 三件套
+#[derive(Clone)]
+pub struct OkxCredentials {
+    pub api_key: ByteString,
+    pub api_passphrase: ByteString,
+    pub api_secret: ByteString,
+}
+
+impl std::fmt::Debug for OkxCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OkxCredentials")
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OkxCredentials {
+    /// 生成 WebSocket `login` 请求
+    ///
+    /// 签名方式与 REST 相同，但时间戳为 Unix 秒，且固定使用
+    /// `GET /users/self/verify` 作为预签名字符串。
+    pub fn ws_login(&self, now: DateTime<Utc>) -> OkxWebSocketLoginRequest {
+        let timestamp = now.timestamp().to_string();
+        let sign = generate_okx_signature(
+            &timestamp,
+            &Method::GET,
+            OKX_WS_LOGIN_PATH,
+            "",
+            &self.api_secret,
+        );
+
+        OkxWebSocketLoginRequest {
+            op: "login".into(),
+            args: vec![OkxLoginArg {
+                api_key: self.api_key.clone(),
+                passphrase: self.api_passphrase.clone(),
+                timestamp: timestamp.into(),
+                sign: sign.into(),
+            }],
+        }
+    }
+}
+
+impl OkxClientV5 {
+    pub(super) fn credentials(&self) -> Result<OkxCredentials> {
+        let required = |value: &Option<ByteString>, name: &str| {
+            value.clone().ok_or_else(|| {
+                eyre::Report::from(OkxError::Auth {
+                    code: ByteString::new(),
+                    msg: format!("{name} is required for private endpoints").into(),
+                })
+            })
+        };
+
+        Ok(OkxCredentials {
+            api_key: required(&self.api_key, "OK_ACCESS_KEY")?,
+            api_passphrase: required(&self.api_passphrase, "OK_ACCESS_PASSPHRASE")?,
+            api_secret: required(&self.api_secret, "OK_ACCESS_SECRET")?,
+        })
+    }
+
+    /// 为私有接口生成鉴权请求头
+    pub(super) fn generate_okx_headers(
+        &self,
+        method: &Method,
+        request_path: &str,
+        body: &str,
+    ) -> Result<HeaderMap> {
+        let credentials = self.credentials()?;
+
+        let timestamp = okx_rest_timestamp(self.clock.now());
+        let signature = generate_okx_signature(
+            &timestamp,
+            method,
+            request_path,
+            body,
+            &credentials.api_secret,
+        );
+
+        let mut headers = HeaderMap::with_capacity(6);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(OK_ACCESS_KEY, HeaderValue::from_str(&credentials.api_key)?);
+        headers.insert(OK_ACCESS_SIGN, HeaderValue::from_str(&signature)?);
+        headers.insert(OK_ACCESS_TIMESTAMP, HeaderValue::from_str(&timestamp)?);
+        headers.insert(
+            OK_ACCESS_PASSPHRASE,
+            HeaderValue::from_str(&credentials.api_passphrase)?,
+        );
+        if self.simulated_trading {
+            headers.insert(X_SIMULATED_TRADING, HeaderValue::from_static("1"));
+        }
+
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// OKX 文档中的示例 SecretKey
+    const SECRET: &str = "22582BD0CFF14C41EDBF1AB98506286D";
+
+    #[test]
+    fn rest_timestamp_has_millis() {
+        let now = DateTime::from_timestamp_millis(1_607_418_537_715).unwrap();
+        assert_eq!(okx_rest_timestamp(now), "2020-12-08T09:08:57.715Z");
+    }
+
+    #[test]
+    fn rest_prehash_and_signature() {
+        let timestamp = "2020-12-08T09:08:57.715Z";
+        let path = "/api/v5/account/balance?ccy=BTC";
+        assert_eq!(
+            okx_prehash(timestamp, &Method::GET, path, ""),
+            "2020-12-08T09:08:57.715ZGET/api/v5/account/balance?ccy=BTC"
+        );
+        assert_eq!(
+            generate_okx_signature(timestamp, &Method::GET, path, "", SECRET),
+            "HiZhvSfMtWJA3uUIVXV3a/bSXNPCWvYFXoGCVS8V4zY="
+        );
+
+        let body =
+            r#"{"instId":"BTC-USDT","tdMode":"cash","side":"buy","ordType":"market","sz":"100"}"#;
+        assert_eq!(
+            okx_prehash(timestamp, &Method::POST, "/api/v5/trade/order", body),
+            format!("2020-12-08T09:08:57.715ZPOST/api/v5/trade/order{body}")
+        );
+        assert_eq!(
+            generate_okx_signature(
+                timestamp,
+                &Method::POST,
+                "/api/v5/trade/order",
+                body,
+                SECRET
+            ),
+            "gLoEHvSwOThUNff+Hv0T7J9JHtjIwa/gKHKIkc4vvhM="
+        );
+    }
+
+    #[test]
+    fn ws_login_signs_verify_path_with_seconds() {
+        let credentials = OkxCredentials {
+            api_key: "985d5b66-57ce-40fb-b714-afc0b9787083".into(),
+            api_passphrase: "123456".into(),
+            api_secret: SECRET.into(),
+        };
+        let now = DateTime::from_timestamp_millis(1_538_054_050_975).unwrap();
+
+        let request = credentials.ws_login(now);
+        assert_eq!(request.op, "login");
+        let [arg] = request.args.as_slice() else {
+            panic!("expected one login arg");
+        };
+        assert_eq!(arg.api_key, credentials.api_key);
+        assert_eq!(arg.passphrase, "123456");
+        assert_eq!(arg.timestamp, "1538054050");
+        assert_eq!(
+            okx_prehash(&arg.timestamp, &Method::GET, OKX_WS_LOGIN_PATH, ""),
+            "1538054050GET/users/self/verify"
+        );
+        assert_eq!(arg.sign, "+LdIr8lkkvhr5hoA3g9TMC0+uQJ849ftAcocA/ouu4M=");
+    }
+}
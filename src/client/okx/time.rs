@@ -0,0 +1,166 @@
+use super::{
+    OkxClientV5,
+    error::OkxError,
+    model::*,
+    rate_limit,
+    rest::{self, RestClient},
+};
+use crate::clock::{Clock, ClockSample};
+use chrono::{DateTime, Utc};
+use eyre::{ContextCompat, Result};
+use futures_util::{Stream, stream};
+use std::time::Duration;
+use tokio::time::{MissedTickBehavior, interval};
+use url::Url;
+
+const TIME_PATH: &str = "api/v5/public/time";
+/// 每次对时发送的请求数，取往返时间最短的一次，减少网络抖动的影响
+const SAMPLES_PER_SYNC: usize = 3;
+
+async fn sample_time(rest: &RestClient, url: &Url) -> Result<ClockSample> {
+    // 先取得令牌，限流等待不计入往返时间
+    rate_limit::acquire(&rest.rate_limiter, TIME_PATH, "", 1).await?;
+
+    let sent = Utc::now();
+    let resp = rest
+        .client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(OkxError::Transport)?;
+    let received = Utc::now();
+
+    let resp =
+        rest::read_response::<OkxServerTimeData>(&rest.rate_limiter, TIME_PATH, "", resp).await?;
+
+    let ts = resp
+        .data
+        .into_iter()
+        .next()
+        .wrap_err("Empty OKX server time response")?
+        .timestamp()?;
+    let server = DateTime::from_timestamp_millis(ts as i64)
+        .wrap_err_with(|| format!("Invalid OKX server time: {ts}"))?;
+
+    Ok(ClockSample::new(sent, server, received))
+}
+
+async fn sync_time(rest: &RestClient, url: &Url, clock: &Clock) -> Result<ClockSample> {
+    let mut best: Option<ClockSample> = None;
+    for _ in 0..SAMPLES_PER_SYNC {
+        let sample = rest.retry(|| sample_time(rest, url)).await?;
+        if best.is_none_or(|best| sample.round_trip < best.round_trip) {
+            best = Some(sample);
+        }
+    }
+
+    let sample = best.wrap_err("No OKX server time sample")?;
+    clock.update(sample);
+
+    Ok(sample)
+}
+
+impl OkxClientV5 {
+    /// 校正后的时钟，签名使用该时钟的时间
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// 请求服务器时间并校正时钟
+    pub async fn sync_time(&self) -> Result<ClockSample> {
+        let url = self.base_http_url.join(TIME_PATH)?;
+
+        sync_time(&self.rest, &url, &self.clock).await
+    }
+
+    /// 立即对时，之后每隔 `period` 对时一次。
+    ///
+    /// 返回的流产出每次对时的结果，需要持续轮询时钟才会更新。
+    /// 偏差超过 `max_skew` 时产出一个 [`ClockSkew`](crate::clock::ClockSkew) 错误；
+    /// OKX 会拒绝时间戳偏差超过 30 秒的签名请求。
+    pub fn sync_clock(
+        &self,
+        period: Duration,
+        max_skew: Duration,
+    ) -> Result<impl Stream<Item = Result<ClockSample>> + Send + use<>> {
+        let rest = self.rest.clone();
+        let url = self.base_http_url.join(TIME_PATH)?;
+        let clock = self.clock.clone();
+
+        let mut ticker = interval(period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Ok(stream::unfold(
+            (ticker, rest, url, clock),
+            move |(mut ticker, rest, url, clock)| async move {
+                ticker.tick().await;
+                let sample = sync_time(&rest, &url, &clock).await.and_then(|sample| {
+                    clock.check_skew(max_skew)?;
+                    Ok(sample)
+                });
+                Some((sample, (ticker, rest, url, clock)))
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockSkew;
+    use futures_util::StreamExt;
+    use reqwest::StatusCode;
+
+    /// 服务器时间比本地时间快 `offset` 毫秒的响应
+    fn responses(offset: i64) -> Vec<(StatusCode, String)> {
+        (0..SAMPLES_PER_SYNC)
+            .map(|_| {
+                let ts = Utc::now().timestamp_millis() + offset;
+                let body = format!(r#"{{"code":"0","msg":"","data":[{{"ts":"{ts}"}}]}}"#);
+                (StatusCode::OK, body)
+            })
+            .collect()
+    }
+
+    async fn client_with_offset(offset: i64) -> OkxClientV5 {
+        let (url, _) = rest::mock_server(responses(offset)).await;
+        OkxClientV5::builder()
+            .base_http_url(url.as_str())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn syncs_clock_to_server_time() {
+        let client = client_with_offset(60_000).await;
+
+        let sample = client.sync_time().await.unwrap();
+        let error = sample.offset - chrono::TimeDelta::seconds(60);
+        assert!(error.num_milliseconds().abs() < 1_000, "{error}");
+        assert_eq!(
+            client.clock().offset().num_milliseconds(),
+            sample.offset.num_milliseconds()
+        );
+    }
+
+    #[tokio::test]
+    async fn alarms_when_skew_exceeds_threshold() {
+        let client = client_with_offset(60_000).await;
+        let mut samples = client
+            .sync_clock(Duration::from_secs(3600), Duration::from_secs(30))
+            .unwrap()
+            .boxed();
+        let err = samples.next().await.unwrap().unwrap_err();
+        let skew = err.downcast_ref::<ClockSkew>().unwrap();
+        assert_eq!(skew.threshold, Duration::from_secs(30));
+        // 校正后的时钟照常使用
+        assert!(client.clock().offset() > chrono::TimeDelta::seconds(59));
+
+        let client = client_with_offset(5_000).await;
+        let mut samples = client
+            .sync_clock(Duration::from_secs(3600), Duration::from_secs(30))
+            .unwrap()
+            .boxed();
+        assert!(samples.next().await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,430 @@
+use super::{OkxClientV5, error::OkxError, model::*};
+use crate::{
+    client::OrderExecutor,
+    order::{AmendOrder, CancelOrder, NewOrder, OrderAck},
+};
+use eyre::{Result, bail, eyre};
+use futures_util::future::join_all;
+use serde::Serialize;
+
+const PLACE_ORDER_PATH: &str = "api/v5/trade/order";
+const CANCEL_ORDER_PATH: &str = "api/v5/trade/cancel-order";
+const AMEND_ORDER_PATH: &str = "api/v5/trade/amend-order";
+const BATCH_ORDERS_PATH: &str = "api/v5/trade/batch-orders";
+const CANCEL_BATCH_ORDERS_PATH: &str = "api/v5/trade/cancel-batch-orders";
+
+/// 批量接口单次最多包含的订单数
+pub const OKX_BATCH_LIMIT: usize = 20;
+
+impl OkxClientV5 {
+    /// 下单。只有指定了 `cl_ord_id` 时才会按重试策略重试，重复提交的订单会被 OKX 以
+    /// clOrdId 重复拒绝，不会重复成交
+    pub async fn place_okx_order(&self, params: &OkxPlaceOrderRequest) -> Result<OrderAck> {
+        let idempotent = params.cl_ord_id.is_some();
+        let resp = self
+            .post_order(PLACE_ORDER_PATH, params, &params.inst_id, 1, idempotent)
+            .await;
+        single_order_ack(resp)
+    }
+
+    /// 撤单。不重试：首次请求成功但响应丢失时，重试会因订单已撤销（51400）而失败，
+    /// 把成功的撤单报告为错误
+    pub async fn cancel_okx_order(&self, params: &OkxCancelOrderRequest) -> Result<OrderAck> {
+        let resp = self
+            .post_order(CANCEL_ORDER_PATH, params, &params.inst_id, 1, false)
+            .await;
+        single_order_ack(resp)
+    }
+
+    /// 修改未完成的订单。与撤单一样不重试，订单修改后可能已经成交，重试会返回错误
+    pub async fn amend_okx_order(&self, params: &OkxAmendOrderRequest) -> Result<OrderAck> {
+        let resp = self
+            .post_order(AMEND_ORDER_PATH, params, &params.inst_id, 1, false)
+            .await;
+        single_order_ack(resp)
+    }
+
+    /// 批量下单，超过 [`OKX_BATCH_LIMIT`] 时自动分批并发发送。
+    /// 返回与 `params` 一一对应的结果，部分失败不会影响其他订单。
+    /// 只有所有订单都指定了 `cl_ord_id` 时才会重试。
+    pub async fn place_okx_orders(&self, params: &[OkxPlaceOrderRequest]) -> Vec<Result<OrderAck>> {
+        let idempotent = params.iter().all(|params| params.cl_ord_id.is_some());
+        self.post_batch(BATCH_ORDERS_PATH, params, idempotent).await
+    }
+
+    /// 批量撤单，超过 [`OKX_BATCH_LIMIT`] 时自动分批并发发送。
+    /// 返回与 `params` 一一对应的结果，部分失败不会影响其他订单。与单个撤单一样不重试。
+    pub async fn cancel_okx_orders(
+        &self,
+        params: &[OkxCancelOrderRequest],
+    ) -> Vec<Result<OrderAck>> {
+        self.post_batch(CANCEL_BATCH_ORDERS_PATH, params, false)
+            .await
+    }
+
+    async fn post_batch<B>(
+        &self,
+        path: &str,
+        params: &[B],
+        idempotent: bool,
+    ) -> Vec<Result<OrderAck>>
+    where
+        B: Serialize + Sync,
+    {
+        send_batches(params, |chunk| {
+            self.post_order(path, chunk, "", chunk.len() as u32, idempotent)
+        })
+        .await
+    }
+
+    /// 发送订单请求。`idempotent` 为 false 时不重试，避免超时后重复下单
+    async fn post_order<B>(
+        &self,
+        path: &str,
+        body: &B,
+        scope: &str,
+        cost: u32,
+        idempotent: bool,
+    ) -> Result<OkxHttpResponse<OkxOrderResult>>
+    where
+        B: Serialize + ?Sized,
+    {
+        let send = || self.post_private(path, body, scope, cost);
+        if idempotent {
+            self.rest.retry(send).await
+        } else {
+            send().await
+        }
+    }
+}
+
+/// 按 [`OKX_BATCH_LIMIT`] 将 `params` 分批，用 `send` 并发发送，结果按 `params` 的顺序返回
+async fn send_batches<'a, B, F, Fut>(params: &'a [B], send: F) -> Vec<Result<OrderAck>>
+where
+    F: Fn(&'a [B]) -> Fut,
+    Fut: Future<Output = Result<OkxHttpResponse<OkxOrderResult>>>,
+{
+    let batches = params.chunks(OKX_BATCH_LIMIT).map(|chunk| {
+        let resp = send(chunk);
+        async move { batch_order_acks(resp.await, chunk.len()) }
+    });
+
+    join_all(batches).await.into_iter().flatten().collect()
+}
+
+/// 单个订单请求的结果。订单被拒绝时整个请求的 `code` 非零，`data` 中的 `sCode`/`sMsg` 更具体
+fn single_order_ack(resp: Result<OkxHttpResponse<OkxOrderResult>>) -> Result<OrderAck> {
+    match order_results(resp)?.into_iter().next() {
+        Some(result) => OrderAck::try_from(result),
+        None => bail!("OKX returned no order result"),
+    }
+}
+
+/// 批量请求的结果按请求顺序返回；整批失败时每个订单都会得到一个错误
+fn batch_order_acks(
+    resp: Result<OkxHttpResponse<OkxOrderResult>>,
+    len: usize,
+) -> Vec<Result<OrderAck>> {
+    match order_results(resp) {
+        Ok(data) if data.len() == len => data.into_iter().map(OrderAck::try_from).collect(),
+        Ok(data) => (0..len)
+            .map(|_| {
+                Err(eyre!(
+                    "OKX returned {} results for a batch of {len} orders",
+                    data.len()
+                ))
+            })
+            .collect(),
+        Err(err) => (0..len)
+            .map(|_| Err(eyre!("Batch order request failed: {err:#}")))
+            .collect(),
+    }
+}
+
+/// 取出每个订单的结果。部分或全部订单失败时 OKX 返回非零的 `code`，
+/// 但 `data` 中仍有每个订单的结果，此时交给调用方按 `sCode` 逐个处理
+fn order_results(resp: Result<OkxHttpResponse<OkxOrderResult>>) -> Result<Vec<OkxOrderResult>> {
+    match resp {
+        Ok(resp) => Ok(resp.data),
+        Err(err) => match err.downcast::<OkxError>() {
+            Ok(OkxError::Api { data, .. }) if !data.is_empty() => Ok(data),
+            Ok(err) => Err(err.into()),
+            Err(err) => Err(err),
+        },
+    }
+}
+
+/// 将转换失败的订单结果与批量请求的结果按原顺序合并
+fn merge_batch_results(
+    slots: Vec<Option<Result<OrderAck>>>,
+    acks: Vec<Result<OrderAck>>,
+) -> Vec<Result<OrderAck>> {
+    let mut acks = acks.into_iter();
+    slots
+        .into_iter()
+        .map(|slot| {
+            slot.unwrap_or_else(|| {
+                acks.next()
+                    .expect("batch returns one result per submitted order")
+            })
+        })
+        .collect()
+}
+
+impl OrderExecutor for OkxClientV5 {
+    async fn place_order(&mut self, order: NewOrder) -> Result<OrderAck> {
+        self.place_okx_order(&order.try_into()?).await
+    }
+
+    async fn cancel_order(&mut self, cancel: CancelOrder) -> Result<OrderAck> {
+        self.cancel_okx_order(&cancel.into()).await
+    }
+
+    async fn amend_order(&mut self, amend: AmendOrder) -> Result<OrderAck> {
+        self.amend_okx_order(&amend.try_into()?).await
+    }
+
+    async fn place_orders(&mut self, orders: Vec<NewOrder>) -> Vec<Result<OrderAck>> {
+        let mut requests = Vec::with_capacity(orders.len());
+        let slots = orders
+            .into_iter()
+            .map(|order| match OkxPlaceOrderRequest::try_from(order) {
+                Ok(request) => {
+                    requests.push(request);
+                    None
+                }
+                Err(err) => Some(Err(err)),
+            })
+            .collect();
+
+        let acks = self.place_okx_orders(&requests).await;
+        merge_batch_results(slots, acks)
+    }
+
+    async fn cancel_orders(&mut self, cancels: Vec<CancelOrder>) -> Vec<Result<OrderAck>> {
+        let requests = cancels
+            .into_iter()
+            .map(OkxCancelOrderRequest::from)
+            .collect::<Vec<_>>();
+
+        self.cancel_okx_orders(&requests).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::{backoff::Backoff, retry::RetryPolicy},
+        order::{OrderType, Side, TradeMode},
+    };
+    use bytestring::ByteString;
+    use reqwest::StatusCode;
+    use std::{sync::Mutex, time::Duration};
+
+    fn result(id: usize, s_code: &str) -> OkxOrderResult {
+        OkxOrderResult {
+            ord_id: id.to_string().into(),
+            cl_ord_id: ByteString::new(),
+            tag: ByteString::new(),
+            req_id: ByteString::new(),
+            s_code: s_code.into(),
+            s_msg: ByteString::new(),
+        }
+    }
+
+    fn response(chunk: &[usize], rejected: &[usize]) -> Result<OkxHttpResponse<OkxOrderResult>> {
+        let data: Vec<_> = chunk
+            .iter()
+            .map(|&id| result(id, if rejected.contains(&id) { "51008" } else { "0" }))
+            .collect();
+        if data.iter().all(|result| result.s_code == OKX_CODE_SUCCESS) {
+            return Ok(OkxHttpResponse {
+                code: OKX_CODE_SUCCESS.into(),
+                msg: ByteString::new(),
+                data,
+            });
+        }
+        // 部分订单失败时 OKX 返回非零的 code，data 中仍有每个订单的结果
+        Err(OkxError::Api {
+            code: "2".into(),
+            msg: "Batch operation partially succeeded".into(),
+            data,
+        }
+        .into())
+    }
+
+    fn ack(id: usize) -> OrderAck {
+        OrderAck {
+            order_id: id.to_string().into(),
+            client_order_id: None,
+        }
+    }
+
+    fn s_code(result: &Result<OrderAck>) -> Option<&str> {
+        result
+            .as_ref()
+            .err()
+            .and_then(|err| err.downcast_ref::<OkxError>())
+            .and_then(OkxError::code)
+    }
+
+    #[tokio::test]
+    async fn splits_batches_and_keeps_order_with_partial_failures() {
+        let orders: Vec<usize> = (0..45).collect();
+        let sizes = Mutex::new(Vec::new());
+
+        let acks = send_batches(&orders, |chunk| {
+            sizes.lock().unwrap().push(chunk.len());
+            let resp = response(chunk, &[21, 39]);
+            async move { resp }
+        })
+        .await;
+
+        let mut sizes = sizes.into_inner().unwrap();
+        sizes.sort_unstable();
+        assert_eq!(sizes, [5, 20, 20]);
+        assert_eq!(acks.len(), 45);
+        for (id, acked) in acks.iter().enumerate() {
+            match id {
+                21 | 39 => assert_eq!(s_code(acked), Some("51008")),
+                id => assert_eq!(acked.as_ref().unwrap(), &ack(id)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_every_order_of_a_failed_batch() {
+        let orders: Vec<usize> = (0..25).collect();
+
+        let acks = send_batches(&orders, |chunk| {
+            let resp = if chunk.len() == OKX_BATCH_LIMIT {
+                response(chunk, &[])
+            } else {
+                Err(OkxError::Api {
+                    code: "50001".into(),
+                    msg: "Service temporarily unavailable".into(),
+                    data: Vec::new(),
+                }
+                .into())
+            };
+            async move { resp }
+        })
+        .await;
+
+        assert!(acks[..20].iter().all(Result::is_ok));
+        assert!(acks[20..].iter().all(Result::is_err));
+
+        // 返回的结果数与请求的订单数不一致时整批失败
+        let acks = send_batches(&orders[..3], |chunk| {
+            let resp = response(&chunk[..2], &[]);
+            async move { resp }
+        })
+        .await;
+        assert_eq!(acks.len(), 3);
+        assert!(acks.iter().all(Result::is_err));
+    }
+
+    async fn mock_client(
+        responses: Vec<(StatusCode, String)>,
+    ) -> (OkxClientV5, flume::Receiver<String>) {
+        let (url, requests) = super::super::rest::mock_server(responses).await;
+        let client = OkxClientV5::builder()
+            .base_http_url(url.as_str())
+            .api_key("key")
+            .api_passphrase("passphrase")
+            .api_secret("secret")
+            .retry(RetryPolicy {
+                backoff: Backoff {
+                    max_retries: 3,
+                    retry_delay: Duration::from_millis(1),
+                    backoff_factor: 1.0,
+                    jitter: false,
+                },
+                timeout: Duration::from_secs(5),
+            })
+            .build()
+            .unwrap();
+
+        (client, requests)
+    }
+
+    fn unavailable() -> (StatusCode, String) {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            r#"{"code":"50001","msg":"Service temporarily unavailable","data":[]}"#.to_owned(),
+        )
+    }
+
+    fn accepted() -> (StatusCode, String) {
+        (
+            StatusCode::OK,
+            r#"{"code":"0","msg":"","data":[{"ordId":"1","clOrdId":"a","sCode":"0","sMsg":""}]}"#
+                .to_owned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn retries_orders_with_client_order_id_only() {
+        let order = |cl_ord_id: Option<&str>| {
+            OkxPlaceOrderRequest::builder("BTC-USDT")
+                .td_mode(TradeMode::Cash)
+                .side(Side::Buy)
+                .ord_type(OrderType::Limit)
+                .sz("1".parse().unwrap())
+                .px("100".parse().unwrap())
+                .maybe_cl_ord_id(cl_ord_id)
+                .build()
+        };
+
+        let (client, requests) = mock_client(vec![unavailable(), accepted()]).await;
+        let ack = client.place_okx_order(&order(Some("a"))).await.unwrap();
+        assert_eq!(ack.order_id, "1");
+        assert_eq!(requests.len(), 2);
+
+        let (client, requests) = mock_client(vec![unavailable(), accepted()]).await;
+        assert!(client.place_okx_order(&order(None)).await.is_err());
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_cancel_or_amend() {
+        let (client, requests) = mock_client(vec![unavailable(), accepted()]).await;
+        let cancel = OkxCancelOrderRequest::builder("BTC-USDT")
+            .ord_id("1")
+            .build();
+        assert!(client.cancel_okx_order(&cancel).await.is_err());
+        assert_eq!(requests.len(), 1);
+
+        let (client, requests) = mock_client(vec![unavailable(), accepted()]).await;
+        let amend = OkxAmendOrderRequest::builder("BTC-USDT")
+            .ord_id("1")
+            .new_sz("2".parse().unwrap())
+            .build();
+        assert!(client.amend_okx_order(&amend).await.is_err());
+        assert_eq!(requests.len(), 1);
+
+        let (client, requests) = mock_client(vec![unavailable(), accepted()]).await;
+        let acks = client.cancel_okx_orders(&[cancel]).await;
+        assert!(acks[0].is_err());
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[test]
+    fn merges_conversion_errors_in_place() {
+        let slots = vec![
+            None,
+            Some(Err(eyre!("Invalid order"))),
+            None,
+            Some(Err(eyre!("Invalid order"))),
+        ];
+        let acks = vec![Ok(ack(0)), Err(OkxError::from(result(2, "51008")).into())];
+
+        let merged = merge_batch_results(slots, acks);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[0].as_ref().unwrap(), &ack(0));
+        assert!(merged[1].is_err() && s_code(&merged[1]).is_none());
+        assert_eq!(s_code(&merged[2]), Some("51008"));
+        assert!(merged[3].is_err());
+    }
+}
@@ -0,0 +1,977 @@
+use super::{
+    error::OkxError,
+    model::*,
+    rate_limit::{
+        OKX_CODE_WS_TOO_MANY_REQUESTS, WS_CONNECT_LIMIT, WS_CONNECT_RULE, WS_OP_LIMIT, WS_OP_PAUSE,
+        WS_OP_RULE,
+    },
+    sign::OkxCredentials,
+};
+use crate::{
+    client::{
+        RawData,
+        backoff::Backoff,
+        rate_limit::{RateLimitKey, RateLimiter},
+    },
+    clock::Clock,
+    data::{DataStream, StreamEvent},
+};
+use bytes::Bytes;
+use bytestring::ByteString;
+use eyre::{Result, bail, eyre};
+use futures_util::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, de::DeserializeOwned};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+use tokio::{net::TcpStream, time::Instant};
+use tokio_websockets::{MaybeTlsStream, Message, WebSocketStream};
+
+const OKX_PING: &str = "ping";
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+const OKX_PONG: &str = "pong";
+
+/// 路由到订阅者的一帧文本消息或连接状态变化
+pub type Frame = Result<StreamEvent<ByteString>>;
+
+/// OKX WebSocket 服务地址，不同频道需要连接不同的地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OkxWsEndpoint {
+    Public,
+    Business,
+    Private,
+}
+
+impl OkxWsEndpoint {
+    pub fn path(self) -> &'static str {
+        match self {
+            OkxWsEndpoint::Public => "ws/v5/public",
+            OkxWsEndpoint::Business => "ws/v5/business",
+            OkxWsEndpoint::Private => "ws/v5/private",
+        }
+    }
+
+    /// 根据频道选择服务地址
+    pub fn for_channel(channel: OkxChannel) -> Self {
+        match channel {
+            OkxChannel::TradesAll | OkxChannel::Candle(_) => OkxWsEndpoint::Business,
+            OkxChannel::Orders
+            | OkxChannel::Fills
+            | OkxChannel::Account
+            | OkxChannel::Positions
+            | OkxChannel::BalanceAndPosition => OkxWsEndpoint::Private,
+            _ => OkxWsEndpoint::Public,
+        }
+    }
+}
+
+/// 心跳参数。OKX 会断开 30 秒内没有任何消息的连接
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// 连接空闲多久后发送 "ping"
+    pub interval: Duration,
+    /// 发送 "ping" 后多久未收到 "pong" 视为连接已断开
+    pub timeout: Duration,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(20),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// 连接参数
+#[derive(Debug, Clone, Default)]
+pub struct OkxWsOptions {
+    pub backoff: Backoff,
+    pub heartbeat: Heartbeat,
+    /// 登录签名使用的时钟
+    pub clock: Clock,
+    /// 建立连接与 subscribe/unsubscribe/login 请求使用的限流器，令牌不足时总是等待
+    pub rate_limiter: RateLimiter,
+    /// 私有频道需要登录，连接和每次重连后都会先登录再订阅
+    pub credentials: Option<OkxCredentials>,
+}
+
+/// 推送消息按订阅参数的所有字段路由，只有频道名相同的订阅互不影响
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RouteKey {
+    channel: OkxChannel,
+    inst_type: Option<OkxInstType>,
+    inst_family: Option<ByteString>,
+    inst_id: Option<ByteString>,
+    ccy: Option<ByteString>,
+}
+
+impl RouteKey {
+    /// 订阅中未指定的字段匹配推送中的任意值
+    fn matches(&self, push: &RouteKey) -> bool {
+        fn field<T: PartialEq>(route: &Option<T>, push: &Option<T>) -> bool {
+            route.is_none() || route == push
+        }
+
+        self.channel == push.channel
+            && field(&self.inst_type, &push.inst_type)
+            && field(&self.inst_family, &push.inst_family)
+            && field(&self.inst_id, &push.inst_id)
+            && field(&self.ccy, &push.ccy)
+    }
+}
+
+impl From<&OkxArg> for RouteKey {
+    fn from(arg: &OkxArg) -> Self {
+        Self {
+            channel: arg.channel,
+            inst_type: arg.inst_type,
+            inst_family: arg.inst_family.clone(),
+            inst_id: arg.inst_id.clone(),
+            ccy: arg.ccy.clone(),
+        }
+    }
+}
+
+/// 只解析用于路由的字段，`data` 留给订阅者按具体类型解析
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameHeader {
+    event: Option<ByteString>,
+    id: Option<ByteString>,
+    code: Option<ByteString>,
+    msg: Option<ByteString>,
+    arg: Option<OkxArg>,
+}
+
+pub(super) enum Command {
+    Subscribe {
+        arg: OkxArg,
+        frames: flume::Sender<Frame>,
+    },
+    Unsubscribe {
+        arg: OkxArg,
+    },
+    /// 重新订阅，服务端会重新推送全量数据，已有的订阅者保持不变
+    Resubscribe {
+        arg: OkxArg,
+    },
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Command::Subscribe { arg, .. } => f.debug_tuple("Subscribe").field(arg).finish(),
+            Command::Unsubscribe { arg } => f.debug_tuple("Unsubscribe").field(arg).finish(),
+            Command::Resubscribe { arg } => f.debug_tuple("Resubscribe").field(arg).finish(),
+        }
+    }
+}
+
+/// 一个 OKX WebSocket 连接的句柄，可在多个订阅间共享
+///
+/// 连接由后台任务维护，所有句柄与订阅流都被 drop 后连接关闭。
+#[derive(Debug, Clone)]
+pub struct OkxWsConnection {
+    endpoint: OkxWsEndpoint,
+    commands: flume::Sender<Command>,
+}
+
+impl OkxWsConnection {
+    /// 建立连接，空闲时按心跳参数发送心跳，断线后按退避参数自动重连并重新订阅
+    pub async fn connect(
+        uri: &str,
+        endpoint: OkxWsEndpoint,
+        options: OkxWsOptions,
+    ) -> Result<Self> {
+        let OkxWsOptions {
+            backoff,
+            heartbeat,
+            clock,
+            rate_limiter,
+            credentials,
+        } = options;
+        let ws = connect_ws(uri, credentials.as_ref(), &clock, &rate_limiter).await?;
+
+        let (commands, commands_rx) = flume::unbounded();
+        let task = ConnectionTask {
+            uri: uri.to_owned(),
+            credentials,
+            clock,
+            rate_limiter,
+            backoff,
+            heartbeat,
+            ws,
+            last_received: Instant::now(),
+            ping_sent: None,
+            commands: commands_rx,
+            routes: Routes::default(),
+            ops: VecDeque::new(),
+            next_op: Instant::now(),
+            pending: HashMap::new(),
+            next_id: 0,
+        };
+        tokio::spawn(task.run());
+
+        Ok(Self { endpoint, commands })
+    }
+
+    pub fn endpoint(&self) -> OkxWsEndpoint {
+        self.endpoint
+    }
+
+    /// 重连失败后后台任务退出，连接不可再用
+    pub fn is_closed(&self) -> bool {
+        self.commands.is_disconnected()
+    }
+
+    /// 订阅一个频道，返回该频道的原始文本推送
+    ///
+    /// 同一频道可被多次订阅，只会向服务端发送一次订阅请求。
+    pub fn subscribe_raw(&self, arg: OkxArg) -> Result<flume::Receiver<Frame>> {
+        let (frames, frames_rx) = flume::unbounded();
+        self.send(Command::Subscribe { arg, frames })?;
+
+        Ok(frames_rx)
+    }
+
+    /// 订阅一个频道，并将推送解析为标准化的数据
+    pub fn subscribe<D>(
+        &self,
+        arg: OkxArg,
+    ) -> Result<impl Stream<Item = Result<StreamEvent<Vec<D::Data>>>> + Send + use<D>>
+    where
+        D: RawData + DeserializeOwned + Send + 'static,
+        D::Data: TryFrom<D, Error = eyre::Report> + Send + 'static,
+    {
+        self.subscribe_with(arg, parse_data::<D>)
+    }
+
+    /// 订阅一个频道，并用 `parse` 解析每一帧推送
+    pub fn subscribe_with<T>(
+        &self,
+        arg: OkxArg,
+        parse: fn(ByteString) -> Result<T>,
+    ) -> Result<impl Stream<Item = Result<StreamEvent<T>>> + Send + use<T>>
+    where
+        T: Send + 'static,
+    {
+        let frames = self.subscribe_raw(arg)?;
+
+        Ok(DataStream::new(
+            frames.into_stream(),
+            move |frame: Frame| frame?.try_map(parse),
+        ))
+    }
+
+    /// 取消订阅，该频道的所有订阅流都会结束，其它频道不受影响
+    pub fn unsubscribe(&self, arg: OkxArg) -> Result<()> {
+        self.send(Command::Unsubscribe { arg })
+    }
+
+    /// 在不影响订阅者的情况下重新订阅，用于从服务端重新获取全量数据
+    pub fn resubscribe(&self, arg: OkxArg) -> Result<()> {
+        self.send(Command::Resubscribe { arg })
+    }
+
+    /// 没有后台任务的连接，发出的命令由返回的接收端取得
+    #[cfg(test)]
+    pub(super) fn detached(endpoint: OkxWsEndpoint) -> (Self, flume::Receiver<Command>) {
+        let (commands, commands_rx) = flume::unbounded();
+        (Self { endpoint, commands }, commands_rx)
+    }
+
+    fn send(&self, command: Command) -> Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| eyre!("OKX {:?} WebSocket connection is closed", self.endpoint))
+    }
+}
+
+/// 将一帧数据推送解析为标准化的数据
+pub(super) fn parse_data<D>(text: ByteString) -> Result<Vec<D::Data>>
+where
+    D: RawData + DeserializeOwned,
+    D::Data: TryFrom<D, Error = eyre::Report>,
+{
+    let resp =
+        simd_json::serde::from_slice::<OkxWebSocketDataResponse<D>>(&mut text.as_bytes().to_vec())?;
+
+    resp.data.into_iter().map(D::Data::try_from).collect()
+}
+
+async fn connect_ws(
+    uri: &str,
+    credentials: Option<&OkxCredentials>,
+    clock: &Clock,
+    rate_limiter: &RateLimiter,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    rate_limiter
+        .wait(&RateLimitKey::new(WS_CONNECT_RULE, ""), WS_CONNECT_LIMIT, 1)
+        .await;
+    let (mut ws, _) = tokio_websockets::client::Builder::new()
+        .uri(uri)?
+        .connect()
+        .await?;
+
+    if let Some(credentials) = credentials {
+        rate_limiter.wait(&ws_op_key(uri), WS_OP_LIMIT, 1).await;
+        tokio::time::timeout(LOGIN_TIMEOUT, login(&mut ws, credentials, clock))
+            .await
+            .map_err(|_| eyre!("OKX WebSocket login timed out after {LOGIN_TIMEOUT:?}"))??;
+    }
+
+    Ok(ws)
+}
+
+/// subscribe/unsubscribe/login 请求按服务地址限流
+fn ws_op_key(uri: &str) -> RateLimitKey {
+    RateLimitKey::new(WS_OP_RULE, uri)
+}
+
+/// 发送 `login` 请求并等待登录结果
+async fn login(
+    ws: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    credentials: &OkxCredentials,
+    clock: &Clock,
+) -> Result<()> {
+    let params = credentials.ws_login(clock.now());
+    ws.send(Message::text(simd_json::serde::to_string(&params)?))
+        .await?;
+
+    while let Some(msg) = ws.next().await {
+        let msg = msg?;
+        let Some(text) = msg.as_text() else {
+            continue;
+        };
+        let event =
+            simd_json::serde::from_slice::<OkxWebSocketEvent>(&mut text.to_string().into_bytes())?;
+
+        return match (event.event.as_ref(), event.code.as_deref()) {
+            ("login", Some(OKX_CODE_SUCCESS) | None) => Ok(()),
+            _ => Err(OkxError::Auth {
+                code: event.code.unwrap_or_default(),
+                msg: event.msg.unwrap_or_default(),
+            }
+            .into()),
+        };
+    }
+
+    bail!("Connection closed during OKX WebSocket login")
+}
+
+/// 一个频道及其所有订阅者
+struct Route {
+    arg: OkxArg,
+    senders: Vec<flume::Sender<Frame>>,
+}
+
+/// 连接上的所有频道
+#[derive(Default)]
+struct Routes(HashMap<RouteKey, Route>);
+
+impl Routes {
+    /// 添加一个订阅者，返回是否为该频道的第一个订阅者
+    fn insert(&mut self, arg: OkxArg, frames: flume::Sender<Frame>) -> bool {
+        let route = self.0.entry(RouteKey::from(&arg)).or_insert_with(|| Route {
+            arg,
+            senders: Vec::new(),
+        });
+        route.senders.push(frames);
+
+        route.senders.len() == 1
+    }
+
+    fn remove(&mut self, arg: &OkxArg) -> Option<Route> {
+        self.0.remove(&RouteKey::from(arg))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn args(&self) -> Vec<OkxArg> {
+        self.0.values().map(|route| route.arg.clone()).collect()
+    }
+
+    /// 将推送发送给对应频道的所有订阅者，没有订阅者时移除该频道并返回其订阅参数
+    ///
+    /// 推送的 `arg` 可能比订阅参数多出一些字段，例如按产品类型订阅时带有具体的产品ID，
+    /// 此时匹配未指定这些字段的订阅。
+    fn deliver(&mut self, arg: &OkxArg, text: ByteString) -> Option<OkxArg> {
+        let push = RouteKey::from(arg);
+        let key = if self.0.contains_key(&push) {
+            push
+        } else {
+            self.0.keys().find(|key| key.matches(&push))?.clone()
+        };
+        let route = self.0.get_mut(&key)?;
+
+        route
+            .senders
+            .retain(|frames| frames.send(Ok(StreamEvent::Data(text.clone()))).is_ok());
+        if route.senders.is_empty() {
+            return self.0.remove(&key).map(|route| route.arg);
+        }
+
+        None
+    }
+
+    fn broadcast(&self, frame: impl Fn() -> Frame) {
+        for route in self.0.values() {
+            for frames in &route.senders {
+                let _ = frames.send(frame());
+            }
+        }
+    }
+}
+
+struct ConnectionTask {
+    uri: String,
+    credentials: Option<OkxCredentials>,
+    clock: Clock,
+    rate_limiter: RateLimiter,
+    backoff: Backoff,
+    heartbeat: Heartbeat,
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// 最近一次收到消息的时间
+    last_received: Instant,
+    /// 已发送但尚未收到 "pong" 的 "ping" 的发送时间
+    ping_sent: Option<Instant>,
+    commands: flume::Receiver<Command>,
+    routes: Routes,
+    /// 等待限流令牌的 subscribe/unsubscribe 请求，按顺序发送
+    ops: VecDeque<(&'static str, Vec<OkxArg>)>,
+    /// 下一次尝试发送 `ops` 的时间
+    next_op: Instant,
+    /// 尚未确认的请求 id 及其对应的频道
+    pending: HashMap<ByteString, Vec<OkxArg>>,
+    next_id: u64,
+}
+
+impl ConnectionTask {
+    async fn run(mut self) {
+        let mut commands_closed = false;
+        loop {
+            let heartbeat_deadline = self.heartbeat_deadline();
+            // 等待限流令牌期间照常收发消息与心跳
+            let disconnected = tokio::select! {
+                command = self.commands.recv_async(), if !commands_closed => match command {
+                    Ok(command) => {
+                        self.handle_command(command);
+                        None
+                    }
+                    // 句柄都已 drop，但仍有订阅流时继续推送
+                    Err(_) => {
+                        commands_closed = true;
+                        None
+                    }
+                },
+                msg = self.ws.next() => match msg {
+                    Some(Ok(msg)) => {
+                        self.last_received = Instant::now();
+                        match msg.as_text() {
+                            Some(OKX_PONG) => {
+                                self.ping_sent = None;
+                                None
+                            }
+                            Some(_) => self.route(msg).err(),
+                            None => None,
+                        }
+                    }
+                    Some(Err(err)) => Some(err.into()),
+                    None => Some(eyre!("Connection closed by server")),
+                },
+                _ = tokio::time::sleep_until(self.next_op), if !self.ops.is_empty() => {
+                    self.send_ops().await.err()
+                }
+                _ = tokio::time::sleep_until(heartbeat_deadline) => self.heartbeat().await.err(),
+            };
+
+            if commands_closed && self.routes.is_empty() && self.ops.is_empty() {
+                break;
+            }
+
+            if let Some(cause) = disconnected
+                && let Err(err) = self.reconnect(cause).await
+            {
+                self.routes.broadcast(|| Err(eyre!("{err:#}")));
+                break;
+            }
+        }
+    }
+
+    /// 下一次需要检查心跳的时间
+    fn heartbeat_deadline(&self) -> Instant {
+        match self.ping_sent {
+            Some(sent) => sent + self.heartbeat.timeout,
+            None => self.last_received + self.heartbeat.interval,
+        }
+    }
+
+    /// 空闲时发送 "ping"，超时未收到 "pong" 时返回错误
+    async fn heartbeat(&mut self) -> Result<()> {
+        if let Some(sent) = self.ping_sent {
+            bail!("No pong received within {:?}", sent.elapsed());
+        }
+
+        self.ws.send(Message::text(OKX_PING)).await?;
+        self.ping_sent = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// 按退避参数重连，成功后重新发送所有订阅
+    async fn reconnect(&mut self, mut cause: eyre::Report) -> Result<()> {
+        self.routes.broadcast(|| Ok(StreamEvent::Disconnected));
+        self.pending.clear();
+        // 新连接上只需要订阅现有的频道
+        self.ops.clear();
+
+        let mut attempt = 0;
+        loop {
+            let Some(delay) = self.backoff.delay(attempt) else {
+                bail!(
+                    "Gave up reconnecting to {} after {attempt} attempts: {cause:#}",
+                    self.uri
+                );
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+
+            match connect_ws(
+                &self.uri,
+                self.credentials.as_ref(),
+                &self.clock,
+                &self.rate_limiter,
+            )
+            .await
+            {
+                Ok(ws) => {
+                    self.ws = ws;
+                    self.last_received = Instant::now();
+                    self.ping_sent = None;
+                    break;
+                }
+                Err(err) => cause = err,
+            }
+        }
+
+        let args = self.routes.args();
+        if !args.is_empty() {
+            self.queue_op("subscribe", args);
+        }
+        self.routes.broadcast(|| Ok(StreamEvent::Reconnected));
+        Ok(())
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Subscribe { arg, frames } => {
+                if self.routes.insert(arg.clone(), frames) {
+                    self.queue_op("subscribe", vec![arg]);
+                }
+            }
+            Command::Unsubscribe { arg } => {
+                if self.routes.remove(&arg).is_some() {
+                    self.queue_op("unsubscribe", vec![arg]);
+                }
+            }
+            Command::Resubscribe { arg } => {
+                self.queue_op("unsubscribe", vec![arg.clone()]);
+                self.queue_op("subscribe", vec![arg]);
+            }
+        }
+    }
+
+    fn queue_op(&mut self, op: &'static str, args: Vec<OkxArg>) {
+        if self.ops.is_empty() {
+            self.next_op = Instant::now();
+        }
+        self.ops.push_back((op, args));
+    }
+
+    /// 按限流发送排队的请求，令牌不足时记录下一次尝试的时间
+    async fn send_ops(&mut self) -> Result<()> {
+        while let Some((op, args)) = self.ops.pop_front() {
+            if let Err(retry_after) =
+                self.rate_limiter
+                    .try_acquire(&ws_op_key(&self.uri), WS_OP_LIMIT, 1)
+            {
+                self.ops.push_front((op, args));
+                self.next_op = Instant::now() + retry_after;
+                break;
+            }
+
+            self.next_id += 1;
+            let id = ByteString::from(self.next_id.to_string());
+            let params = OkxWebSocketSubscribeRequest::<()>::builder(op, args.clone())
+                .id(id.clone())
+                .build();
+            self.ws
+                .send(Message::text(simd_json::serde::to_string(&params)?))
+                .await?;
+            self.pending.insert(id, args);
+        }
+
+        Ok(())
+    }
+
+    fn route(&mut self, msg: Message) -> Result<()> {
+        let text = ByteString::try_from(Bytes::from(msg.into_payload()))?;
+        let header =
+            match simd_json::serde::from_slice::<FrameHeader>(&mut text.as_bytes().to_vec()) {
+                Ok(header) => header,
+                Err(err) => {
+                    // 无法识别的消息不影响连接上的其它订阅
+                    self.routes.broadcast(|| {
+                        Err(eyre!("Unrecognized OKX WebSocket message '{text}': {err}"))
+                    });
+                    return Ok(());
+                }
+            };
+
+        let pending = header.id.and_then(|id| self.pending.remove(&id));
+        match header.event.as_deref() {
+            Some("error") => {
+                if header.code.as_deref() == Some(OKX_CODE_WS_TOO_MANY_REQUESTS) {
+                    self.rate_limiter
+                        .penalize(&ws_op_key(&self.uri), WS_OP_LIMIT, WS_OP_PAUSE);
+                }
+                let (code, msg) = (
+                    header.code.unwrap_or_default(),
+                    header.msg.unwrap_or_default(),
+                );
+                let err = || OkxError::from_code(code.clone(), msg.clone(), Vec::new()).into();
+                let failed = match (header.arg, pending) {
+                    (Some(arg), _) => Some(arg),
+                    (None, Some(mut args)) if args.len() == 1 => args.pop(),
+                    _ => None,
+                };
+                match failed {
+                    // 订阅失败的频道不会再有推送
+                    Some(arg) => {
+                        if let Some(route) = self.routes.remove(&arg) {
+                            for frames in route.senders {
+                                let _ = frames.send(Err(err()));
+                            }
+                        }
+                    }
+                    None => self.routes.broadcast(|| Err(err())),
+                }
+            }
+            Some(_) => {}
+            None => {
+                if let Some(arg) = header.arg
+                    && let Some(arg) = self.routes.deliver(&arg, text)
+                {
+                    self.queue_op("unsubscribe", vec![arg]);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{net::TcpListener, task::JoinHandle};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    type ServerStream = WebSocketStream<TcpStream>;
+
+    /// 本地 WebSocket 服务端，按顺序交出每个连接
+    async fn server() -> (String, flume::Receiver<ServerStream>, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("ws://{}/ws/v5/public", listener.local_addr().unwrap());
+        let (conns, conns_rx) = flume::unbounded();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (_, ws) = tokio_websockets::ServerBuilder::new()
+                    .accept(stream)
+                    .await
+                    .unwrap();
+                if conns.send(ws).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (uri, conns_rx, task)
+    }
+
+    fn options(heartbeat: Heartbeat) -> OkxWsOptions {
+        OkxWsOptions {
+            backoff: Backoff {
+                max_retries: 2,
+                retry_delay: Duration::from_millis(10),
+                backoff_factor: 1.0,
+                jitter: false,
+            },
+            heartbeat,
+            ..OkxWsOptions::default()
+        }
+    }
+
+    async fn accept(conns: &flume::Receiver<ServerStream>) -> ServerStream {
+        tokio::time::timeout(TIMEOUT, conns.recv_async())
+            .await
+            .expect("client did not connect")
+            .unwrap()
+    }
+
+    async fn recv_text(ws: &mut ServerStream) -> String {
+        loop {
+            let msg = tokio::time::timeout(TIMEOUT, ws.next())
+                .await
+                .expect("client sent nothing")
+                .unwrap()
+                .unwrap();
+            if let Some(text) = msg.as_text() {
+                return text.to_owned();
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Op {
+        op: String,
+        args: Vec<OkxArg>,
+    }
+
+    async fn recv_op(ws: &mut ServerStream) -> Op {
+        let text = recv_text(ws).await;
+        simd_json::serde::from_slice(&mut text.into_bytes()).unwrap()
+    }
+
+    async fn next_frame(frames: &flume::Receiver<Frame>) -> Frame {
+        tokio::time::timeout(TIMEOUT, frames.recv_async())
+            .await
+            .expect("no frame received")
+            .unwrap()
+    }
+
+    const TRADE_PUSH: &str = r#"{"arg":{"channel":"trades","instId":"BTC-USDT"},"data":[]}"#;
+
+    #[tokio::test]
+    async fn reconnects_and_resubscribes() {
+        let (uri, conns, server) = server().await;
+        let conn =
+            OkxWsConnection::connect(&uri, OkxWsEndpoint::Public, options(Heartbeat::default()))
+                .await
+                .unwrap();
+        let arg = OkxArg::new(OkxChannel::Trades, "BTC-USDT");
+        let frames = conn.subscribe_raw(arg.clone()).unwrap();
+
+        let mut ws = accept(&conns).await;
+        let op = recv_op(&mut ws).await;
+        assert_eq!((op.op.as_str(), op.args), ("subscribe", vec![arg.clone()]));
+        ws.send(Message::text(TRADE_PUSH)).await.unwrap();
+        assert!(
+            matches!(next_frame(&frames).await, Ok(StreamEvent::Data(text)) if text == TRADE_PUSH)
+        );
+
+        // 服务端断开后先通知断线，重连成功后通知恢复并重新订阅
+        drop(ws);
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Disconnected)
+        ));
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Reconnected)
+        ));
+        let mut ws = accept(&conns).await;
+        let op = recv_op(&mut ws).await;
+        assert_eq!((op.op.as_str(), op.args), ("subscribe", vec![arg]));
+        ws.send(Message::text(TRADE_PUSH)).await.unwrap();
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Data(_))
+        ));
+
+        // 无法重连时重试次数用完后产出错误，连接关闭
+        server.abort();
+        let _ = server.await;
+        drop(ws);
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Disconnected)
+        ));
+        let err = next_frame(&frames).await.unwrap_err();
+        assert!(err.to_string().contains("Gave up reconnecting"), "{err}");
+        assert!(
+            tokio::time::timeout(TIMEOUT, frames.recv_async())
+                .await
+                .unwrap()
+                .is_err()
+        );
+        assert!(conn.is_closed());
+    }
+
+    fn push(arg: &str) -> OkxArg {
+        simd_json::serde::from_slice(&mut arg.as_bytes().to_vec()).unwrap()
+    }
+
+    fn received(frames: &flume::Receiver<Frame>) -> Vec<ByteString> {
+        frames
+            .drain()
+            .map(|frame| match frame {
+                Ok(StreamEvent::Data(text)) => text,
+                _ => panic!("expected data"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reconnects_when_pong_times_out() {
+        let (uri, conns, _server) = server().await;
+        let heartbeat = Heartbeat {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(50),
+        };
+        let conn = OkxWsConnection::connect(&uri, OkxWsEndpoint::Public, options(heartbeat))
+            .await
+            .unwrap();
+        let frames = conn
+            .subscribe_raw(OkxArg::new(OkxChannel::Trades, "BTC-USDT"))
+            .unwrap();
+
+        let mut ws = accept(&conns).await;
+        assert_eq!(recv_op(&mut ws).await.op, "subscribe");
+
+        // 空闲时发送 ping，及时回复 pong 后连接保持
+        let sent = Instant::now();
+        assert_eq!(recv_text(&mut ws).await, OKX_PING);
+        assert!(sent.elapsed() >= Duration::from_millis(40));
+        ws.send(Message::text(OKX_PONG)).await.unwrap();
+        assert_eq!(recv_text(&mut ws).await, OKX_PING);
+        assert!(frames.is_empty());
+
+        // 超时未收到 pong 时断开并重连
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Disconnected)
+        ));
+        assert!(matches!(
+            next_frame(&frames).await,
+            Ok(StreamEvent::Reconnected)
+        ));
+        let mut ws = accept(&conns).await;
+        assert_eq!(recv_op(&mut ws).await.op, "subscribe");
+    }
+
+    #[test]
+    fn routes_by_inst_type() {
+        let mut routes = Routes::default();
+        let (spot, spot_rx) = flume::unbounded();
+        let (swap, swap_rx) = flume::unbounded();
+        assert!(routes.insert(
+            OkxArg::with_inst_type(OkxChannel::Instruments, OkxInstType::Spot),
+            spot
+        ));
+        assert!(routes.insert(
+            OkxArg::with_inst_type(OkxChannel::Instruments, OkxInstType::Swap),
+            swap
+        ));
+
+        routes.deliver(
+            &push(r#"{"channel":"instruments","instType":"SPOT"}"#),
+            "spot".into(),
+        );
+        routes.deliver(
+            &push(r#"{"channel":"instruments","instType":"SWAP"}"#),
+            "swap".into(),
+        );
+        assert_eq!(received(&spot_rx), ["spot"]);
+        assert_eq!(received(&swap_rx), ["swap"]);
+    }
+
+    #[test]
+    fn routes_by_ccy() {
+        let account = |ccy: &str| OkxArg {
+            ccy: Some(ccy.into()),
+            ..OkxArg::channel(OkxChannel::Account)
+        };
+        let mut routes = Routes::default();
+        let (btc, btc_rx) = flume::unbounded();
+        let (usdt, usdt_rx) = flume::unbounded();
+        assert!(routes.insert(account("BTC"), btc));
+        assert!(routes.insert(account("USDT"), usdt));
+
+        routes.deliver(
+            &push(r#"{"channel":"account","ccy":"USDT","uid":"1"}"#),
+            "usdt".into(),
+        );
+        routes.deliver(
+            &push(r#"{"channel":"account","ccy":"BTC","uid":"1"}"#),
+            "btc".into(),
+        );
+        assert_eq!(received(&btc_rx), ["btc"]);
+        assert_eq!(received(&usdt_rx), ["usdt"]);
+    }
+
+    #[test]
+    fn shares_identical_subscriptions() {
+        let mut routes = Routes::default();
+        let (first, first_rx) = flume::unbounded();
+        let (second, second_rx) = flume::unbounded();
+        assert!(routes.insert(OkxArg::new(OkxChannel::Trades, "BTC-USDT"), first));
+        assert!(!routes.insert(OkxArg::new(OkxChannel::Trades, "BTC-USDT"), second));
+
+        routes.deliver(
+            &push(r#"{"channel":"trades","instId":"BTC-USDT"}"#),
+            "a".into(),
+        );
+        routes.deliver(
+            &push(r#"{"channel":"trades","instId":"ETH-USDT"}"#),
+            "b".into(),
+        );
+        assert_eq!(received(&first_rx), ["a"]);
+        assert_eq!(received(&second_rx), ["a"]);
+    }
+
+    #[test]
+    fn matches_pushes_with_extra_fields() {
+        let mut routes = Routes::default();
+        let (any, any_rx) = flume::unbounded();
+        let (spot, spot_rx) = flume::unbounded();
+        routes.insert(
+            OkxArg::with_inst_type(OkxChannel::Orders, OkxInstType::Any),
+            any,
+        );
+        routes.insert(
+            OkxArg::with_inst_type(OkxChannel::Orders, OkxInstType::Spot),
+            spot,
+        );
+
+        routes.deliver(
+            &push(r#"{"channel":"orders","instType":"ANY","instId":"BTC-USDT","uid":"1"}"#),
+            "any".into(),
+        );
+        routes.deliver(
+            &push(r#"{"channel":"orders","instType":"SPOT","instId":"BTC-USDT","uid":"1"}"#),
+            "spot".into(),
+        );
+        assert_eq!(received(&any_rx), ["any"]);
+        assert_eq!(received(&spot_rx), ["spot"]);
+    }
+
+    #[test]
+    fn unsubscribes_when_all_subscribers_dropped() {
+        let mut routes = Routes::default();
+        let arg = OkxArg::new(OkxChannel::Trades, "BTC-USDT");
+        let (frames, frames_rx) = flume::unbounded();
+        routes.insert(arg.clone(), frames);
+        drop(frames_rx);
+
+        let push = push(r#"{"channel":"trades","instId":"BTC-USDT"}"#);
+        assert_eq!(routes.deliver(&push, "a".into()), Some(arg));
+        assert!(routes.is_empty());
+        assert_eq!(routes.deliver(&push, "b".into()), None);
+    }
+}
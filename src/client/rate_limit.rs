@@ -0,0 +1,313 @@
+use bytestring::ByteString;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// 触发限流后暂停时长的最大翻倍次数
+const MAX_PENALTY_DOUBLINGS: u32 = 5;
+
+/// 每 `period` 最多 `capacity` 次请求
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub const fn new(capacity: u32, period: Duration) -> Self {
+        Self { capacity, period }
+    }
+
+    /// 每秒补充的令牌数
+    fn rate(self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// 限流规则与其作用范围，例如 (下单接口, 产品ID)。作用范围为空表示按用户或 IP 限流
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RateLimitKey {
+    pub rule: ByteString,
+    pub scope: ByteString,
+}
+
+impl RateLimitKey {
+    pub fn new(rule: impl Into<ByteString>, scope: impl Into<ByteString>) -> Self {
+        Self {
+            rule: rule.into(),
+            scope: scope.into(),
+        }
+    }
+}
+
+/// 令牌不足时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// 等待令牌补充
+    #[default]
+    Wait,
+    /// 立即返回 [`RateLimited`] 错误
+    FailFast,
+}
+
+/// 请求被限流
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    pub key: RateLimitKey,
+    /// 预计多久后可以重试
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rate limited on '{}'", self.key.rule)?;
+        if !self.key.scope.is_empty() {
+            write!(f, " ({})", self.key.scope)?;
+        }
+        write!(f, ", retry after {:?}", self.retry_after)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// 收到交易所的限流错误后，在此之前不再发送请求
+    blocked_until: Option<Instant>,
+    /// 连续触发限流的次数，每次暂停时长翻倍
+    strikes: u32,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.capacity as f64,
+            updated: now,
+            blocked_until: None,
+            strikes: 0,
+        }
+    }
+
+    /// 取出 `cost` 个令牌，不足时返回需要等待的时间
+    fn try_take(&mut self, limit: RateLimit, cost: u32, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.blocked_until
+            && now < until
+        {
+            return Err(until - now);
+        }
+
+        let rate = limit.rate();
+        let capacity = limit.capacity as f64;
+        self.tokens = (self.tokens + (now - self.updated).as_secs_f64() * rate).min(capacity);
+        self.updated = now;
+
+        // 超过容量的请求最多等到令牌桶满
+        let cost = (cost as f64).min(capacity);
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((cost - self.tokens) / rate))
+        }
+    }
+
+    fn penalize(&mut self, pause: Duration, now: Instant) -> Duration {
+        // 上一次暂停结束后 `pause` 内再次触发，视为连续触发
+        self.strikes = match self.blocked_until {
+            Some(until) if now < until + pause => (self.strikes + 1).min(MAX_PENALTY_DOUBLINGS),
+            _ => 0,
+        };
+        let penalty = pause * 2u32.pow(self.strikes);
+
+        self.blocked_until = Some(now + penalty);
+        penalty
+    }
+}
+
+/// 按规则与作用范围分别计数的令牌桶限流器，可以在多个客户端间共享
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    mode: RateLimitMode,
+    buckets: Arc<Mutex<HashMap<RateLimitKey, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(mode: RateLimitMode) -> Self {
+        Self {
+            mode,
+            buckets: Default::default(),
+        }
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// 取得 `cost` 个令牌，令牌不足时按 [`RateLimitMode`] 等待或返回 [`RateLimited`] 错误
+    pub async fn acquire(
+        &self,
+        key: &RateLimitKey,
+        limit: RateLimit,
+        cost: u32,
+    ) -> Result<(), RateLimited> {
+        self.acquire_with(self.mode, key, limit, cost).await
+    }
+
+    /// 取得 `cost` 个令牌，总是等待。用于不能失败的后台请求，例如重连后的重新订阅
+    pub async fn wait(&self, key: &RateLimitKey, limit: RateLimit, cost: u32) {
+        let _ = self
+            .acquire_with(RateLimitMode::Wait, key, limit, cost)
+            .await;
+    }
+
+    /// 立即尝试取得 `cost` 个令牌，不足时返回需要等待的时间
+    pub fn try_acquire(
+        &self,
+        key: &RateLimitKey,
+        limit: RateLimit,
+        cost: u32,
+    ) -> Result<(), Duration> {
+        self.with_bucket(key, limit, |bucket, now| bucket.try_take(limit, cost, now))
+    }
+
+    async fn acquire_with(
+        &self,
+        mode: RateLimitMode,
+        key: &RateLimitKey,
+        limit: RateLimit,
+        cost: u32,
+    ) -> Result<(), RateLimited> {
+        loop {
+            let retry_after = match self.try_acquire(key, limit, cost) {
+                Ok(()) => return Ok(()),
+                Err(retry_after) => retry_after,
+            };
+
+            match mode {
+                RateLimitMode::Wait => tokio::time::sleep(retry_after).await,
+                RateLimitMode::FailFast => {
+                    return Err(RateLimited {
+                        key: key.clone(),
+                        retry_after,
+                    });
+                }
+            }
+        }
+    }
+
+    /// 交易所返回限流错误时调用，暂停该规则与范围下的请求 `pause`，
+    /// 连续触发时暂停时长翻倍。返回本次暂停的时长
+    pub fn penalize(&self, key: &RateLimitKey, limit: RateLimit, pause: Duration) -> Duration {
+        self.with_bucket(key, limit, |bucket, now| bucket.penalize(pause, now))
+    }
+
+    fn with_bucket<R>(
+        &self,
+        key: &RateLimitKey,
+        limit: RateLimit,
+        f: impl FnOnce(&mut Bucket, Instant) -> R,
+    ) -> R {
+        let now = Instant::now();
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let bucket = buckets
+            .entry(key.clone())
+            .or_insert_with(|| Bucket::new(limit, now));
+
+        f(bucket, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::advance;
+
+    const LIMIT: RateLimit = RateLimit::new(5, Duration::from_secs(1));
+
+    fn key(scope: &str) -> RateLimitKey {
+        RateLimitKey::new("api/v5/trade/order", scope)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_up_to_capacity() {
+        let limiter = RateLimiter::default();
+        for _ in 0..5 {
+            assert!(limiter.try_acquire(&key(""), LIMIT, 1).is_ok());
+        }
+        assert_eq!(
+            limiter.try_acquire(&key(""), LIMIT, 1),
+            Err(Duration::from_millis(200))
+        );
+        // 作用范围不同的请求分别计数
+        assert!(limiter.try_acquire(&key("BTC-USDT"), LIMIT, 1).is_ok());
+
+        advance(Duration::from_millis(100)).await;
+        assert_eq!(
+            limiter.try_acquire(&key(""), LIMIT, 1),
+            Err(Duration::from_millis(100))
+        );
+        advance(Duration::from_millis(100)).await;
+        assert!(limiter.try_acquire(&key(""), LIMIT, 1).is_ok());
+
+        // 长时间空闲后最多积攒 `capacity` 个令牌
+        advance(Duration::from_secs(10)).await;
+        assert!(limiter.try_acquire(&key(""), LIMIT, 5).is_ok());
+        assert!(limiter.try_acquire(&key(""), LIMIT, 1).is_err());
+
+        // 超过容量的请求等到令牌桶满即可
+        advance(Duration::from_secs(1)).await;
+        assert!(limiter.try_acquire(&key(""), LIMIT, 8).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_or_fails_fast() {
+        let limiter = RateLimiter::default();
+        limiter.acquire(&key(""), LIMIT, 5).await.unwrap();
+        let start = Instant::now();
+        limiter.acquire(&key(""), LIMIT, 2).await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+
+        let limiter = RateLimiter::new(RateLimitMode::FailFast);
+        limiter.acquire(&key(""), LIMIT, 5).await.unwrap();
+        let err = limiter.acquire(&key(""), LIMIT, 1).await.unwrap_err();
+        assert_eq!(err.key, key(""));
+        assert_eq!(err.retry_after, Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn penalizes_and_doubles_on_repeated_rate_limits() {
+        let limiter = RateLimiter::default();
+        let pause = Duration::from_secs(1);
+
+        assert_eq!(limiter.penalize(&key(""), LIMIT, pause), pause);
+        assert_eq!(limiter.try_acquire(&key(""), LIMIT, 1), Err(pause));
+        let start = Instant::now();
+        limiter.wait(&key(""), LIMIT, 1).await;
+        assert_eq!(start.elapsed(), pause);
+
+        // 暂停结束后 `pause` 内再次触发时翻倍，最多翻倍 MAX_PENALTY_DOUBLINGS 次
+        assert_eq!(limiter.penalize(&key(""), LIMIT, pause), pause * 2);
+        advance(pause * 2).await;
+        assert_eq!(limiter.penalize(&key(""), LIMIT, pause), pause * 4);
+        for _ in 0..10 {
+            limiter.penalize(&key(""), LIMIT, pause);
+        }
+        assert_eq!(
+            limiter.penalize(&key(""), LIMIT, pause),
+            pause * 2u32.pow(MAX_PENALTY_DOUBLINGS)
+        );
+
+        // 长时间未再触发时恢复为 `pause`
+        advance(Duration::from_secs(3600)).await;
+        assert_eq!(limiter.penalize(&key(""), LIMIT, pause), pause);
+        assert!(limiter.try_acquire(&key("BTC-USDT"), LIMIT, 1).is_ok());
+    }
+}
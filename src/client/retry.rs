@@ -0,0 +1,150 @@
+use super::backoff::Backoff;
+use serde::Deserialize;
+use serde_with::{DurationSecondsWithFrac, serde_as};
+use std::{future::Future, time::Duration};
+
+/// REST 请求的重试策略，字段与 conf.yaml 中策略的 `params` 一致
+#[serde_as]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct RetryPolicy {
+    #[serde(flatten)]
+    pub backoff: Backoff,
+    /// 单次请求的超时时间（秒），超时视为可重试的错误
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            backoff: Backoff {
+                max_retries: 3,
+                ..Backoff::default()
+            },
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试
+    pub fn none() -> Self {
+        Self {
+            backoff: Backoff {
+                max_retries: 0,
+                ..Backoff::default()
+            },
+            ..Self::default()
+        }
+    }
+
+    /// 执行 `op`，失败时按退避参数重试
+    ///
+    /// `retry_after` 判断错误是否值得重试：返回 `None` 时直接返回错误，
+    /// 否则至少等待返回的时间（例如被限流时交易所建议的等待时间）后重试。
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        mut op: F,
+        retry_after: impl Fn(&E) -> Option<Duration>,
+    ) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match op().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let Some(min_delay) = retry_after(&err) else {
+                return Err(err);
+            };
+            let Some(delay) = self.backoff.delay(attempt) else {
+                return Err(err);
+            };
+            attempt += 1;
+
+            tokio::time::sleep(delay.max(min_delay)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tokio::time::Instant;
+
+    fn policy(max_retries: usize) -> RetryPolicy {
+        RetryPolicy {
+            backoff: Backoff {
+                max_retries,
+                retry_delay: Duration::from_secs(1),
+                backoff_factor: 2.0,
+                jitter: false,
+            },
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// 前 `failures` 次返回错误，之后成功，返回结果与执行次数
+    async fn run(
+        policy: RetryPolicy,
+        failures: usize,
+        retry_after: impl Fn(&&str) -> Option<Duration>,
+    ) -> (Result<usize, &'static str>, usize) {
+        let attempts = Cell::new(0);
+        let result = policy
+            .run(
+                || {
+                    attempts.set(attempts.get() + 1);
+                    let attempt = attempts.get();
+                    async move {
+                        if attempt <= failures {
+                            Err("unavailable")
+                        } else {
+                            Ok(attempt)
+                        }
+                    }
+                },
+                retry_after,
+            )
+            .await;
+
+        (result, attempts.get())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_with_exponential_backoff() {
+        let start = Instant::now();
+        let (result, attempts) = run(policy(3), 2, |_| Some(Duration::ZERO)).await;
+        assert_eq!((result, attempts), (Ok(3), 3));
+        assert_eq!(start.elapsed(), Duration::from_secs(1 + 2));
+
+        // 重试次数用完后返回最后一次的错误
+        let start = Instant::now();
+        let (result, attempts) = run(policy(3), 10, |_| Some(Duration::ZERO)).await;
+        assert_eq!((result, attempts), (Err("unavailable"), 4));
+        assert_eq!(start.elapsed(), Duration::from_secs(1 + 2 + 4));
+
+        let (result, attempts) = run(RetryPolicy::none(), 1, |_| Some(Duration::ZERO)).await;
+        assert_eq!((result, attempts), (Err("unavailable"), 1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_on_errors_not_worth_retrying() {
+        let start = Instant::now();
+        let (result, attempts) = run(policy(3), 1, |_| None).await;
+        assert_eq!((result, attempts), (Err("unavailable"), 1));
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_at_least_the_suggested_delay() {
+        let start = Instant::now();
+        let (result, _) = run(policy(3), 1, |_| Some(Duration::from_secs(5))).await;
+        assert_eq!(result, Ok(2));
+        assert_eq!(start.elapsed(), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::Timestamp;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+/// 一次对时的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    /// 服务器时间减去本地时间
+    pub offset: TimeDelta,
+    /// 请求的往返时间
+    pub round_trip: Duration,
+}
+
+impl ClockSample {
+    /// 按 NTP 的方式估算时钟偏差：假设请求与响应的单程耗时相同，
+    /// 服务器时间对应本地发送与接收时间的中点
+    pub fn new(sent: DateTime<Utc>, server: DateTime<Utc>, received: DateTime<Utc>) -> Self {
+        let round_trip = received - sent;
+        let midpoint = sent + round_trip / 2;
+
+        Self {
+            offset: server - midpoint,
+            round_trip: round_trip.to_std().unwrap_or_default(),
+        }
+    }
+}
+
+/// 本地时钟与服务器时钟偏差过大
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkew {
+    pub offset: TimeDelta,
+    pub threshold: Duration,
+}
+
+impl std::fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Local clock is off by {}ms from the server, exceeding {}ms",
+            self.offset.num_milliseconds(),
+            self.threshold.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for ClockSkew {}
+
+/// 按服务器时间校正的时钟，可以在多个任务间共享
+///
+/// 未对时前与本地时钟相同。
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    /// 偏差，微秒
+    offset: Arc<AtomicI64>,
+    /// 最近一次对时的往返时间，微秒
+    round_trip: Arc<AtomicU64>,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 校正后的当前时间
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset()
+    }
+
+    /// 校正后的当前时间，Unix时间戳的毫秒数
+    pub fn timestamp(&self) -> Timestamp {
+        self.now().timestamp_millis().max(0) as Timestamp
+    }
+
+    pub fn offset(&self) -> TimeDelta {
+        TimeDelta::microseconds(self.offset.load(Ordering::Relaxed))
+    }
+
+    pub fn round_trip(&self) -> Duration {
+        Duration::from_micros(self.round_trip.load(Ordering::Relaxed))
+    }
+
+    /// 应用一次对时结果
+    pub fn update(&self, sample: ClockSample) {
+        let offset = sample.offset.num_microseconds().unwrap_or(i64::MAX);
+        self.offset.store(offset, Ordering::Relaxed);
+        self.round_trip
+            .store(sample.round_trip.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// 偏差超过 `threshold` 时返回 [`ClockSkew`] 错误
+    pub fn check_skew(&self, threshold: Duration) -> Result<(), ClockSkew> {
+        let offset = self.offset();
+        if offset.abs().to_std().unwrap_or(Duration::MAX) > threshold {
+            return Err(ClockSkew { offset, threshold });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_700_000_000_000 + millis).unwrap()
+    }
+
+    #[test]
+    fn estimates_offset_from_round_trip_midpoint() {
+        // 本地 0ms 发出、100ms 收到，服务器在中点 50ms 时的时间为 1050ms
+        let sample = ClockSample::new(at(0), at(1050), at(100));
+        assert_eq!(sample.offset, TimeDelta::milliseconds(1000));
+        assert_eq!(sample.round_trip, Duration::from_millis(100));
+
+        let sample = ClockSample::new(at(0), at(-450), at(100));
+        assert_eq!(sample.offset, TimeDelta::milliseconds(-500));
+
+        // 本地时钟回拨时往返时间按 0 处理
+        let sample = ClockSample::new(at(100), at(50), at(0));
+        assert_eq!(sample.round_trip, Duration::ZERO);
+    }
+
+    #[test]
+    fn applies_offset_and_checks_skew() {
+        let clock = Clock::new();
+        assert_eq!(clock.offset(), TimeDelta::zero());
+        assert!(clock.check_skew(Duration::ZERO).is_ok());
+
+        clock.update(ClockSample::new(at(0), at(1050), at(100)));
+        assert_eq!(clock.offset(), TimeDelta::milliseconds(1000));
+        assert_eq!(clock.round_trip(), Duration::from_millis(100));
+        let drift = clock.now() - Utc::now() - TimeDelta::milliseconds(1000);
+        assert!(drift.abs() < TimeDelta::milliseconds(100), "{drift}");
+
+        // 偏差等于阈值时不报警
+        assert!(clock.check_skew(Duration::from_secs(1)).is_ok());
+        let skew = clock.check_skew(Duration::from_millis(999)).unwrap_err();
+        assert_eq!(skew.offset, TimeDelta::milliseconds(1000));
+        assert_eq!(
+            skew.to_string(),
+            "Local clock is off by 1000ms from the server, exceeding 999ms"
+        );
+
+        // 本地时钟快于服务器时同样报警
+        clock.update(ClockSample::new(at(0), at(-1950), at(100)));
+        assert!(clock.check_skew(Duration::from_secs(1)).is_err());
+        assert!(clock.check_skew(Duration::from_secs(2)).is_ok());
+    }
+}
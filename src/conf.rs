@@ -0,0 +1,748 @@
+use crate::{
+    analytics::Metric,
+    backtest::{FillModel, Slippage},
+    client::retry::RetryPolicy,
+    data::{CandleInterval, MarketDataType},
+    recorder::DEFAULT_FLUSH_INTERVAL,
+};
+use bytestring::ByteString;
+use config::{Config, File, Map, Value, ValueKind};
+use eyre::{ContextCompat, Result, WrapErr, bail, ensure};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_with::{DurationSecondsWithFrac, serde_as};
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// 环境变量覆盖的前缀，层级之间用 `__` 分隔，例如
+/// `SQUANT__STRATEGIES__HIGH_VOLUME__PARAMS__TIMEOUT=10`。
+/// 列表中的元素可以用下标或 `name` 字段指定。
+pub const ENV_PREFIX: &str = "SQUANT__";
+const ENV_SEPARATOR: &str = "__";
+
+/// conf.yaml 的配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppConfig {
+    /// 是否连接模拟盘，默认为 `true`，实盘需要显式设置为 `false`
+    #[serde(default = "AppConfig::default_simulated_trading")]
+    pub simulated_trading: bool,
+    pub strategies: Vec<StrategyConfig>,
+    /// 设置后录制所有策略订阅的行情
+    #[serde(default)]
+    pub recorder: Option<RecorderConfig>,
+}
+
+/// 行情录制的配置
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecorderConfig {
+    /// 录制文件的根目录
+    pub dir: PathBuf,
+    /// 写入磁盘的间隔（秒），崩溃时最多丢失这段时间内的数据
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    #[serde(default = "RecorderConfig::default_flush_interval")]
+    pub flush_interval: Duration,
+}
+
+/// 一个策略实例的配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// 策略实例名，不能重复
+    pub name: ByteString,
+    /// 策略类型，对应注册的策略实现
+    #[serde(rename = "type")]
+    pub kind: ByteString,
+    /// 策略订阅的行情
+    pub data_source: Vec<DataSourceConfig>,
+    pub params: StrategyParams,
+    /// 参数优化的搜索空间，只用于回测
+    #[serde(default)]
+    pub sweep: Option<SweepConfig>,
+}
+
+/// 策略参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct StrategyParams {
+    #[serde(flatten)]
+    pub retry: RetryPolicy,
+    /// 完整的参数表，也包括 `retry` 解析过的字段，策略从中解析自己的参数
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// 参数优化的配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepConfig {
+    /// 用于排序的指标，默认为夏普比率
+    #[serde(default)]
+    pub metric: Metric,
+    /// 参数的取值，覆盖 `params` 中的同名参数，所有取值的组合都会被回测
+    pub params: BTreeMap<String, ParamRange>,
+    /// 设置后按滚动窗口做前向分析
+    #[serde(default)]
+    pub walk_forward: Option<WalkForwardConfig>,
+    /// 回测的手续费、延迟与滑点，未设置的字段使用默认值
+    #[serde(default)]
+    pub fill_model: FillModel,
+}
+
+/// 一个参数的取值，可以是列表或等差区间
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ParamRange {
+    /// 例如 `[3, 5, 8]`
+    Values(Vec<Value>),
+    /// 例如 `{ start: 10, end: 40, step: 10 }`，包含 `end`；三者都是整数时生成整数
+    Range { start: f64, end: f64, step: f64 },
+}
+
+/// 前向分析的窗口：在样本内窗口选出最优参数，再在紧随其后的样本外窗口检验，
+/// 之后窗口向后滚动一个样本外窗口的长度
+#[serde_as]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WalkForwardConfig {
+    /// 样本内窗口的长度（秒）
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub in_sample: Duration,
+    /// 样本外窗口的长度（秒）
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub out_of_sample: Duration,
+}
+
+/// 交易所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    Okx,
+}
+
+/// 一个行情订阅
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DataSourceConfig {
+    pub exchange: Exchange,
+    /// 产品ID，例如 "BTC-USDT"
+    pub symbol: ByteString,
+    /// trade、candle（或 kline）、book
+    pub data_type: MarketDataType,
+    /// K线周期，例如 "1m"、"1D"，仅 candle 需要
+    #[serde(default)]
+    pub interval: Option<CandleInterval>,
+}
+
+impl AppConfig {
+    /// 按顺序加载 `paths`，后面的文件覆盖前面的文件，再应用环境变量覆盖并校验
+    ///
+    /// 表按字段合并；元素都带有 `name` 字段的列表（例如 `strategies`）按 `name` 合并，
+    /// 其余列表整体替换。
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        Self::load_with_env(paths, std::env::vars())
+    }
+
+    /// 与 [`AppConfig::load`] 相同，环境变量取自 `vars`
+    fn load_with_env(
+        paths: &[impl AsRef<Path>],
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let mut root = Value::new(None, ValueKind::Table(Map::new()));
+        for path in paths {
+            let path = path.as_ref();
+            let table = Config::builder()
+                .add_source(File::from(path))
+                .build()
+                .and_then(|config| config.try_deserialize::<Map<String, Value>>())
+                .wrap_err_with(|| format!("Failed to load config file {}", path.display()))?;
+            merge(&mut root, Value::new(None, ValueKind::Table(table)));
+        }
+
+        for (key, value) in vars {
+            let Some(path) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let path: Vec<_> = path
+                .split(ENV_SEPARATOR)
+                .map(str::to_ascii_lowercase)
+                .collect();
+            apply_override(&mut root, &path, &value)
+                .wrap_err_with(|| format!("Invalid config override {key}"))?;
+        }
+
+        let config = AppConfig::deserialize(root).wrap_err("Invalid config")?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// 校验字段取值，错误信息指出出错的字段
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            !self.strategies.is_empty(),
+            "strategies: at least one strategy is required"
+        );
+
+        let mut names = HashSet::new();
+        for (i, strategy) in self.strategies.iter().enumerate() {
+            let key = format!("strategies[{i}]");
+            ensure!(!strategy.name.is_empty(), "{key}.name: must not be empty");
+            ensure!(
+                names.insert(&strategy.name),
+                "{key}.name: duplicate strategy name '{}'",
+                strategy.name
+            );
+            ensure!(!strategy.kind.is_empty(), "{key}.type: must not be empty");
+            strategy.params.validate(&format!("{key}.params"))?;
+            if let Some(sweep) = &strategy.sweep {
+                sweep.validate(&format!("{key}.sweep"))?;
+            }
+
+            ensure!(
+                !strategy.data_source.is_empty(),
+                "{key}.data_source: at least one data source is required"
+            );
+            for (j, source) in strategy.data_source.iter().enumerate() {
+                source.validate(&format!("{key}.data_source[{j}]"))?;
+            }
+        }
+
+        if let Some(recorder) = &self.recorder {
+            ensure!(
+                !recorder.dir.as_os_str().is_empty(),
+                "recorder.dir: must not be empty"
+            );
+            ensure!(
+                !recorder.flush_interval.is_zero(),
+                "recorder.flush_interval: must be greater than 0"
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn strategy(&self, name: &str) -> Option<&StrategyConfig> {
+        self.strategies
+            .iter()
+            .find(|strategy| strategy.name == name)
+    }
+
+    fn default_simulated_trading() -> bool {
+        true
+    }
+}
+
+impl RecorderConfig {
+    fn default_flush_interval() -> Duration {
+        DEFAULT_FLUSH_INTERVAL
+    }
+}
+
+impl StrategyConfig {
+    /// 用 `overrides` 覆盖同名参数后的配置
+    pub fn with_params<'a>(
+        &self,
+        overrides: impl IntoIterator<Item = (&'a String, &'a Value)>,
+    ) -> Self {
+        let mut config = self.clone();
+        for (key, value) in overrides {
+            config.params.extra.insert(key.clone(), value.clone());
+        }
+        config
+    }
+}
+
+impl SweepConfig {
+    /// 所有参数取值的组合，参数按名称排序
+    pub fn combinations(&self) -> Vec<BTreeMap<String, Value>> {
+        let mut combinations = vec![BTreeMap::new()];
+        for (key, range) in &self.params {
+            let values = range.values();
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.insert(key.clone(), value.clone());
+                        combination
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+
+    fn validate(&self, key: &str) -> Result<()> {
+        ensure!(
+            !self.params.is_empty(),
+            "{key}.params: at least one parameter is required"
+        );
+        for (name, range) in &self.params {
+            let key = format!("{key}.params.{name}");
+            match *range {
+                ParamRange::Values(ref values) => {
+                    ensure!(!values.is_empty(), "{key}: must not be empty")
+                }
+                ParamRange::Range { start, end, step } => {
+                    ensure!(
+                        step.is_finite() && step > 0.0,
+                        "{key}.step: must be greater than 0"
+                    );
+                    ensure!(
+                        start.is_finite() && end.is_finite() && start <= end,
+                        "{key}: start must not exceed end"
+                    );
+                }
+            }
+        }
+        let fees = &self.fill_model.fees;
+        ensure!(
+            fees.maker.is_finite() && fees.taker.is_finite(),
+            "{key}.fill_model.fees: rates must be finite"
+        );
+        match self.fill_model.slippage {
+            Slippage::Fixed(offset) => ensure!(
+                !offset.is_negative(),
+                "{key}.fill_model.slippage.fixed: must not be negative"
+            ),
+            Slippage::Percent(rate) => ensure!(
+                rate.is_finite() && (0.0..1.0).contains(&rate),
+                "{key}.fill_model.slippage.percent: must be in [0, 1)"
+            ),
+            Slippage::None => {}
+        }
+        if let Some(walk_forward) = &self.walk_forward {
+            ensure!(
+                !walk_forward.in_sample.is_zero(),
+                "{key}.walk_forward.in_sample: must be greater than 0"
+            );
+            ensure!(
+                !walk_forward.out_of_sample.is_zero(),
+                "{key}.walk_forward.out_of_sample: must be greater than 0"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl ParamRange {
+    /// 所有取值。无效的区间（`step` 不为正数或 `start` 大于 `end`，校验时会报错）没有取值
+    pub fn values(&self) -> Vec<Value> {
+        match *self {
+            ParamRange::Values(ref values) => values.clone(),
+            ParamRange::Range { start, end, step } => {
+                if !(step.is_finite() && step > 0.0 && start.is_finite() && start <= end) {
+                    return Vec::new();
+                }
+                let integer = [start, end, step].iter().all(|value| value.fract() == 0.0);
+                // 按下标计算避免累加误差，允许末尾有微小的舍入误差
+                let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+                (0..count)
+                    .map(|i| {
+                        let value = start + step * i as f64;
+                        let kind = if integer {
+                            ValueKind::I64(value as i64)
+                        } else {
+                            ValueKind::Float(value)
+                        };
+                        Value::new(None, kind)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl StrategyParams {
+    /// 将参数表解析为策略自己的参数类型，未声明的字段被忽略
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        Value::new(None, ValueKind::Table(self.extra.clone()))
+            .try_deserialize()
+            .wrap_err("Invalid strategy params")
+    }
+
+    fn validate(&self, key: &str) -> Result<()> {
+        let backoff = &self.retry.backoff;
+        ensure!(
+            !self.retry.timeout.is_zero(),
+            "{key}.timeout: must be greater than 0"
+        );
+        ensure!(
+            backoff.backoff_factor.is_finite() && backoff.backoff_factor >= 1.0,
+            "{key}.backoff_factor: must be at least 1, got {}",
+            backoff.backoff_factor
+        );
+
+        Ok(())
+    }
+}
+
+impl DataSourceConfig {
+    fn validate(&self, key: &str) -> Result<()> {
+        ensure!(!self.symbol.is_empty(), "{key}.symbol: must not be empty");
+        match (self.data_type, self.interval) {
+            (MarketDataType::Candle, None) => {
+                bail!("{key}.interval: required for candle data")
+            }
+            (MarketDataType::Trade | MarketDataType::Book, Some(_)) => {
+                bail!("{key}.interval: only valid for candle data")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 列表元素的 `name` 字段
+fn name_of(value: &Value) -> Option<&str> {
+    let ValueKind::Table(table) = &value.kind else {
+        return None;
+    };
+    match &table.get("name")?.kind {
+        ValueKind::String(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn merge(base: &mut Value, overlay: Value) {
+    match (&mut base.kind, overlay.kind) {
+        (ValueKind::Table(base), ValueKind::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (ValueKind::Array(base), ValueKind::Array(overlay))
+            if base
+                .iter()
+                .chain(&overlay)
+                .all(|value| name_of(value).is_some()) =>
+        {
+            for value in overlay {
+                match base
+                    .iter_mut()
+                    .find(|existing| name_of(existing) == name_of(&value))
+                {
+                    Some(existing) => merge(existing, value),
+                    None => base.push(value),
+                }
+            }
+        }
+        (kind, overlay) => *kind = overlay,
+    }
+}
+
+/// 将环境变量的值写入 `path` 指定的位置，不存在的表字段会被创建
+fn apply_override(value: &mut Value, path: &[String], raw: &str) -> Result<()> {
+    let Some((key, rest)) = path.split_first() else {
+        value.kind = parse_env_value(raw);
+        return Ok(());
+    };
+
+    let child = match &mut value.kind {
+        ValueKind::Table(table) => table
+            .entry(key.clone())
+            .or_insert_with(|| Value::new(None, ValueKind::Table(Map::new()))),
+        ValueKind::Array(array) => match key.parse::<usize>() {
+            Ok(index) => array
+                .get_mut(index)
+                .wrap_err_with(|| format!("index {index} is out of range"))?,
+            Err(_) => array
+                .iter_mut()
+                .find(|value| name_of(value).is_some_and(|name| name.eq_ignore_ascii_case(key)))
+                .wrap_err_with(|| format!("no element named '{key}'"))?,
+        },
+        _ => bail!("'{key}' cannot be set on a scalar value"),
+    };
+
+    apply_override(child, rest, raw)
+}
+
+/// 与 `config::Environment::try_parsing` 相同，依次尝试整数、浮点数与布尔值
+fn parse_env_value(raw: &str) -> ValueKind {
+    if let Ok(value) = raw.parse::<i64>() {
+        ValueKind::I64(value)
+    } else if let Ok(value) = raw.parse::<f64>() {
+        ValueKind::Float(value)
+    } else if let Ok(value) = raw.parse::<bool>() {
+        ValueKind::Boolean(value)
+    } else {
+        ValueKind::String(raw.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"
+strategies:
+  - name: "default"
+    type: "default"
+    data_source:
+      - { exchange: "okx", symbol: "BTC-USDT", data_type: "candle", interval: "1m" }
+      - { exchange: "okx", symbol: "ETH-USDT", data_type: "candle", interval: "1m" }
+    params:
+      max_retries: 3
+      retry_delay: 5
+      timeout: 30
+      backoff_factor: 1.5
+      jitter: true
+      fast: 5
+      slow: 20
+  - name: "high_volume"
+    type: "high_volume"
+    data_source:
+      - { exchange: "okx", symbol: "BTC-USDT", data_type: "trade" }
+    params:
+      max_retries: 5
+      retry_delay: 2
+      timeout: 20
+      backoff_factor: 2.0
+      jitter: false
+      window: 20
+"#;
+
+    /// 将 `files` 写入临时目录，返回文件路径
+    fn write_files(files: &[&str]) -> (PathBuf, Vec<PathBuf>) {
+        let dir = std::env::temp_dir().join(format!("squant-conf-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = files
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = dir.join(format!("conf{i}.yaml"));
+                std::fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        (dir, paths)
+    }
+
+    fn load(files: &[&str], vars: &[(&str, &str)]) -> Result<AppConfig> {
+        let (dir, paths) = write_files(files);
+        let vars = vars
+            .iter()
+            .map(|&(key, value)| (key.to_owned(), value.to_owned()));
+        let config = AppConfig::load_with_env(&paths, vars);
+        std::fs::remove_dir_all(dir).unwrap();
+        config
+    }
+
+    fn param(config: &StrategyConfig, key: &str) -> Option<String> {
+        config.params.extra.get(key).map(ToString::to_string)
+    }
+
+    #[test]
+    fn merges_layers_by_name() {
+        let overlay = r#"
+simulated_trading: false
+strategies:
+  - name: "default"
+    params:
+      fast: 8
+  - name: "high_volume"
+    data_source:
+      - { exchange: "okx", symbol: "SOL-USDT", data_type: "book" }
+recorder:
+  dir: "data"
+"#;
+        let config = load(&[BASE, overlay], &[]).unwrap();
+        assert!(!config.simulated_trading);
+        let names: Vec<&str> = config
+            .strategies
+            .iter()
+            .map(|strategy| &*strategy.name)
+            .collect();
+        assert_eq!(names, ["default", "high_volume"]);
+
+        // 表按字段合并
+        let default = config.strategy("default").unwrap();
+        assert_eq!(param(default, "fast").as_deref(), Some("8"));
+        assert_eq!(param(default, "slow").as_deref(), Some("20"));
+        assert_eq!(default.params.retry.backoff.max_retries, 3);
+        assert_eq!(default.data_source.len(), 2);
+
+        // 没有 name 字段的列表整体替换
+        let high_volume = config.strategy("high_volume").unwrap();
+        assert_eq!(high_volume.data_source.len(), 1);
+        assert_eq!(high_volume.data_source[0].symbol, "SOL-USDT");
+
+        let recorder = config.recorder.unwrap();
+        assert_eq!(recorder.dir, Path::new("data"));
+        assert_eq!(recorder.flush_interval, DEFAULT_FLUSH_INTERVAL);
+    }
+
+    #[test]
+    fn applies_env_overrides() {
+        let config = load(
+            &[BASE],
+            &[
+                ("SQUANT__STRATEGIES__HIGH_VOLUME__PARAMS__TIMEOUT", "10"),
+                ("SQUANT__STRATEGIES__0__PARAMS__FAST", "4"),
+                ("SQUANT__STRATEGIES__0__PARAMS__JITTER", "false"),
+                ("SQUANT__RECORDER__DIR", "records"),
+                ("OTHER__SIMULATED_TRADING", "false"),
+            ],
+        )
+        .unwrap();
+        assert!(config.simulated_trading);
+        let default = config.strategy("default").unwrap();
+        assert_eq!(param(default, "fast").as_deref(), Some("4"));
+        assert!(!default.params.retry.backoff.jitter);
+        let high_volume = config.strategy("high_volume").unwrap();
+        assert_eq!(high_volume.params.retry.timeout, Duration::from_secs(10));
+        assert_eq!(config.recorder.unwrap().dir, Path::new("records"));
+
+        let err = load(
+            &[BASE],
+            &[("SQUANT__STRATEGIES__MISSING__PARAMS__FAST", "4")],
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "Invalid config override SQUANT__STRATEGIES__MISSING__PARAMS__FAST: no element named 'missing'"
+        );
+        let err = load(&[BASE], &[("SQUANT__STRATEGIES__5__NAME", "x")]).unwrap_err();
+        assert!(format!("{err:#}").ends_with("index 5 is out of range"));
+    }
+
+    #[test]
+    fn params_keep_the_retry_fields() {
+        let config = load(&[BASE], &[]).unwrap();
+        let default = config.strategy("default").unwrap();
+        let mut keys: Vec<_> = default.params.extra.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "backoff_factor",
+                "fast",
+                "jitter",
+                "max_retries",
+                "retry_delay",
+                "slow",
+                "timeout"
+            ]
+        );
+        assert_eq!(default.params.retry.backoff.max_retries, 3);
+    }
+
+    #[test]
+    fn validation_errors_name_the_key() {
+        let base = load(&[BASE], &[]).unwrap();
+        type Case = (fn(&mut AppConfig), &'static str);
+        let cases: Vec<Case> = vec![
+            (|config| config.strategies.clear(), "strategies: "),
+            (
+                |config| config.strategies[1].name = "default".into(),
+                "strategies[1].name: duplicate strategy name 'default'",
+            ),
+            (
+                |config| config.strategies[0].kind = "".into(),
+                "strategies[0].type: must not be empty",
+            ),
+            (
+                |config| config.strategies[1].params.retry.backoff.backoff_factor = 0.5,
+                "strategies[1].params.backoff_factor: must be at least 1",
+            ),
+            (
+                |config| config.strategies[0].params.retry.timeout = Duration::ZERO,
+                "strategies[0].params.timeout: must be greater than 0",
+            ),
+            (
+                |config| config.strategies[0].data_source[1].interval = None,
+                "strategies[0].data_source[1].interval: required for candle data",
+            ),
+            (
+                |config| config.strategies[1].data_source[0].interval = Some(CandleInterval::M1),
+                "strategies[1].data_source[0].interval: only valid for candle data",
+            ),
+            (
+                |config| config.strategies[1].data_source.clear(),
+                "strategies[1].data_source: ",
+            ),
+            (
+                |config| {
+                    config.recorder = Some(RecorderConfig {
+                        dir: "data".into(),
+                        flush_interval: Duration::ZERO,
+                    })
+                },
+                "recorder.flush_interval: must be greater than 0",
+            ),
+            (
+                |config| {
+                    config.strategies[0].sweep = Some(SweepConfig {
+                        metric: Metric::default(),
+                        params: BTreeMap::from([(
+                            "fast".to_owned(),
+                            ParamRange::Range {
+                                start: 1.0,
+                                end: 5.0,
+                                step: 0.0,
+                            },
+                        )]),
+                        walk_forward: None,
+                        fill_model: FillModel::default(),
+                    })
+                },
+                "strategies[0].sweep.params.fast.step: must be greater than 0",
+            ),
+        ];
+
+        assert!(base.validate().is_ok());
+        for (mutate, expected) in cases {
+            let mut config = base.clone();
+            mutate(&mut config);
+            let err = config.validate().unwrap_err().to_string();
+            assert!(
+                err.starts_with(expected),
+                "expected '{expected}', got '{err}'"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let err = load(&[BASE, "strategie: []"], &[]).unwrap_err();
+        assert!(format!("{err:#}").contains("strategie"), "{err:#}");
+    }
+
+    fn values(range: ParamRange) -> Vec<String> {
+        range.values().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn param_range_values() {
+        let range = |start, end, step| ParamRange::Range { start, end, step };
+        assert_eq!(values(range(10.0, 40.0, 10.0)), ["10", "20", "30", "40"]);
+        // 不整除时不超过 `end`
+        assert_eq!(values(range(10.0, 35.0, 10.0)), ["10", "20", "30"]);
+        assert_eq!(values(range(5.0, 5.0, 1.0)), ["5"]);
+        // 任一值为小数时生成小数，末尾的舍入误差不会丢掉 `end`
+        assert_eq!(values(range(1.0, 2.0, 0.5)), ["1", "1.5", "2"]);
+        assert_eq!(range(0.1, 0.3, 0.1).values().len(), 3);
+        assert!(matches!(
+            range(0.1, 0.3, 0.1).values()[2].kind,
+            ValueKind::Float(value) if (value - 0.3).abs() < 1e-12
+        ));
+
+        // 无效的区间没有取值
+        assert!(values(range(1.0, 5.0, 0.0)).is_empty());
+        assert!(values(range(1.0, 5.0, -1.0)).is_empty());
+        assert!(values(range(5.0, 1.0, 1.0)).is_empty());
+        assert!(values(range(1.0, 5.0, f64::NAN)).is_empty());
+
+        let list = ParamRange::Values(vec![Value::from(3), Value::from("a")]);
+        assert_eq!(values(list), ["3", "a"]);
+    }
+}
@@ -1,22 +1,62 @@
 use crate::Timestamp;
-use crate::order::Side;
+use crate::decimal::{Price, Quantity};
+use crate::order::{Side, TradeMode};
 use bytestring::ByteString;
+use chrono::{DateTime, Months};
 use futures_util::Stream;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
 use std::pin::Pin;
 use std::task::Context;
 use std::task::Poll;
-use strum::EnumDiscriminants;
+use std::time::Duration;
+use strum::{AsRefStr, Display, EnumDiscriminants, EnumString, IntoStaticStr};
 
-#[derive(Debug, EnumDiscriminants)]
-#[strum_discriminants(vis(pub), name(MarketDataType), derive(Deserialize))]
+/// 标准化的行情，序列化为 `{"trade": {...}}` 的形式，回测与录制的文件使用该格式
+#[derive(Debug, Clone, Serialize, Deserialize, EnumDiscriminants)]
+#[serde(rename_all = "lowercase")]
+#[strum_discriminants(
+    vis(pub),
+    name(MarketDataType),
+    derive(Deserialize),
+    serde(rename_all = "lowercase")
+)]
 pub enum DataEnum {
     Trade(TradeData),
+    #[strum_discriminants(serde(alias = "kline"))]
     Candle(CandleData),
     Book(BookData),
 }
 
-#[derive(Debug)]
+impl DataEnum {
+    pub fn symbol(&self) -> &ByteString {
+        match self {
+            DataEnum::Trade(data) => &data.symbol,
+            DataEnum::Candle(data) => &data.symbol,
+            DataEnum::Book(data) => &data.symbol,
+        }
+    }
+
+    /// 行情时间，K线为开盘时间
+    pub fn timestamp(&self) -> Timestamp {
+        match self {
+            DataEnum::Trade(data) => data.timestamp,
+            DataEnum::Candle(data) => data.timestamp,
+            DataEnum::Book(data) => data.timestamp,
+        }
+    }
+
+    /// 行情完整可见的时间，K线为收盘时间，其余与 [`DataEnum::timestamp`] 相同。
+    /// 回测按该时间回放，避免在K线收盘前看到收盘价
+    pub fn available_at(&self) -> Timestamp {
+        match self {
+            DataEnum::Candle(data) => data.interval.close_time(data.timestamp),
+            _ => self.timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeData {
     /// 交易所分配的唯一交易ID
     pub trade_id: ByteString,
@@ -25,10 +65,10 @@ pub struct TradeData {
     pub symbol: ByteString,
 
     /// 最新成交价。
-    pub price: f64,
+    pub price: Price,
 
     /// 最新成交的数量。
-    pub quantity: f64,
+    pub quantity: Quantity,
 
     /// 交易方向
     pub side: Side,
@@ -37,25 +77,126 @@ pub struct TradeData {
     pub timestamp: Timestamp,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CandleData {
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: f64,
+    /// 产品ID，例如 "BTC-USDT"。
+    pub symbol: ByteString,
+    /// 时间粒度
+    pub interval: CandleInterval,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Quantity,
     pub timestamp: Timestamp,
 }
 
-#[derive(Debug)]
+/// 深度推送的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BookAction {
+    /// 全量快照
+    Snapshot,
+    /// 增量更新，数量为 0 表示删除该档位
+    Update,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookData {
+    /// 产品ID，例如 "BTC-USDT"。
+    pub symbol: ByteString,
+    pub action: BookAction,
     /// (价格, 数量)
-    pub bids: Vec<(f64, f64)>,
+    pub bids: Vec<(Price, Quantity)>,
     /// (价格, 数量)
-    pub asks: Vec<(f64, f64)>,
+    pub asks: Vec<(Price, Quantity)>,
     pub timestamp: Timestamp,
 }
 
+/// 某一币种的账户余额
+#[derive(Debug, Clone)]
+pub struct Balance {
+    /// 币种，例如 "USDT"
+    pub currency: ByteString,
+    /// 币种总权益
+    pub equity: f64,
+    /// 币种余额
+    pub cash: f64,
+    /// 可用余额
+    pub available: f64,
+    /// 冻结（不可用）余额
+    pub frozen: f64,
+    /// 未实现盈亏
+    pub unrealized_pnl: f64,
+    /// 余额更新时间，Unix时间戳的毫秒数
+    pub timestamp: Timestamp,
+}
+
+/// 持仓方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PositionSide {
+    Long,
+    Short,
+    /// 买卖模式下的净持仓，数量的正负表示方向
+    Net,
+}
+
+/// 持仓
+#[derive(Debug, Clone)]
+pub struct Position {
+    /// 产品ID，例如 "BTC-USDT-SWAP"
+    pub symbol: ByteString,
+    pub side: PositionSide,
+    pub trade_mode: TradeMode,
+    /// 持仓数量，净持仓模式下为负数表示空头
+    pub quantity: Quantity,
+    /// 可平仓数量
+    pub available_quantity: Option<Quantity>,
+    /// 开仓均价
+    pub average_price: Option<Price>,
+    /// 标记价格
+    pub mark_price: Option<Price>,
+    /// 预估强平价
+    pub liquidation_price: Option<Price>,
+    /// 未实现盈亏（以标记价格计算）
+    pub unrealized_pnl: f64,
+    /// 已实现盈亏
+    pub realized_pnl: f64,
+    /// 杠杆倍数
+    pub leverage: Option<f64>,
+    /// 持仓更新时间，Unix时间戳的毫秒数
+    pub timestamp: Timestamp,
+}
+
+/// 订阅流中的事件，`Disconnected` 与 `Reconnected` 之间的数据可能存在缺口
+#[derive(Debug)]
+pub enum StreamEvent<T> {
+    Data(T),
+    /// 连接断开，正在重连
+    Disconnected,
+    /// 连接已恢复，所有订阅已重新发送
+    Reconnected,
+}
+
+impl<T> StreamEvent<T> {
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> StreamEvent<U> {
+        match self {
+            StreamEvent::Data(data) => StreamEvent::Data(f(data)),
+            StreamEvent::Disconnected => StreamEvent::Disconnected,
+            StreamEvent::Reconnected => StreamEvent::Reconnected,
+        }
+    }
+
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<StreamEvent<U>, E> {
+        Ok(match self {
+            StreamEvent::Data(data) => StreamEvent::Data(f(data)?),
+            StreamEvent::Disconnected => StreamEvent::Disconnected,
+            StreamEvent::Reconnected => StreamEvent::Reconnected,
+        })
+    }
+}
+
 #[pin_project::pin_project]
 pub struct DataStream<D, I, S, F>
 where
@@ -95,71 +236,164 @@ where
     }
 }
 
-// #[derive(
-//     Debug,
-//     PartialEq,
-//     Eq,
-//     Clone,
-//     Copy,
-//     Serialize,
-//     Deserialize,
-//     EnumString,
-//     Display,
-//     IntoStaticStr,
-//     AsRefStr,
-// )]
-// pub enum CandleInterval {
-//     // 基础时间粒度
-//     #[strum(serialize = "1m")]
-//     M1,
-//     #[strum(serialize = "3m")]
-//     M3,
-//     #[strum(serialize = "5m")]
-//     M5,
-//     #[strum(serialize = "15m")]
-//     M15,
-//     #[strum(serialize = "30m")]
-//     M30,
-//     #[strum(serialize = "1H")]
-//     H1,
-//     #[strum(serialize = "2H")]
-//     H2,
-//     #[strum(serialize = "4H")]
-//     H4,
-//
-//     // 香港时间开盘价 K 线
-//     #[strum(serialize = "6H")]
-//     H6,
-//     #[strum(serialize = "12H")]
-//     H12,
-//     #[strum(serialize = "1D")]
-//     D1,
-//     #[strum(serialize = "2D")]
-//     D2,
-//     #[strum(serialize = "3D")]
-//     D3,
-//     #[strum(serialize = "1W")]
-//     W1,
-//     #[strum(serialize = "1M")]
-//     Month1, // 使用 Month1 避免与 M1 (minute) 冲突
-//     #[strum(serialize = "3M")]
-//     Month3,
-//
-//     // UTC 时间开盘价 K 线
-//     #[strum(serialize = "6Hutc")]
-//     H6utc,
-//     #[strum(serialize = "12Hutc")]
-//     H12utc,
-//     #[strum(serialize = "1Dutc")]
-//     D1utc,
-//     #[strum(serialize = "2Dutc")]
-//     D2utc,
-//     #[strum(serialize = "3Dutc")]
-//     D3utc,
-//     #[strum(serialize = "1Wutc")]
-//     W1utc,
-//     #[strum(serialize = "1Mutc")]
-//     Month1utc,
-//     #[strum(serialize = "3Mutc")]
-//     Month3utc,
-// }
+/// K线时间粒度
+#[derive(
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
+    SerializeDisplay,
+    DeserializeFromStr,
+    EnumString,
+    Display,
+    IntoStaticStr,
+    AsRefStr,
+)]
+pub enum CandleInterval {
+    // 基础时间粒度
+    #[strum(serialize = "1s")]
+    S1,
+    #[default]
+    #[strum(serialize = "1m")]
+    M1,
+    #[strum(serialize = "3m")]
+    M3,
+    #[strum(serialize = "5m")]
+    M5,
+    #[strum(serialize = "15m")]
+    M15,
+    #[strum(serialize = "30m")]
+    M30,
+    #[strum(serialize = "1H")]
+    H1,
+    #[strum(serialize = "2H")]
+    H2,
+    #[strum(serialize = "4H")]
+    H4,
+
+    // 香港时间开盘价 K 线
+    #[strum(serialize = "6H")]
+    H6,
+    #[strum(serialize = "12H")]
+    H12,
+    #[strum(serialize = "1D")]
+    D1,
+    #[strum(serialize = "2D")]
+    D2,
+    #[strum(serialize = "3D")]
+    D3,
+    #[strum(serialize = "1W")]
+    W1,
+    #[strum(serialize = "1M")]
+    Month1, // 使用 Month1 避免与 M1 (minute) 冲突
+    #[strum(serialize = "3M")]
+    Month3,
+
+    // UTC 时间开盘价 K 线
+    #[strum(serialize = "6Hutc")]
+    H6utc,
+    #[strum(serialize = "12Hutc")]
+    H12utc,
+    #[strum(serialize = "1Dutc")]
+    D1utc,
+    #[strum(serialize = "2Dutc")]
+    D2utc,
+    #[strum(serialize = "3Dutc")]
+    D3utc,
+    #[strum(serialize = "1Wutc")]
+    W1utc,
+    #[strum(serialize = "1Mutc")]
+    Month1utc,
+    #[strum(serialize = "3Mutc")]
+    Month3utc,
+}
+
+impl CandleInterval {
+    /// 每根K线的时长，按月划分的粒度长度不固定，返回 `None`
+    pub fn duration(self) -> Option<Duration> {
+        use CandleInterval::*;
+
+        let secs = match self {
+            S1 => 1,
+            M1 => 60,
+            M3 => 3 * 60,
+            M5 => 5 * 60,
+            M15 => 15 * 60,
+            M30 => 30 * 60,
+            H1 => 3600,
+            H2 => 2 * 3600,
+            H4 => 4 * 3600,
+            H6 | H6utc => 6 * 3600,
+            H12 | H12utc => 12 * 3600,
+            D1 | D1utc => 86400,
+            D2 | D2utc => 2 * 86400,
+            D3 | D3utc => 3 * 86400,
+            W1 | W1utc => 7 * 86400,
+            Month1 | Month3 | Month1utc | Month3utc => return None,
+        };
+
+        Some(Duration::from_secs(secs))
+    }
+
+    /// 开盘时间为 `open` 的K线的收盘时间，按月划分的粒度按香港时间或 UTC 的自然月计算
+    pub fn close_time(self, open: Timestamp) -> Timestamp {
+        use CandleInterval::*;
+
+        if let Some(duration) = self.duration() {
+            return open + duration.as_millis();
+        }
+        let (months, offset) = match self {
+            Month1 => (1, HONG_KONG_OFFSET),
+            Month3 => (3, HONG_KONG_OFFSET),
+            Month1utc => (1, 0),
+            Month3utc => (3, 0),
+            _ => unreachable!("fixed intervals have a duration"),
+        };
+
+        i64::try_from(open)
+            .ok()
+            .and_then(|open| DateTime::from_timestamp_millis(open + offset))
+            .and_then(|open| open.checked_add_months(Months::new(months)))
+            .map_or(open, |close| {
+                (close.timestamp_millis() - offset) as Timestamp
+            })
+    }
+}
+
+/// 香港时间相对 UTC 的毫秒数
+const HONG_KONG_OFFSET: i64 = 8 * 3600 * 1000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(s: &str) -> Timestamp {
+        DateTime::parse_from_rfc3339(s).unwrap().timestamp_millis() as Timestamp
+    }
+
+    #[test]
+    fn candle_close_time() {
+        let open = millis("2024-01-31T16:00:00Z");
+        assert_eq!(CandleInterval::M1.close_time(open), open + 60_000);
+        assert_eq!(
+            CandleInterval::D1.close_time(open),
+            millis("2024-02-01T16:00:00Z")
+        );
+        // 香港时间 2024-02-01 至 2024-03-01
+        assert_eq!(
+            CandleInterval::Month1.close_time(millis("2024-01-31T16:00:00Z")),
+            millis("2024-02-29T16:00:00Z")
+        );
+        assert_eq!(
+            CandleInterval::Month1.close_time(millis("2024-02-29T16:00:00Z")),
+            millis("2024-03-31T16:00:00Z")
+        );
+        assert_eq!(
+            CandleInterval::Month3utc.close_time(millis("2024-01-01T00:00:00Z")),
+            millis("2024-04-01T00:00:00Z")
+        );
+    }
+}
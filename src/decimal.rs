@@ -0,0 +1,496 @@
+use eyre::{Result, bail, ensure};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+/// 价格
+pub type Price = Decimal;
+/// 数量
+pub type Quantity = Decimal;
+
+/// 支持的最大小数位数
+pub const MAX_SCALE: u8 = 18;
+
+/// 定点小数，值为 `mantissa / 10^scale`
+///
+/// 保留解析时的小数位数（即交易所按产品精度给出的位数），`to_string` 与原始文本完全一致，
+/// 可以直接用于下单和校验和计算。相等、排序与哈希只比较数值，与小数位数无关。
+///
+/// 运算符在溢出时饱和到尾数的边界，需要检测溢出时使用 `checked_*`。
+#[derive(Clone, Copy, Default, SerializeDisplay, DeserializeFromStr)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u8,
+}
+
+/// 按步长取整的方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// 四舍五入，恰好一半时远离零
+    Nearest,
+    /// 向负无穷取整
+    Down,
+    /// 向正无穷取整
+    Up,
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    pub fn new(mantissa: i128, scale: u8) -> Result<Self> {
+        ensure!(
+            scale <= MAX_SCALE,
+            "Decimal scale {scale} exceeds the maximum of {MAX_SCALE}"
+        );
+
+        Ok(Self { mantissa, scale })
+    }
+
+    pub fn mantissa(self) -> i128 {
+        self.mantissa
+    }
+
+    /// 小数位数
+    pub fn scale(self) -> u8 {
+        self.scale
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.mantissa == 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.mantissa > 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.mantissa < 0
+    }
+
+    pub fn abs(self) -> Self {
+        Self {
+            mantissa: self.mantissa.saturating_abs(),
+            scale: self.scale,
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// 去掉末尾多余的 0
+    pub fn normalize(self) -> Self {
+        let mut value = self;
+        while value.scale > 0 && value.mantissa % 10 == 0 {
+            value.mantissa /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+
+    /// 增加小数位数，数值不变。`scale` 小于当前位数时返回 `None`
+    pub fn rescale(self, scale: u8) -> Option<Self> {
+        if scale < self.scale || scale > MAX_SCALE {
+            return None;
+        }
+
+        Some(Self {
+            mantissa: self.mantissa.checked_mul(pow10(scale - self.scale))?,
+            scale,
+        })
+    }
+
+    /// 取整到 `step` 的整数倍，结果的小数位数与 `step` 相同，溢出时返回 `None`
+    pub fn round_to(self, step: Decimal, rounding: Rounding) -> Option<Self> {
+        if !step.is_positive() {
+            return Some(self);
+        }
+
+        let scale = self.scale.max(step.scale);
+        let value = self.aligned(scale)?;
+        let step_mantissa = step.aligned(scale)?;
+        let (quotient, remainder) = (value / step_mantissa, value % step_mantissa);
+        let steps = match rounding {
+            Rounding::Nearest if remainder.unsigned_abs() * 2 >= step_mantissa as u128 => {
+                quotient + remainder.signum()
+            }
+            Rounding::Down if remainder < 0 => quotient - 1,
+            Rounding::Up if remainder > 0 => quotient + 1,
+            _ => quotient,
+        };
+
+        Some(Self {
+            mantissa: steps.checked_mul(step.mantissa)?,
+            scale: step.scale,
+        })
+    }
+
+    /// 是否为 `step` 的整数倍。对齐小数位数时溢出说明 `self` 的精度超出了 `step`，返回 `false`
+    pub fn is_multiple_of(self, step: Decimal) -> bool {
+        if step.is_zero() {
+            return true;
+        }
+
+        let scale = self.scale.max(step.scale);
+        match (self.aligned(scale), step.aligned(scale)) {
+            (Some(value), Some(step)) => value % step == 0,
+            _ => false,
+        }
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let scale = self.scale.max(rhs.scale);
+        Some(Self {
+            mantissa: self.aligned(scale)?.checked_add(rhs.aligned(scale)?)?,
+            scale,
+        })
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(Self {
+            mantissa: rhs.mantissa.checked_neg()?,
+            scale: rhs.scale,
+        })
+    }
+
+    /// 结果的小数位数超过 [`MAX_SCALE`] 时四舍五入
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let mut mantissa = self.mantissa.checked_mul(rhs.mantissa)?;
+        let mut scale = self.scale + rhs.scale;
+        if scale > MAX_SCALE {
+            let divisor = pow10(scale - MAX_SCALE);
+            let remainder = mantissa % divisor;
+            mantissa /= divisor;
+            if remainder.unsigned_abs() * 2 >= divisor as u128 {
+                mantissa += remainder.signum();
+            }
+            scale = MAX_SCALE;
+        }
+
+        Some(Self { mantissa, scale })
+    }
+
+    /// 将尾数对齐到更大的小数位数
+    fn aligned(self, scale: u8) -> Option<i128> {
+        self.rescale(scale).map(|value| value.mantissa)
+    }
+
+    /// 溢出时按浮点近似值的符号取尾数的边界
+    fn saturated(approx: f64, scale: u8) -> Self {
+        Self {
+            mantissa: if approx < 0.0 { -i128::MAX } else { i128::MAX },
+            scale,
+        }
+    }
+}
+
+fn pow10(exp: u8) -> i128 {
+    10i128.pow(exp as u32)
+}
+
+impl FromStr for Decimal {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if int_part.is_empty() && frac_part.is_empty()
+            || !int_part.bytes().all(|b| b.is_ascii_digit())
+            || !frac_part.bytes().all(|b| b.is_ascii_digit())
+        {
+            bail!("Invalid decimal: '{s}'");
+        }
+        if frac_part.len() > MAX_SCALE as usize {
+            bail!("Too many decimal places: '{s}'");
+        }
+
+        let mut mantissa: i128 = 0;
+        for b in int_part.bytes().chain(frac_part.bytes()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add((b - b'0') as i128))
+                .ok_or_else(|| eyre::eyre!("Decimal overflow: '{s}'"))?;
+        }
+
+        Ok(Self {
+            mantissa: if negative { -mantissa } else { mantissa },
+            scale: frac_part.len() as u8,
+        })
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mantissa < 0 {
+            f.write_str("-")?;
+        }
+
+        let abs = self.mantissa.unsigned_abs();
+        let divisor = pow10(self.scale) as u128;
+        write!(f, "{}", abs / divisor)?;
+        if self.scale > 0 {
+            write!(f, ".{:0width$}", abs % divisor, width = self.scale as usize)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Self {
+            mantissa: value as i128,
+            scale: 0,
+        }
+    }
+}
+
+/// 按 `f64` 的最短十进制表示转换，例如 `0.1` 转换为 `0.1`
+impl TryFrom<f64> for Decimal {
+    type Error = eyre::Report;
+
+    fn try_from(value: f64) -> Result<Self> {
+        ensure!(value.is_finite(), "Cannot convert {value} to decimal");
+
+        value.to_string().parse()
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        match (self.rescale(scale), other.rescale(scale)) {
+            (Some(a), Some(b)) => a.mantissa.cmp(&b.mantissa),
+            // 对齐时溢出说明两者数量级相差极大，用浮点比较即可
+            _ => self.to_f64().total_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let normalized = self.normalize();
+        normalized.mantissa.hash(state);
+        normalized.scale.hash(state);
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            mantissa: self.mantissa.checked_neg().unwrap_or(i128::MAX),
+            scale: self.scale,
+        }
+    }
+}
+
+/// 溢出时饱和
+impl Add for Decimal {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.checked_add(rhs).unwrap_or_else(|| {
+            Self::saturated(self.to_f64() + rhs.to_f64(), self.scale.max(rhs.scale))
+        })
+    }
+}
+
+/// 溢出时饱和
+impl Sub for Decimal {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.checked_sub(rhs).unwrap_or_else(|| {
+            Self::saturated(self.to_f64() - rhs.to_f64(), self.scale.max(rhs.scale))
+        })
+    }
+}
+
+/// 结果的小数位数超过 [`MAX_SCALE`] 时四舍五入，溢出时饱和
+impl Mul for Decimal {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.checked_mul(rhs).unwrap_or_else(|| {
+            Self::saturated(
+                self.to_f64() * rhs.to_f64(),
+                (self.scale + rhs.scale).min(MAX_SCALE),
+            )
+        })
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::BuildHasher;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    fn hash(value: Decimal) -> u64 {
+        static STATE: std::sync::LazyLock<std::collections::hash_map::RandomState> =
+            std::sync::LazyLock::new(Default::default);
+        STATE.hash_one(value)
+    }
+
+    #[test]
+    fn parses_and_displays_exchange_text() {
+        for text in [
+            "0",
+            "1.0",
+            "1.00",
+            "-0.5",
+            "8476.98",
+            "0.000000000000000001",
+        ] {
+            assert_eq!(dec(text).to_string(), text);
+        }
+        assert_eq!(dec("+1.5").to_string(), "1.5");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        assert_eq!(dec("5.").to_string(), "5");
+        assert_eq!(dec("1.50").scale(), 2);
+
+        for text in ["", "-", ".", "1e5", "1.2.3", "abc", "0.0000000000000000001"] {
+            assert!(text.parse::<Decimal>().is_err(), "{text}");
+        }
+        assert!("1".repeat(40).parse::<Decimal>().is_err());
+    }
+
+    #[test]
+    fn equality_and_hash_ignore_trailing_zeros() {
+        assert_eq!(dec("1.0"), dec("1.00"));
+        assert_eq!(hash(dec("1.0")), hash(dec("1.00")));
+        assert_eq!(hash(dec("1")), hash(dec("1.000")));
+        assert_eq!(hash(dec("0")), hash(dec("-0.00")));
+        assert_ne!(dec("1.0"), dec("1.01"));
+        assert!(dec("0.9") < dec("1.00"));
+        assert!(dec("-2") < dec("-1.5"));
+    }
+
+    #[test]
+    fn rounds_to_step() {
+        let step = dec("0.5");
+        assert_eq!(
+            dec("1.24").round_to(step, Rounding::Nearest),
+            Some(dec("1.0"))
+        );
+        assert_eq!(
+            dec("1.25").round_to(step, Rounding::Nearest),
+            Some(dec("1.5"))
+        );
+        assert_eq!(
+            dec("-1.25").round_to(step, Rounding::Nearest),
+            Some(dec("-1.5"))
+        );
+        assert_eq!(dec("1.4").round_to(step, Rounding::Down), Some(dec("1.0")));
+        assert_eq!(
+            dec("-1.4").round_to(step, Rounding::Down),
+            Some(dec("-1.5"))
+        );
+        assert_eq!(dec("1.1").round_to(step, Rounding::Up), Some(dec("1.5")));
+        // 结果的小数位数与步长一致
+        assert_eq!(
+            dec("100.26")
+                .round_to(dec("0.10"), Rounding::Nearest)
+                .unwrap()
+                .to_string(),
+            "100.30"
+        );
+        assert_eq!(
+            dec("1.3").round_to(Decimal::ZERO, Rounding::Down),
+            Some(dec("1.3"))
+        );
+    }
+
+    #[test]
+    fn overflow_does_not_panic() {
+        let huge = Decimal::new(i128::MAX, 0).unwrap();
+        let tiny = dec("0.000000000000000001");
+
+        assert_eq!(huge.checked_add(huge), None);
+        assert_eq!(huge.checked_add(tiny), None);
+        assert_eq!(huge.checked_mul(huge), None);
+        assert_eq!((-huge).checked_sub(huge), None);
+        assert_eq!(huge.round_to(tiny, Rounding::Down), None);
+        assert!(!huge.is_multiple_of(tiny));
+
+        assert!((huge + huge).is_positive());
+        assert!((-huge - huge).is_negative());
+        assert!((huge * -huge).is_negative());
+        assert!((huge + tiny).is_positive());
+        assert_eq!(-(huge * huge), -huge * huge);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(dec("1.5").checked_add(dec("0.25")), Some(dec("1.75")));
+        assert_eq!(dec("1.5").checked_sub(dec("2")), Some(dec("-0.5")));
+        assert_eq!(dec("1.5").checked_mul(dec("0.2")), Some(dec("0.3")));
+        assert_eq!(
+            dec("0.000000001").checked_mul(dec("0.0000000015")),
+            Some(dec("0.000000000000000002"))
+        );
+        assert_eq!(
+            [dec("0.1"), dec("0.2"), dec("0.30")]
+                .into_iter()
+                .sum::<Decimal>(),
+            dec("0.6")
+        );
+    }
+}
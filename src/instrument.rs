@@ -0,0 +1,350 @@
+use crate::{
+    Timestamp,
+    decimal::{Price, Quantity, Rounding},
+};
+use bytestring::ByteString;
+use eyre::{ContextCompat, Result, ensure};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// 产品类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstrumentKind {
+    /// 币币
+    Spot,
+    /// 币币杠杆
+    Margin,
+    /// 永续合约
+    Swap,
+    /// 交割合约
+    Futures,
+    /// 期权
+    Option,
+}
+
+/// 产品状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstrumentState {
+    /// 交易中
+    Live,
+    /// 暂停交易
+    Suspend,
+    /// 预上线
+    Preopen,
+    /// 测试中，不可交易
+    Test,
+}
+
+/// 产品的交易规则
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    /// 产品ID，例如 "BTC-USDT"
+    pub symbol: ByteString,
+    pub kind: InstrumentKind,
+    pub state: InstrumentState,
+    /// 交易货币，仅币币/币币杠杆
+    pub base_currency: Option<ByteString>,
+    /// 计价货币，仅币币/币币杠杆
+    pub quote_currency: Option<ByteString>,
+    /// 盈亏结算和保证金币种，仅合约/期权
+    pub settle_currency: Option<ByteString>,
+    /// 下单价格精度
+    pub tick_size: Price,
+    /// 下单数量精度，合约/期权的单位为张
+    pub lot_size: Quantity,
+    /// 最小下单数量
+    pub min_size: Quantity,
+    /// 合约面值，仅合约/期权
+    pub contract_value: Option<f64>,
+    /// 上线时间，Unix时间戳的毫秒数
+    pub listed_at: Option<Timestamp>,
+    /// 交割/行权时间，Unix时间戳的毫秒数
+    pub expires_at: Option<Timestamp>,
+}
+
+impl Instrument {
+    /// 将价格四舍五入到最近的 `tick_size` 整数倍，结果的小数位数与 `tick_size` 相同
+    pub fn round_price(&self, price: Price) -> Result<Price> {
+        price
+            .round_to(self.tick_size, Rounding::Nearest)
+            .wrap_err_with(|| format!("Price {price} of {} is out of range", self.symbol))
+    }
+
+    /// 将数量向下取整到 `lot_size` 的整数倍，避免超出预期的下单数量
+    pub fn round_quantity(&self, quantity: Quantity) -> Result<Quantity> {
+        quantity
+            .round_to(self.lot_size, Rounding::Down)
+            .wrap_err_with(|| format!("Quantity {quantity} of {} is out of range", self.symbol))
+    }
+
+    /// 检查价格是否为正数且是 `tick_size` 的整数倍
+    pub fn validate_price(&self, price: Price) -> Result<()> {
+        ensure!(
+            price.is_positive(),
+            "Invalid price for {}: {price}",
+            self.symbol
+        );
+        ensure!(
+            price.is_multiple_of(self.tick_size),
+            "Price {price} of {} is not a multiple of tick size {}",
+            self.symbol,
+            self.tick_size
+        );
+
+        Ok(())
+    }
+
+    /// 检查数量是否不小于 `min_size` 且是 `lot_size` 的整数倍
+    pub fn validate_quantity(&self, quantity: Quantity) -> Result<()> {
+        ensure!(
+            quantity >= self.min_size,
+            "Quantity {quantity} of {} is below the minimum size {}",
+            self.symbol,
+            self.min_size
+        );
+        ensure!(
+            quantity.is_multiple_of(self.lot_size),
+            "Quantity {quantity} of {} is not a multiple of lot size {}",
+            self.symbol,
+            self.lot_size
+        );
+
+        Ok(())
+    }
+}
+
+/// 每种产品类型的产品，同一产品ID可以同时出现在多种类型中，例如币币与币币杠杆的 "BTC-USDT"
+type Instruments = HashMap<InstrumentKind, HashMap<ByteString, Instrument>>;
+
+/// 按产品ID查找时依次查找的产品类型
+const LOOKUP_ORDER: [InstrumentKind; 5] = [
+    InstrumentKind::Spot,
+    InstrumentKind::Margin,
+    InstrumentKind::Swap,
+    InstrumentKind::Futures,
+    InstrumentKind::Option,
+];
+
+/// 内存中的产品注册表，可以在多个任务间共享
+///
+/// 产品按类型分别保存，只按产品ID查找时依次查找币币、币币杠杆、永续、交割与期权。
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentRegistry {
+    instruments: Arc<RwLock<Instruments>>,
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<Instrument> {
+        lookup(&self.read(), symbol).cloned()
+    }
+
+    /// 查找 `kind` 类型的产品
+    pub fn get_by_kind(&self, kind: InstrumentKind, symbol: &str) -> Option<Instrument> {
+        self.read()
+            .get(&kind)
+            .and_then(|instruments| instruments.get(symbol))
+            .cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 新增或覆盖产品
+    pub fn extend(&self, instruments: impl IntoIterator<Item = Instrument>) {
+        let mut map = self.write();
+        for instrument in instruments {
+            map.entry(instrument.kind)
+                .or_default()
+                .insert(instrument.symbol.clone(), instrument);
+        }
+    }
+
+    /// 用 `kind` 类型产品的全量快照替换注册表中该类型的产品，快照中没有的产品（例如已下线或已交割）被删除
+    ///
+    /// 快照中其它类型的产品被忽略，其它类型的产品不受影响。
+    pub fn replace(&self, kind: InstrumentKind, instruments: impl IntoIterator<Item = Instrument>) {
+        let instruments = instruments
+            .into_iter()
+            .filter(|instrument| instrument.kind == kind)
+            .map(|instrument| (instrument.symbol.clone(), instrument))
+            .collect();
+        self.write().insert(kind, instruments);
+    }
+
+    pub fn round_price(&self, symbol: &str, price: Price) -> Result<Price> {
+        self.with(symbol, |instrument| instrument.round_price(price))
+    }
+
+    pub fn round_quantity(&self, symbol: &str, quantity: Quantity) -> Result<Quantity> {
+        self.with(symbol, |instrument| instrument.round_quantity(quantity))
+    }
+
+    pub fn validate_price(&self, symbol: &str, price: Price) -> Result<()> {
+        self.with(symbol, |instrument| instrument.validate_price(price))
+    }
+
+    pub fn validate_quantity(&self, symbol: &str, quantity: Quantity) -> Result<()> {
+        self.with(symbol, |instrument| instrument.validate_quantity(quantity))
+    }
+
+    fn with<R>(&self, symbol: &str, f: impl FnOnce(&Instrument) -> Result<R>) -> Result<R> {
+        let map = self.read();
+        let instrument =
+            lookup(&map, symbol).wrap_err_with(|| format!("Unknown instrument: '{symbol}'"))?;
+
+        f(instrument)
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Instruments> {
+        self.instruments
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Instruments> {
+        self.instruments
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn lookup<'a>(instruments: &'a Instruments, symbol: &str) -> Option<&'a Instrument> {
+    LOOKUP_ORDER
+        .iter()
+        .find_map(|kind| instruments.get(kind)?.get(symbol))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(symbol: &str, kind: InstrumentKind) -> Instrument {
+        Instrument {
+            symbol: symbol.into(),
+            kind,
+            state: InstrumentState::Live,
+            base_currency: None,
+            quote_currency: None,
+            settle_currency: None,
+            tick_size: "0.1".parse().unwrap(),
+            lot_size: "1".parse().unwrap(),
+            min_size: "1".parse().unwrap(),
+            contract_value: None,
+            listed_at: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn replace_drops_delisted_instruments_of_the_same_kind() {
+        let registry = InstrumentRegistry::new();
+        registry.replace(
+            InstrumentKind::Futures,
+            [
+                instrument("BTC-USD-240329", InstrumentKind::Futures),
+                instrument("BTC-USD-240628", InstrumentKind::Futures),
+            ],
+        );
+        registry.replace(
+            InstrumentKind::Swap,
+            [instrument("BTC-USD-SWAP", InstrumentKind::Swap)],
+        );
+
+        registry.replace(
+            InstrumentKind::Futures,
+            [
+                instrument("BTC-USD-240628", InstrumentKind::Futures),
+                instrument("BTC-USD-240927", InstrumentKind::Futures),
+            ],
+        );
+        assert!(registry.get("BTC-USD-240329").is_none());
+        assert!(registry.get("BTC-USD-240628").is_some());
+        assert!(registry.get("BTC-USD-240927").is_some());
+        assert!(registry.get("BTC-USD-SWAP").is_some());
+        assert_eq!(registry.len(), 3);
+    }
+
+    #[test]
+    fn keeps_the_same_symbol_of_different_kinds() {
+        let registry = InstrumentRegistry::new();
+        registry.replace(
+            InstrumentKind::Margin,
+            [instrument("BTC-USDT", InstrumentKind::Margin)],
+        );
+        registry.replace(
+            InstrumentKind::Spot,
+            [
+                instrument("BTC-USDT", InstrumentKind::Spot),
+                instrument("ETH-USDT", InstrumentKind::Spot),
+            ],
+        );
+        assert_eq!(registry.len(), 3);
+
+        // 刷新一种类型不影响另一种类型的同名产品
+        registry.replace(
+            InstrumentKind::Margin,
+            [instrument("BTC-USDT", InstrumentKind::Margin)],
+        );
+        registry.replace(
+            InstrumentKind::Spot,
+            [instrument("ETH-USDT", InstrumentKind::Spot)],
+        );
+        assert!(
+            registry
+                .get_by_kind(InstrumentKind::Spot, "BTC-USDT")
+                .is_none()
+        );
+        let margin = registry
+            .get_by_kind(InstrumentKind::Margin, "BTC-USDT")
+            .unwrap();
+        assert_eq!(margin.kind, InstrumentKind::Margin);
+        assert_eq!(
+            registry.get("BTC-USDT").map(|instrument| instrument.kind),
+            Some(InstrumentKind::Margin)
+        );
+        assert_eq!(registry.len(), 2);
+
+        registry.extend([instrument("BTC-USDT", InstrumentKind::Spot)]);
+        assert_eq!(
+            registry.get("BTC-USDT").map(|instrument| instrument.kind),
+            Some(InstrumentKind::Spot)
+        );
+    }
+
+    #[test]
+    fn rounds_and_validates() {
+        let registry = InstrumentRegistry::new();
+        registry.extend([instrument("BTC-USDT", InstrumentKind::Spot)]);
+
+        let price = registry
+            .round_price("BTC-USDT", "100.26".parse().unwrap())
+            .unwrap();
+        assert_eq!(price.to_string(), "100.3");
+        let quantity = registry
+            .round_quantity("BTC-USDT", "2.9".parse().unwrap())
+            .unwrap();
+        assert_eq!(quantity.to_string(), "2");
+        assert!(
+            registry
+                .validate_price("BTC-USDT", "100.25".parse().unwrap())
+                .is_err()
+        );
+        assert!(
+            registry
+                .validate_quantity("BTC-USDT", "0".parse().unwrap())
+                .is_err()
+        );
+        assert!(registry.round_price("ETH-USDT", price).is_err());
+    }
+}
@@ -1,5 +1,14 @@
+pub mod analytics;
+pub mod backtest;
+pub mod book;
 pub mod client;
+pub mod clock;
+pub mod conf;
 pub mod data;
+pub mod decimal;
+pub mod instrument;
 pub mod order;
+pub mod recorder;
+pub mod strategy;
 
 pub type Timestamp = u128;
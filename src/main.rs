@@ -1,27 +1,194 @@
 use eyre::Result;
-use futures_util::StreamExt;
-use squant::client::{
-    DataSubscriber,
-    okx::{
-        OkxClientV5,
-        model::{OkxArg, OkxWebSocketSubscribeRequest},
+use futures_util::{StreamExt, stream};
+use itertools::Itertools;
+use squant::{
+    client::{
+        DataSubscriber,
+        okx::{
+            OkxClientV5,
+            model::{
+                OkxArg, OkxChannel, OkxInstType, OkxMarketData, OkxOrderData,
+                OkxWebSocketSubscribeRequest, OkxWebSocketSubscribeResponse,
+            },
+        },
+        rate_limit::RateLimiter,
     },
+    clock::Clock,
+    conf::{AppConfig, RecorderConfig, StrategyConfig},
+    recorder::Recorder,
+    strategy::{Strategy, StrategyRegistry, runtime::StrategyRuntime},
 };
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let mut client = OkxClientV5::builder().build()?;
+    dotenvy::dotenv().ok();
 
-    let params = OkxWebSocketSubscribeRequest::builder(
-        "subscribe",
-        vec![OkxArg::new("candle1D", "BTC-USDT")],
-    )
-    .build();
-    let mut stream = client.subscribe_data(params).await?;
+    // 配置文件按顺序叠加，默认为 conf.yaml
+    let mut paths: Vec<_> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        paths.push("conf.yaml".to_owned());
+    }
+    let config = AppConfig::load(&paths)?;
+
+    // 先创建所有策略，参数错误时不启动任何策略
+    let registry = StrategyRegistry::builtin();
+    let strategies = config
+        .strategies
+        .into_iter()
+        .map(|config| Ok((registry.create(&config)?, config)))
+        .collect::<Result<Vec<_>>>()?;
 
-    while let Some(data) = stream.next().await {
-        println!("debug0: {data:?}");
+    let simulated_trading = config.simulated_trading;
+    let shutdown = CancellationToken::new();
+    let rate_limiter = RateLimiter::default();
+    let clock = Clock::new();
+    let mut tasks = JoinSet::new();
+    if let Some(recorder) = config.recorder {
+        // 多个策略订阅的相同行情只录制一次
+        let args = strategies
+            .iter()
+            .flat_map(|(_, config)| config.data_source.iter().map(OkxArg::from))
+            .unique()
+            .collect();
+        tasks.spawn(record(
+            recorder,
+            simulated_trading,
+            args,
+            rate_limiter.clone(),
+            clock.clone(),
+            shutdown.clone(),
+        ));
+    }
+    for (strategy, config) in strategies {
+        tasks.spawn(run_strategy(
+            config,
+            simulated_trading,
+            strategy,
+            rate_limiter.clone(),
+            clock.clone(),
+            shutdown.clone(),
+        ));
+    }
+
+    let ctrl_c = shutdown.clone();
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c().await.ok();
+        ctrl_c.cancel();
+    });
+    while let Some(result) = tasks.join_next().await {
+        result??;
     }
 
     Ok(())
 }
+
+/// 单独订阅所有策略的行情并录制，直到收到退出信号或行情流出错
+async fn record(
+    config: RecorderConfig,
+    simulated_trading: bool,
+    args: Vec<OkxArg>,
+    rate_limiter: RateLimiter,
+    clock: Clock,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    // 每个频道使用一个客户端单独订阅，以便按订阅时的频道名分目录录制
+    let mut clients = args
+        .iter()
+        .map(|_| {
+            OkxClientV5::builder()
+                .simulated_trading(simulated_trading)
+                .rate_limiter(rate_limiter.clone())
+                .clock(clock.clone())
+                .build()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut markets = Vec::new();
+    for (client, arg) in clients.iter_mut().zip(args) {
+        let channel = arg.channel.to_string();
+        let params =
+            OkxWebSocketSubscribeRequest::<OkxMarketData>::builder("subscribe", vec![arg]).build();
+        let market =
+            DataSubscriber::<OkxWebSocketSubscribeResponse<OkxMarketData>>::subscribe_data(
+                client, params,
+            )
+            .await?;
+        markets.push((channel, market));
+    }
+
+    let recorder = Recorder::spawn(config.dir, config.flush_interval);
+    let streams = markets
+        .into_iter()
+        .map(|(channel, market)| Box::pin(recorder.tee(channel.into(), market)));
+    let result = {
+        let mut market = stream::select_all(streams);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break Ok(()),
+                event = market.next() => match event {
+                    Some(Err(err)) => break Err(err.wrap_err("Recorder market stream failed")),
+                    Some(Ok(_)) => {}
+                    None => break Ok(()),
+                },
+            }
+        }
+    };
+
+    // 出错时也先写完已录制的数据
+    tokio::task::spawn_blocking(move || recorder.close()).await??;
+    result
+}
+
+async fn run_strategy(
+    config: StrategyConfig,
+    simulated_trading: bool,
+    strategy: Box<dyn Strategy>,
+    rate_limiter: RateLimiter,
+    clock: Clock,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    // 行情、订单推送与下单各使用一个客户端，共享限流器与时钟
+    let client = || {
+        OkxClientV5::builder()
+            .simulated_trading(simulated_trading)
+            .retry(config.params.retry)
+            .rate_limiter(rate_limiter.clone())
+            .clock(clock.clone())
+            .build()
+    };
+    let (mut market_client, mut order_client, executor) = (client()?, client()?, client()?);
+
+    let args: Vec<_> = config.data_source.iter().map(OkxArg::from).collect();
+    let params = OkxWebSocketSubscribeRequest::<OkxMarketData>::builder("subscribe", args).build();
+    let market = DataSubscriber::<OkxWebSocketSubscribeResponse<OkxMarketData>>::subscribe_data(
+        &mut market_client,
+        params,
+    )
+    .await?;
+
+    // 订单推送需要 API Key，订阅失败时错误交给策略的 on_error，之后收不到订单更新
+    let params = OkxWebSocketSubscribeRequest::<OkxOrderData>::builder(
+        "subscribe",
+        vec![OkxArg::with_inst_type(OkxChannel::Orders, OkxInstType::Any)],
+    )
+    .build();
+    let orders =
+        match DataSubscriber::<OkxWebSocketSubscribeResponse<OkxOrderData>>::subscribe_data(
+            &mut order_client,
+            params,
+        )
+        .await
+        {
+            Ok(orders) => orders.left_stream(),
+            Err(err) => {
+                stream::iter([Err(err.wrap_err("Order updates unavailable"))]).right_stream()
+            }
+        };
+
+    StrategyRuntime::builder(config.name, strategy, executor)
+        .clock(clock)
+        .build()
+        .run(market, orders, shutdown.cancelled())
+        .await
+}
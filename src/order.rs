@@ -1,4 +1,13 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use crate::{
+    Timestamp,
+    decimal::{Price, Quantity},
+};
+use bon::Builder;
+use bytestring::ByteString;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Side {
     Buy,
     Sell,
@@ -15,3 +24,161 @@ impl TryFrom<&str> for Side {
         }
     }
 }
+
+/// 交易模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeMode {
+    /// 非保证金（现货）
+    Cash,
+    /// 全仓
+    Cross,
+    /// 逐仓
+    Isolated,
+}
+
+/// 订单类型
+///
+/// 交易所推送的其它订单类型按成交方式归入相近的类型，无法归类的解析为 [`OrderType::Unknown`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderType {
+    /// 限价单
+    #[serde(alias = "mmp")]
+    Limit,
+    /// 市价单
+    Market,
+    /// 只做 maker 单
+    #[serde(alias = "mmp_and_post_only")]
+    PostOnly,
+    /// 全部成交或立即取消
+    #[serde(alias = "op_fok")]
+    Fok,
+    /// 立即成交并取消剩余
+    #[serde(alias = "optimal_limit_ioc")]
+    Ioc,
+    /// 无法识别的订单类型，只出现在订单推送中，不能用于下单
+    #[serde(other)]
+    Unknown,
+}
+
+/// 订单的引用方式，交易所订单ID或客户自定义订单ID
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OrderRef {
+    Exchange(ByteString),
+    Client(ByteString),
+}
+
+/// 下单请求
+#[derive(Debug, Clone, Builder)]
+pub struct NewOrder {
+    /// 产品ID，例如 "BTC-USDT"。
+    #[builder(start_fn, into)]
+    pub symbol: ByteString,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub trade_mode: TradeMode,
+    /// 委托数量，币币以交易货币计，合约以张数计
+    pub quantity: Quantity,
+    /// 市价单不需要价格
+    pub price: Option<Price>,
+    /// 客户自定义订单ID
+    #[builder(into)]
+    pub client_order_id: Option<ByteString>,
+    /// 是否只减仓
+    pub reduce_only: Option<bool>,
+}
+
+/// 撤单请求
+#[derive(Debug, Clone)]
+pub struct CancelOrder {
+    pub symbol: ByteString,
+    pub order: OrderRef,
+}
+
+impl CancelOrder {
+    pub fn new(symbol: impl Into<ByteString>, order: OrderRef) -> Self {
+        Self {
+            symbol: symbol.into(),
+            order,
+        }
+    }
+}
+
+/// 改单请求
+#[derive(Debug, Clone, Builder)]
+pub struct AmendOrder {
+    #[builder(start_fn, into)]
+    pub symbol: ByteString,
+    #[builder(start_fn)]
+    pub order: OrderRef,
+    pub new_quantity: Option<Quantity>,
+    pub new_price: Option<Price>,
+    /// 修改失败时是否自动撤单
+    #[builder(default)]
+    pub cancel_on_fail: bool,
+}
+
+/// 交易所对下单、撤单、改单请求的确认
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderAck {
+    /// 交易所分配的订单ID
+    pub order_id: ByteString,
+    /// 客户自定义订单ID
+    pub client_order_id: Option<ByteString>,
+}
+
+/// 订单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderStatus {
+    /// 等待成交
+    New,
+    /// 部分成交
+    PartiallyFilled,
+    /// 完全成交
+    Filled,
+    /// 已撤销
+    Canceled,
+    /// 无法识别的订单状态，只出现在订单推送中
+    Unknown,
+}
+
+/// 一笔成交
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub trade_id: ByteString,
+    pub price: Price,
+    pub quantity: Quantity,
+    /// 手续费，负数表示支出，正数表示返佣
+    pub fee: f64,
+    pub fee_currency: ByteString,
+    /// 是否为 maker 成交
+    pub is_maker: bool,
+    pub timestamp: Timestamp,
+}
+
+/// 标准化的订单状态更新
+#[derive(Debug, Clone)]
+pub struct OrderUpdate {
+    pub symbol: ByteString,
+    pub order_id: ByteString,
+    pub client_order_id: Option<ByteString>,
+    pub side: Side,
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    /// 委托价格，市价单没有价格
+    pub price: Option<Price>,
+    /// 委托数量
+    pub quantity: Quantity,
+    /// 累计成交数量
+    pub filled_quantity: Quantity,
+    /// 成交均价
+    pub average_price: Option<Price>,
+    /// 累计手续费，负数表示支出，正数表示返佣
+    pub fee: f64,
+    pub fee_currency: ByteString,
+    /// 本次更新对应的成交，没有新成交时为 `None`
+    pub last_fill: Option<Fill>,
+    /// 订单状态更新时间，Unix时间戳的毫秒数
+    pub timestamp: Timestamp,
+}
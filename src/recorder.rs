@@ -0,0 +1,453 @@
+//! 行情录制：将订阅流写入按产品、频道与日期划分的压缩文件，供回测回放
+//!
+//! 文件路径为 `<dir>/<symbol>/<channel>/<YYYY-MM-DD>.jsonl.gz`，频道为订阅时使用的频道名
+//! （例如 "books5"、"bbo-tbt"、"candle1m"），日期取交易所时间戳（UTC）。
+//! 每行一条 [`RecordedEvent`]。每次刷新将缓冲的记录追加为一个独立的 gzip 成员并同步到磁盘，
+//! 进程崩溃最多留下一个不完整的成员，重新打开文件时会被截断，之前的数据不受影响。
+
+use crate::{
+    Timestamp,
+    data::{DataEnum, StreamEvent},
+};
+use bytestring::ByteString;
+use chrono::{DateTime, NaiveDate, Utc};
+use eyre::{Result, WrapErr, eyre};
+use flate2::{Compression, bufread::GzDecoder, read::MultiGzDecoder, write::GzEncoder};
+use futures_util::{Stream, StreamExt, future};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// 默认的刷新间隔，崩溃时最多丢失这段时间内的数据
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// 单个文件缓冲的数据达到该大小时立即刷新
+const MAX_BUFFER: usize = 1 << 20;
+
+/// 录制的一条行情
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// 本地收到数据的时间，Unix时间戳的毫秒数
+    pub received: Timestamp,
+    /// 交易所时间戳见 [`DataEnum::timestamp`]
+    pub data: DataEnum,
+}
+
+/// 同步写入录制文件，按交易所时间戳的日期轮换
+#[derive(Debug)]
+pub struct RecordWriter {
+    dir: PathBuf,
+    /// (产品ID, 频道) 当前写入的文件
+    files: HashMap<(String, String), RecordFile>,
+}
+
+#[derive(Debug)]
+struct RecordFile {
+    date: NaiveDate,
+    file: File,
+    /// 尚未写入文件的 JSON Lines
+    buffer: Vec<u8>,
+}
+
+impl RecordWriter {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// 写入 `channel` 频道的缓冲区，缓冲区过大时写入文件
+    pub fn write(&mut self, channel: &str, event: &RecordedEvent) -> Result<()> {
+        let key = (event.data.symbol().to_string(), channel.to_owned());
+        let date = DateTime::from_timestamp_millis(event.data.timestamp() as i64)
+            .ok_or_else(|| eyre!("Invalid timestamp {}", event.data.timestamp()))?
+            .date_naive();
+
+        // 跨日时先写完旧文件再切换
+        if let Some(current) = self.files.get_mut(&key)
+            && current.date != date
+        {
+            current.flush()?;
+            self.files.remove(&key);
+        }
+        let current = match self.files.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let (symbol, channel) = entry.key();
+                let path = self
+                    .dir
+                    .join(sanitize(symbol))
+                    .join(sanitize(channel))
+                    .join(format!("{date}.jsonl.gz"));
+                entry.insert(RecordFile::open(&path, date)?)
+            }
+        };
+
+        simd_json::to_writer(&mut current.buffer, event)?;
+        current.buffer.push(b'\n');
+        if current.buffer.len() >= MAX_BUFFER {
+            current.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// 将所有缓冲的数据写入文件并同步到磁盘
+    pub fn flush(&mut self) -> Result<()> {
+        self.files.values_mut().try_for_each(RecordFile::flush)
+    }
+}
+
+impl RecordFile {
+    /// 以追加方式打开文件，先截掉上次崩溃留下的不完整数据
+    fn open(path: &Path, date: NaiveDate) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("Failed to create {}", dir.display()))?;
+        }
+        if path.exists() {
+            recover(path).wrap_err_with(|| format!("Failed to recover {}", path.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+        Ok(Self {
+            date,
+            file,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// 将缓冲区压缩为一个完整的 gzip 成员追加到文件末尾
+    fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.buffer)?;
+        let member = encoder.finish()?;
+        self.file.write_all(&member)?;
+        self.file.sync_data()?;
+        self.buffer.clear();
+
+        Ok(())
+    }
+}
+
+/// 找到最后一个完整的 gzip 成员的结尾，截掉之后的数据
+fn recover(path: &Path) -> Result<()> {
+    let file = OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut reader = BufReader::new(&file);
+    let mut valid = 0;
+    while valid < len {
+        let mut decoder = GzDecoder::new(reader);
+        if io::copy(&mut decoder, &mut io::sink()).is_err() {
+            break;
+        }
+        reader = decoder.into_inner();
+        valid = reader.stream_position()?;
+    }
+
+    if valid < len {
+        file.set_len(valid)?;
+        file.sync_data()?;
+    }
+
+    Ok(())
+}
+
+/// 在后台线程写入录制文件，订阅流通过 [`Recorder::tee`] 接入
+#[derive(Debug)]
+pub struct Recorder {
+    /// (频道, 行情)
+    events: flume::Sender<(ByteString, RecordedEvent)>,
+    writer: JoinHandle<Result<()>>,
+    /// 写入线程失败的原因，完整的错误由 [`Recorder::close`] 返回
+    failure: Arc<OnceLock<String>>,
+}
+
+impl Recorder {
+    /// 在 `dir` 下录制，每隔 `flush_interval` 将数据写入磁盘
+    pub fn spawn(dir: impl Into<PathBuf>, flush_interval: Duration) -> Self {
+        let (events, events_rx) = flume::unbounded::<(ByteString, RecordedEvent)>();
+        let mut writer = RecordWriter::new(dir);
+        let failure = Arc::new(OnceLock::new());
+
+        let writer = thread::spawn({
+            let failure = failure.clone();
+            move || -> Result<()> {
+                let result = write_events(&mut writer, &events_rx, flush_interval);
+                // 先记录原因再关闭接收端，发送失败的一方总能读到原因
+                if let Err(err) = &result {
+                    let _ = failure.set(format!("{err:#}"));
+                }
+                drop(events_rx);
+                result
+            }
+        });
+
+        Self {
+            events,
+            writer,
+            failure,
+        }
+    }
+
+    /// 原样转发 `channel` 频道的订阅流，同时录制其中的行情，收到数据的时间取本地时钟
+    ///
+    /// 同一产品不同频道（例如 books 与 books5）的行情写入不同的目录，
+    /// 因此每个频道需要单独订阅。K线订阅只产出已完结的K线，录制的K线与历史K线一致。
+    /// 写入线程失败后，流在下一条行情处产出该错误并结束，完整的错误由 [`Recorder::close`] 返回。
+    pub fn tee<S>(&self, channel: ByteString, stream: S) -> impl Stream<Item = S::Item> + use<S>
+    where
+        S: Stream<Item = Result<StreamEvent<Vec<DataEnum>>>>,
+    {
+        let events = self.events.clone();
+        let failure = self.failure.clone();
+        stream.scan(false, move |stopped, item| {
+            if *stopped {
+                return future::ready(None);
+            }
+            if let Ok(StreamEvent::Data(data)) = &item {
+                let received = Utc::now().timestamp_millis().max(0) as Timestamp;
+                for data in data {
+                    let event = RecordedEvent {
+                        received,
+                        data: data.clone(),
+                    };
+                    if events.send((channel.clone(), event)).is_err() {
+                        *stopped = true;
+                        let cause = failure.get().map_or("thread panicked", String::as_str);
+                        return future::ready(Some(Err(eyre!(
+                            "Recorder stopped, '{channel}' is no longer recorded: {cause}"
+                        ))));
+                    }
+                }
+            }
+            future::ready(Some(item))
+        })
+    }
+
+    /// 等待所有 [`Recorder::tee`] 返回的流结束，写完剩余数据
+    pub fn close(self) -> Result<()> {
+        drop(self.events);
+        self.writer
+            .join()
+            .map_err(|_| eyre!("Recorder thread panicked"))?
+    }
+}
+
+/// 写入收到的行情直到所有发送端关闭
+///
+/// 持续有行情时 `recv_deadline` 总能取到数据而不会超时，因此每次写入后也检查是否到了刷新时间。
+fn write_events(
+    writer: &mut RecordWriter,
+    events: &flume::Receiver<(ByteString, RecordedEvent)>,
+    flush_interval: Duration,
+) -> Result<()> {
+    let mut deadline = Instant::now() + flush_interval;
+    loop {
+        match events.recv_deadline(deadline) {
+            Ok((channel, event)) => writer.write(&channel, &event)?,
+            Err(flume::RecvTimeoutError::Timeout) => {}
+            Err(flume::RecvTimeoutError::Disconnected) => return writer.flush(),
+        }
+        if Instant::now() >= deadline {
+            writer.flush()?;
+            deadline = Instant::now() + flush_interval;
+        }
+    }
+}
+
+/// 读取一个录制文件，行情按录制顺序排列
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<RecordedEvent>> {
+    let path = path.as_ref();
+    let file = File::open(path).wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+    let mut events = Vec::new();
+    for (i, line) in BufReader::new(MultiGzDecoder::new(file))
+        .lines()
+        .enumerate()
+    {
+        let line = line.wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = simd_json::serde::from_slice::<RecordedEvent>(&mut line.into_bytes())
+            .wrap_err_with(|| format!("Invalid event at {}:{}", path.display(), i + 1))?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// 避免产品ID中的路径分隔符
+fn sanitize(name: &str) -> String {
+    name.replace(['/', '\\'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backtest::source::read_events, data::TradeData, order::Side};
+
+    fn event(id: u128, timestamp: Timestamp) -> RecordedEvent {
+        RecordedEvent {
+            received: timestamp + 5,
+            data: DataEnum::Trade(TradeData {
+                trade_id: id.to_string().into(),
+                symbol: "BTC-USDT".into(),
+                price: "100".parse().unwrap(),
+                quantity: "1".parse().unwrap(),
+                side: Side::Buy,
+                timestamp,
+            }),
+        }
+    }
+
+    fn trade_ids(path: &Path) -> Vec<String> {
+        read_recording(path)
+            .unwrap()
+            .into_iter()
+            .map(|event| match event.data {
+                DataEnum::Trade(trade) => trade.trade_id.to_string(),
+                data => panic!("unexpected {data:?}"),
+            })
+            .collect()
+    }
+
+    /// 2024-01-01 00:00:00 UTC
+    const DAY: Timestamp = 1_704_067_200_000;
+
+    #[test]
+    fn separates_channels_and_recovers_truncated_member() {
+        let dir = std::env::temp_dir().join(format!("squant-recorder-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("BTC-USDT/trades/2024-01-01.jsonl.gz");
+
+        let mut writer = RecordWriter::new(&dir);
+        writer.write("trades", &event(1, DAY)).unwrap();
+        writer.flush().unwrap();
+        writer.write("trades", &event(2, DAY + 1)).unwrap();
+        writer.write("trades-all", &event(3, DAY + 1)).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        assert_eq!(trade_ids(&path), ["1", "2"]);
+        assert_eq!(
+            trade_ids(&dir.join("BTC-USDT/trades-all/2024-01-01.jsonl.gz")),
+            ["3"]
+        );
+
+        // 模拟写入第三个 gzip 成员时崩溃
+        let complete = fs::metadata(&path).unwrap().len();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        simd_json::to_writer(&mut encoder, &event(4, DAY + 2)).unwrap();
+        let member = encoder.finish().unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&member[..member.len() / 2])
+            .unwrap();
+        assert!(read_recording(&path).is_err());
+
+        let mut writer = RecordWriter::new(&dir);
+        writer.write("trades", &event(5, DAY + 3)).unwrap();
+        writer.write("trades", &event(6, DAY + 1)).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(trade_ids(&path), ["1", "2", "5", "6"]);
+        assert!(fs::metadata(&path).unwrap().len() > complete);
+
+        // 回放时按交易所时间戳排序，时间相同时保持收到的顺序
+        let replayed: Vec<_> = read_events(&path)
+            .unwrap()
+            .into_iter()
+            .map(|data| match data {
+                DataEnum::Trade(trade) => trade.trade_id.to_string(),
+                data => panic!("unexpected {data:?}"),
+            })
+            .collect();
+        assert_eq!(replayed, ["1", "2", "6", "5"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// 文件中 gzip 成员的个数，每次刷新追加一个成员
+    fn members(path: &Path) -> usize {
+        let mut reader = BufReader::new(File::open(path).unwrap());
+        let mut count = 0;
+        while !reader.fill_buf().unwrap().is_empty() {
+            let mut decoder = GzDecoder::new(reader);
+            io::copy(&mut decoder, &mut io::sink()).unwrap();
+            reader = decoder.into_inner();
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn flushes_on_time_under_steady_traffic() {
+        let dir = std::env::temp_dir().join(format!("squant-recorder-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("BTC-USDT/trades/2024-01-01.jsonl.gz");
+        // 总量不到缓冲区上限，不会因为缓冲区过大而刷新
+        let line = simd_json::to_vec(&event(u128::from(u32::MAX), DAY))
+            .unwrap()
+            .len()
+            + 1;
+        let events: Vec<_> = (0..(MAX_BUFFER / line) as u128)
+            .map(|id| event(id, DAY))
+            .collect();
+        let count = events.len();
+
+        // 发送远快于写入，写入线程处理完之前队列一直不空
+        let recorder = Recorder::spawn(&dir, Duration::from_millis(1));
+        for event in events {
+            recorder.events.send(("trades".into(), event)).unwrap();
+        }
+        recorder.close().unwrap();
+
+        assert_eq!(trade_ids(&path).len(), count);
+        assert!(members(&path) > 1, "flushed only after the queue drained");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn tee_ends_with_the_writer_error() {
+        // 录制目录是一个文件，写入线程无法创建子目录
+        let dir = std::env::temp_dir().join(format!("squant-recorder-{}", uuid::Uuid::new_v4()));
+        fs::write(&dir, "").unwrap();
+        let recorder = Recorder::spawn(&dir, DEFAULT_FLUSH_INTERVAL);
+        recorder
+            .events
+            .send(("trades".into(), event(1, DAY)))
+            .unwrap();
+        while !recorder.writer.is_finished() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let items = [2, 3].map(|id| Ok(StreamEvent::Data(vec![event(id, DAY).data])));
+        let items: Vec<_> = recorder
+            .tee("trades".into(), futures_util::stream::iter(items))
+            .collect()
+            .await;
+        assert_eq!(items.len(), 1);
+        let err = items.into_iter().next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("Failed to create"), "{err:#}");
+
+        let err = recorder.close().unwrap_err();
+        assert!(format!("{err:#}").contains("Failed to create"), "{err:#}");
+        fs::remove_file(&dir).unwrap();
+    }
+}
@@ -0,0 +1,479 @@
+//! 内置策略，conf.yaml 中以 `type` 引用
+
+use super::{Context, OrderIntent, Strategy};
+use crate::{
+    Timestamp,
+    data::CandleData,
+    decimal::{Price, Quantity},
+    order::{NewOrder, OrderStatus, OrderType, OrderUpdate, Side, TradeMode},
+};
+use bytestring::ByteString;
+use eyre::{Report, Result, ensure};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+
+/// [`SmaCross`] 的类型名
+pub const DEFAULT: &str = "default";
+/// [`VolumeBreakout`] 的类型名
+pub const HIGH_VOLUME: &str = "high_volume";
+
+/// 一根已收盘的K线
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    timestamp: Timestamp,
+    open: Price,
+    close: Price,
+    volume: Quantity,
+}
+
+impl From<&CandleData> for Bar {
+    fn from(value: &CandleData) -> Self {
+        Self {
+            timestamp: value.timestamp,
+            open: value.open,
+            close: value.close,
+            volume: value.volume,
+        }
+    }
+}
+
+/// 每个产品的持仓与K线状态
+#[derive(Debug, Default)]
+struct SymbolState {
+    /// 已收盘的K线，最多保留策略需要的根数
+    bars: VecDeque<Bar>,
+    position: Quantity,
+    /// 是否有未完成的订单
+    pending: bool,
+}
+
+impl SymbolState {
+    /// 记录一根已收盘的K线，重复或更早的K线返回 `None`
+    fn update(&mut self, candle: &CandleData, keep: usize) -> Option<Bar> {
+        let bar = Bar::from(candle);
+        if self
+            .bars
+            .back()
+            .is_some_and(|last| bar.timestamp <= last.timestamp)
+        {
+            return None;
+        }
+
+        self.bars.push_back(bar);
+        while self.bars.len() > keep {
+            self.bars.pop_front();
+        }
+
+        Some(bar)
+    }
+
+    fn on_order_update(&mut self, update: &OrderUpdate) {
+        if let Some(fill) = &update.last_fill {
+            match update.side {
+                Side::Buy => self.position += fill.quantity,
+                Side::Sell => self.position -= fill.quantity,
+            }
+        }
+        if matches!(update.status, OrderStatus::Filled | OrderStatus::Canceled) {
+            self.pending = false;
+        }
+    }
+}
+
+/// 记录各产品的状态并以市价单开平仓
+#[derive(Debug, Default)]
+struct Positions {
+    symbols: HashMap<ByteString, SymbolState>,
+}
+
+impl Positions {
+    fn get(&mut self, symbol: &ByteString) -> &mut SymbolState {
+        self.symbols.entry(symbol.clone()).or_default()
+    }
+
+    fn submit(
+        &mut self,
+        ctx: &mut Context,
+        symbol: &ByteString,
+        side: Side,
+        quantity: Quantity,
+        trade_mode: TradeMode,
+    ) {
+        self.get(symbol).pending = true;
+        ctx.place_order(
+            NewOrder::builder(symbol.clone())
+                .side(side)
+                .order_type(OrderType::Market)
+                .trade_mode(trade_mode)
+                .quantity(quantity)
+                .build(),
+        );
+    }
+
+    fn on_order_update(&mut self, update: &OrderUpdate) {
+        self.get(&update.symbol).on_order_update(update);
+    }
+
+    /// 下单失败时不会收到订单更新，直接解除挂单状态
+    fn on_order_error(&mut self, intent: &OrderIntent) {
+        if let OrderIntent::Place(order) = intent {
+            self.get(&order.symbol).pending = false;
+        }
+    }
+}
+
+/// [`SmaCross`] 的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct SmaCrossParams {
+    /// 快线周期
+    #[serde(default = "SmaCrossParams::default_fast")]
+    pub fast: usize,
+    /// 慢线周期
+    #[serde(default = "SmaCrossParams::default_slow")]
+    pub slow: usize,
+    /// 每次开仓的数量
+    pub quantity: Quantity,
+    #[serde(default = "default_trade_mode")]
+    pub trade_mode: TradeMode,
+}
+
+impl SmaCrossParams {
+    fn default_fast() -> usize {
+        5
+    }
+
+    fn default_slow() -> usize {
+        20
+    }
+}
+
+fn default_trade_mode() -> TradeMode {
+    TradeMode::Cash
+}
+
+/// 双均线策略：收盘价的快线上穿慢线时开多，下穿时平仓
+#[derive(Debug)]
+pub struct SmaCross {
+    params: SmaCrossParams,
+    positions: Positions,
+}
+
+impl SmaCross {
+    pub fn new(params: SmaCrossParams) -> Result<Self> {
+        let SmaCrossParams { fast, slow, .. } = params;
+        ensure!(
+            0 < fast && fast < slow,
+            "params.fast must be greater than 0 and less than params.slow, got fast {fast}, slow {slow}"
+        );
+        ensure!(
+            params.quantity.is_positive(),
+            "params.quantity must be positive"
+        );
+
+        Ok(Self {
+            params,
+            positions: Positions::default(),
+        })
+    }
+
+    /// 最近 `period` 根K线收盘价的均值，`offset` 为向前跳过的根数
+    fn sma(bars: &VecDeque<Bar>, period: usize, offset: usize) -> f64 {
+        let sum: f64 = bars
+            .iter()
+            .rev()
+            .skip(offset)
+            .take(period)
+            .map(|bar| bar.close.to_f64())
+            .sum();
+        sum / period as f64
+    }
+}
+
+impl Strategy for SmaCross {
+    fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+        let SmaCrossParams {
+            fast,
+            slow,
+            quantity,
+            trade_mode,
+        } = self.params;
+        let state = self.positions.get(&candle.symbol);
+        if state.update(candle, slow + 1).is_none() || state.bars.len() <= slow || state.pending {
+            return Ok(());
+        }
+
+        let delta =
+            |offset| Self::sma(&state.bars, fast, offset) - Self::sma(&state.bars, slow, offset);
+        let (prev, curr) = (delta(1), delta(0));
+        let position = state.position;
+        if prev <= 0.0 && curr > 0.0 && position.is_zero() {
+            self.positions
+                .submit(ctx, &candle.symbol, Side::Buy, quantity, trade_mode);
+        } else if prev >= 0.0 && curr < 0.0 && position.is_positive() {
+            self.positions
+                .submit(ctx, &candle.symbol, Side::Sell, position, trade_mode);
+        }
+
+        Ok(())
+    }
+
+    fn on_order_update(&mut self, _ctx: &mut Context, update: &OrderUpdate) -> Result<()> {
+        self.positions.on_order_update(update);
+        Ok(())
+    }
+
+    fn on_order_error(
+        &mut self,
+        _ctx: &mut Context,
+        intent: &OrderIntent,
+        _err: &Report,
+    ) -> Result<()> {
+        self.positions.on_order_error(intent);
+        Ok(())
+    }
+}
+
+/// [`VolumeBreakout`] 的参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolumeBreakoutParams {
+    /// 计算平均成交量的K线根数
+    #[serde(default = "VolumeBreakoutParams::default_window")]
+    pub window: usize,
+    /// 成交量超过平均值的倍数时视为放量
+    #[serde(default = "VolumeBreakoutParams::default_multiplier")]
+    pub multiplier: f64,
+    /// 开仓后持有的K线根数
+    #[serde(default = "VolumeBreakoutParams::default_hold")]
+    pub hold: usize,
+    /// 每次开仓的数量
+    pub quantity: Quantity,
+    #[serde(default = "default_trade_mode")]
+    pub trade_mode: TradeMode,
+}
+
+impl VolumeBreakoutParams {
+    fn default_window() -> usize {
+        20
+    }
+
+    fn default_multiplier() -> f64 {
+        3.0
+    }
+
+    fn default_hold() -> usize {
+        5
+    }
+}
+
+/// 放量突破策略：收阳且成交量超过近期均量的若干倍时开多，持有固定根数后平仓
+#[derive(Debug)]
+pub struct VolumeBreakout {
+    params: VolumeBreakoutParams,
+    positions: Positions,
+    /// 各产品开仓后经过的K线根数
+    held: HashMap<ByteString, usize>,
+}
+
+impl VolumeBreakout {
+    pub fn new(params: VolumeBreakoutParams) -> Result<Self> {
+        ensure!(params.window > 0, "params.window must be greater than 0");
+        ensure!(
+            params.multiplier.is_finite() && params.multiplier > 0.0,
+            "params.multiplier must be positive"
+        );
+        ensure!(
+            params.quantity.is_positive(),
+            "params.quantity must be positive"
+        );
+
+        Ok(Self {
+            params,
+            positions: Positions::default(),
+            held: HashMap::new(),
+        })
+    }
+}
+
+impl Strategy for VolumeBreakout {
+    fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+        let VolumeBreakoutParams {
+            window,
+            multiplier,
+            hold,
+            quantity,
+            trade_mode,
+        } = self.params;
+        let state = self.positions.get(&candle.symbol);
+        let Some(bar) = state.update(candle, window + 1) else {
+            return Ok(());
+        };
+        if state.pending {
+            return Ok(());
+        }
+
+        let position = state.position;
+        if position.is_positive() {
+            let held = self.held.entry(candle.symbol.clone()).or_default();
+            *held += 1;
+            if *held >= hold {
+                self.positions
+                    .submit(ctx, &candle.symbol, Side::Sell, position, trade_mode);
+            }
+            return Ok(());
+        }
+
+        if state.bars.len() <= window {
+            return Ok(());
+        }
+        let average = state
+            .bars
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|bar| bar.volume.to_f64())
+            .sum::<f64>()
+            / window as f64;
+        if bar.close > bar.open && bar.volume.to_f64() > average * multiplier {
+            self.held.insert(candle.symbol.clone(), 0);
+            self.positions
+                .submit(ctx, &candle.symbol, Side::Buy, quantity, trade_mode);
+        }
+
+        Ok(())
+    }
+
+    fn on_order_update(&mut self, _ctx: &mut Context, update: &OrderUpdate) -> Result<()> {
+        self.positions.on_order_update(update);
+        Ok(())
+    }
+
+    fn on_order_error(
+        &mut self,
+        _ctx: &mut Context,
+        intent: &OrderIntent,
+        _err: &Report,
+    ) -> Result<()> {
+        self.positions.on_order_error(intent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backtest::{Backtest, SimExchange},
+        data::{CandleInterval, DataEnum, StreamEvent},
+        strategy::runtime::StrategyRuntime,
+    };
+    use futures_util::stream;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    type Signals = Arc<Mutex<Vec<(Timestamp, Side)>>>;
+
+    /// 记录被包装的策略下单时的 [`Context::now`] 与方向
+    struct Recorded<S> {
+        inner: S,
+        signals: Signals,
+    }
+
+    impl<S: Strategy> Strategy for Recorded<S> {
+        fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+            self.inner.on_candle(ctx, candle)?;
+            for intent in ctx.take_intents() {
+                if let OrderIntent::Place(order) = intent {
+                    self.signals.lock().unwrap().push((ctx.now(), order.side));
+                    ctx.place_order(order);
+                }
+            }
+            Ok(())
+        }
+
+        fn on_order_update(&mut self, ctx: &mut Context, update: &OrderUpdate) -> Result<()> {
+            self.inner.on_order_update(ctx, update)
+        }
+
+        fn on_order_error(
+            &mut self,
+            ctx: &mut Context,
+            intent: &OrderIntent,
+            err: &Report,
+        ) -> Result<()> {
+            self.inner.on_order_error(ctx, intent, err)
+        }
+    }
+
+    fn sma_cross(signals: &Signals) -> Box<dyn Strategy> {
+        let params = SmaCrossParams {
+            fast: 2,
+            slow: 3,
+            quantity: "1".parse().unwrap(),
+            trade_mode: TradeMode::Cash,
+        };
+        Box::new(Recorded {
+            inner: SmaCross::new(params).unwrap(),
+            signals: signals.clone(),
+        })
+    }
+
+    const MINUTE: Timestamp = 60_000;
+
+    /// 每分钟一根K线，开盘价等于收盘价
+    fn candles(closes: &[&str]) -> Vec<DataEnum> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let close = close.parse().unwrap();
+                DataEnum::Candle(CandleData {
+                    symbol: "BTC-USDT".into(),
+                    interval: CandleInterval::M1,
+                    open: close,
+                    high: close,
+                    low: close,
+                    close,
+                    volume: "1".parse().unwrap(),
+                    timestamp: i as Timestamp * MINUTE,
+                })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn signals_on_the_same_bar_in_backtest_and_live() {
+        // 第 6 根K线（下标 5）收盘时快线上穿慢线
+        let events = candles(&["10", "10", "10", "9", "8", "12", "13"]);
+
+        let backtest = Signals::default();
+        Backtest::builder("backtest", sma_cross(&backtest))
+            .build()
+            .run(events.clone())
+            .await
+            .unwrap();
+
+        let live = Signals::default();
+        let executor = SimExchange::new(10_000.0, "USDT", Default::default());
+        // 已收盘的K线逐条推送，同一根K线的重复推送被忽略
+        let pushes = events
+            .iter()
+            .flat_map(|data| [data.clone(), data.clone()])
+            .map(|data| Ok(StreamEvent::Data(vec![data])));
+        StrategyRuntime::builder("live", sma_cross(&live), executor)
+            .reorder_window(Duration::ZERO)
+            .build()
+            .run(
+                stream::iter(pushes),
+                stream::empty(),
+                std::future::pending(),
+            )
+            .await
+            .unwrap();
+
+        let expected = vec![(6 * MINUTE, Side::Buy)];
+        assert_eq!(*backtest.lock().unwrap(), expected);
+        assert_eq!(*live.lock().unwrap(), expected);
+    }
+}
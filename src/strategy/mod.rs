@@ -0,0 +1,226 @@
+use crate::{
+    Timestamp,
+    book::OrderBook,
+    client::OrderExecutor,
+    conf::StrategyConfig,
+    data::{CandleData, DataEnum, TradeData},
+    order::{AmendOrder, CancelOrder, NewOrder, OrderUpdate},
+};
+use bytestring::ByteString;
+use eyre::{OptionExt, Report, Result};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+pub mod builtin;
+pub mod runtime;
+
+/// 策略，由运行时按时间顺序回调
+///
+/// 回调是同步的，下单等操作通过 [`Context`] 记录为 [`OrderIntent`]，
+/// 回调返回后由运行时交给执行器。回调返回错误时运行时停止该策略。
+#[allow(unused_variables)]
+pub trait Strategy: Send {
+    fn on_start(&mut self, ctx: &mut Context) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_candle(&mut self, ctx: &mut Context, candle: &CandleData) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_trade(&mut self, ctx: &mut Context, trade: &TradeData) -> Result<()> {
+        Ok(())
+    }
+
+    /// `book` 为应用本次推送后的本地订单簿
+    fn on_book(&mut self, ctx: &mut Context, book: &OrderBook) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_order_update(&mut self, ctx: &mut Context, update: &OrderUpdate) -> Result<()> {
+        Ok(())
+    }
+
+    /// 通过 [`Context::set_timer`] 设置的定时器触发
+    fn on_timer(&mut self, ctx: &mut Context) -> Result<()> {
+        Ok(())
+    }
+
+    /// 执行器未能执行 `intent`，例如下单被拒绝
+    fn on_order_error(
+        &mut self,
+        ctx: &mut Context,
+        intent: &OrderIntent,
+        err: &Report,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// 行情或订单流中的非致命错误
+    fn on_error(&mut self, ctx: &mut Context, err: &Report) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_stop(&mut self, ctx: &mut Context) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 策略发出的交易指令
+#[derive(Debug, Clone)]
+pub enum OrderIntent {
+    Place(NewOrder),
+    Cancel(CancelOrder),
+    Amend(AmendOrder),
+}
+
+/// 策略回调的上下文
+#[derive(Debug, Default)]
+pub struct Context {
+    now: Timestamp,
+    intents: Vec<OrderIntent>,
+    timer: Option<Duration>,
+}
+
+impl Context {
+    pub fn new(now: Timestamp) -> Self {
+        Self {
+            now,
+            ..Default::default()
+        }
+    }
+
+    /// 当前事件的时间，Unix时间戳的毫秒数。回测时为模拟时钟的时间
+    pub fn now(&self) -> Timestamp {
+        self.now
+    }
+
+    pub fn set_now(&mut self, now: Timestamp) {
+        self.now = now;
+    }
+
+    pub fn place_order(&mut self, order: NewOrder) {
+        self.intents.push(OrderIntent::Place(order));
+    }
+
+    pub fn cancel_order(&mut self, cancel: CancelOrder) {
+        self.intents.push(OrderIntent::Cancel(cancel));
+    }
+
+    pub fn amend_order(&mut self, amend: AmendOrder) {
+        self.intents.push(OrderIntent::Amend(amend));
+    }
+
+    /// 取出本次回调发出的指令
+    pub fn take_intents(&mut self) -> Vec<OrderIntent> {
+        std::mem::take(&mut self.intents)
+    }
+
+    /// 每隔 `interval` 触发一次 [`Strategy::on_timer`]，重复调用会替换之前的定时器
+    pub fn set_timer(&mut self, interval: Duration) {
+        self.timer = Some(interval).filter(|interval| !interval.is_zero());
+    }
+
+    pub fn cancel_timer(&mut self) {
+        self.timer = None;
+    }
+
+    pub fn timer(&self) -> Option<Duration> {
+        self.timer
+    }
+}
+
+/// 将一条行情交给策略，深度推送先应用到 `books` 中对应的本地订单簿。调用前需要设置 [`Context::now`]
+pub(crate) fn on_data(
+    strategy: &mut dyn Strategy,
+    ctx: &mut Context,
+    books: &mut HashMap<ByteString, OrderBook>,
+    data: &DataEnum,
+) -> Result<()> {
+    match data {
+        DataEnum::Trade(trade) => strategy.on_trade(ctx, trade),
+        DataEnum::Candle(candle) => strategy.on_candle(ctx, candle),
+        DataEnum::Book(data) => {
+            let book = books
+                .entry(data.symbol.clone())
+                .or_insert_with(|| OrderBook::new(data.symbol.clone()));
+            book.apply(data);
+            strategy.on_book(ctx, book)
+        }
+    }
+}
+
+/// 按顺序执行策略发出的指令，执行失败时回调 [`Strategy::on_order_error`]
+pub(crate) async fn execute<E: OrderExecutor>(
+    strategy: &mut dyn Strategy,
+    ctx: &mut Context,
+    executor: &mut E,
+) -> Result<()> {
+    let mut intents = VecDeque::from(ctx.take_intents());
+    while let Some(intent) = intents.pop_front() {
+        let result = match &intent {
+            OrderIntent::Place(order) => executor.place_order(order.clone()).await,
+            OrderIntent::Cancel(cancel) => executor.cancel_order(cancel.clone()).await,
+            OrderIntent::Amend(amend) => executor.amend_order(amend.clone()).await,
+        };
+        if let Err(err) = result {
+            strategy.on_order_error(ctx, &intent, &err)?;
+            intents.extend(ctx.take_intents());
+        }
+    }
+
+    Ok(())
+}
+
+type StrategyFactory = Box<dyn Fn(&StrategyConfig) -> Result<Box<dyn Strategy>> + Send + Sync>;
+
+/// 按 conf.yaml 中的 `type` 创建策略
+#[derive(Default)]
+pub struct StrategyRegistry {
+    factories: HashMap<ByteString, StrategyFactory>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册了内置策略的注册表，见 [`builtin`]
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(builtin::DEFAULT, |config| {
+            Ok(Box::new(builtin::SmaCross::new(config.params.parse()?)?))
+        });
+        registry.register(builtin::HIGH_VOLUME, |config| {
+            Ok(Box::new(builtin::VolumeBreakout::new(
+                config.params.parse()?,
+            )?))
+        });
+        registry
+    }
+
+    /// 注册策略类型，已存在的类型会被替换
+    pub fn register<F>(&mut self, kind: impl Into<ByteString>, factory: F) -> &mut Self
+    where
+        F: Fn(&StrategyConfig) -> Result<Box<dyn Strategy>> + Send + Sync + 'static,
+    {
+        self.factories.insert(kind.into(), Box::new(factory));
+        self
+    }
+
+    pub fn contains(&self, kind: &str) -> bool {
+        self.factories.contains_key(kind)
+    }
+
+    pub fn create(&self, config: &StrategyConfig) -> Result<Box<dyn Strategy>> {
+        let factory = self.factories.get(&config.kind).ok_or_eyre(format!(
+            "Unknown strategy type '{}' for strategy '{}'",
+            config.kind, config.name
+        ))?;
+
+        factory(config)
+            .map_err(|err| err.wrap_err(format!("Failed to create strategy '{}'", config.name)))
+    }
+}
@@ -0,0 +1,282 @@
+use super::{Context, Strategy, execute, on_data};
+use crate::{
+    Timestamp,
+    book::OrderBook,
+    client::OrderExecutor,
+    clock::Clock,
+    data::{DataEnum, StreamEvent},
+    order::OrderUpdate,
+};
+use bon::Builder;
+use bytestring::ByteString;
+use eyre::{Report, Result, WrapErr};
+use futures_util::{Stream, StreamExt};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    future::Future,
+    pin::pin,
+    time::Duration,
+};
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+/// 不同频道的推送到达顺序可能与行情时间不一致，每条行情最多等待该时长再交给策略
+pub const DEFAULT_REORDER_WINDOW: Duration = Duration::from_millis(100);
+
+/// 驱动单个策略的实时运行时
+///
+/// 行情按可见时间（[`DataEnum::available_at`]）排序后回调策略，与回测的回放顺序一致，
+/// 策略发出的指令按顺序交给执行器。
+/// 执行失败时回调 [`Strategy::on_order_error`]，不会中断运行。
+#[derive(Builder)]
+pub struct StrategyRuntime<E> {
+    /// 策略实例名，用于错误信息
+    #[builder(start_fn, into)]
+    name: ByteString,
+    #[builder(start_fn)]
+    strategy: Box<dyn Strategy>,
+    #[builder(start_fn)]
+    executor: E,
+    /// 行情的排序等待时间，为 0 时按到达顺序交付
+    #[builder(default = DEFAULT_REORDER_WINDOW)]
+    reorder_window: Duration,
+    /// 定时器回调时 [`Context::now`] 使用的时钟
+    #[builder(default)]
+    clock: Clock,
+    #[builder(skip)]
+    ctx: Context,
+    #[builder(skip)]
+    books: HashMap<ByteString, OrderBook>,
+}
+
+impl<E: OrderExecutor + Send> StrategyRuntime<E> {
+    pub fn name(&self) -> &ByteString {
+        &self.name
+    }
+
+    /// 运行策略直到行情流结束或 `shutdown` 完成，返回前回调 [`Strategy::on_stop`]
+    ///
+    /// `orders` 为订单更新流，不需要时可以传入 `futures_util::stream::empty()`。
+    /// 流中的错误交给 [`Strategy::on_error`]；策略回调返回错误时停止运行并返回该错误。
+    pub async fn run<M, O>(
+        mut self,
+        market: M,
+        orders: O,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()>
+    where
+        M: Stream<Item = Result<StreamEvent<Vec<DataEnum>>>>,
+        O: Stream<Item = Result<StreamEvent<Vec<OrderUpdate>>>>,
+    {
+        let mut market = pin!(market);
+        let mut orders = pin!(orders);
+        let mut shutdown = pin!(shutdown);
+        let mut orders_done = false;
+        let mut buffer = ReorderBuffer::new(self.reorder_window);
+        let mut timer = Timer::default();
+
+        self.call(self.clock.timestamp(), |strategy, ctx| {
+            strategy.on_start(ctx)
+        })
+        .await?;
+        timer.reset(self.ctx.timer());
+
+        loop {
+            let deadline = buffer.deadline();
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                _ = timer.tick() => {
+                    self.call(self.clock.timestamp(), |strategy, ctx| strategy.on_timer(ctx))
+                        .await?;
+                }
+                item = orders.next(), if !orders_done => match item {
+                    Some(Ok(StreamEvent::Data(updates))) => {
+                        for update in updates {
+                            self.call(update.timestamp, |strategy, ctx| {
+                                strategy.on_order_update(ctx, &update)
+                            })
+                            .await?;
+                        }
+                    }
+                    Some(Ok(StreamEvent::Disconnected | StreamEvent::Reconnected)) => {}
+                    Some(Err(err)) => self.on_error(err).await?,
+                    None => orders_done = true,
+                },
+                item = market.next() => match item {
+                    Some(Ok(StreamEvent::Data(batch))) => {
+                        let now = Instant::now();
+                        for data in batch {
+                            buffer.push(data, now);
+                        }
+                    }
+                    // 断线期间的增量缺失，等待重连后的快照重建订单簿
+                    Some(Ok(StreamEvent::Disconnected)) => self.books.clear(),
+                    Some(Ok(StreamEvent::Reconnected)) => {}
+                    Some(Err(err)) => self.on_error(err).await?,
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() => {}
+            }
+
+            while let Some(data) = buffer.pop(Instant::now()) {
+                self.dispatch(data).await?;
+            }
+            timer.reset(self.ctx.timer());
+        }
+
+        while let Some(data) = buffer.pop_any() {
+            self.dispatch(data).await?;
+        }
+        self.call(self.clock.timestamp(), |strategy, ctx| {
+            strategy.on_stop(ctx)
+        })
+        .await
+    }
+
+    async fn dispatch(&mut self, data: DataEnum) -> Result<()> {
+        self.ctx.set_now(data.available_at());
+        let (strategy, ctx, books) = (self.strategy.as_mut(), &mut self.ctx, &mut self.books);
+        on_data(strategy, ctx, books, &data).wrap_err_with(|| self.failed())?;
+
+        self.execute().await
+    }
+
+    async fn on_error(&mut self, err: Report) -> Result<()> {
+        self.call(self.clock.timestamp(), |strategy, ctx| {
+            strategy.on_error(ctx, &err)
+        })
+        .await
+    }
+
+    /// 回调策略并执行它发出的指令
+    async fn call<F>(&mut self, now: Timestamp, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut dyn Strategy, &mut Context) -> Result<()>,
+    {
+        self.ctx.set_now(now);
+        f(self.strategy.as_mut(), &mut self.ctx).wrap_err_with(|| self.failed())?;
+
+        self.execute().await
+    }
+
+    async fn execute(&mut self) -> Result<()> {
+        execute(self.strategy.as_mut(), &mut self.ctx, &mut self.executor)
+            .await
+            .wrap_err_with(|| self.failed())
+    }
+
+    fn failed(&self) -> String {
+        format!("Strategy '{}' failed", self.name)
+    }
+}
+
+/// 周期性触发 [`Strategy::on_timer`]，未设置时永不触发
+#[derive(Default)]
+struct Timer {
+    interval: Option<(Duration, Interval)>,
+}
+
+impl Timer {
+    fn reset(&mut self, period: Option<Duration>) {
+        if self.interval.as_ref().map(|(period, _)| *period) == period {
+            return;
+        }
+        self.interval = period.map(|period| {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            (period, interval)
+        });
+    }
+
+    async fn tick(&mut self) {
+        match &mut self.interval {
+            Some((_, interval)) => {
+                interval.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+struct Pending {
+    data: DataEnum,
+    /// 到达顺序，可见时间相同时保持到达顺序
+    seq: u64,
+    received: Instant,
+}
+
+impl Pending {
+    fn key(&self) -> (Timestamp, u64) {
+        (self.data.available_at(), self.seq)
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// 按可见时间排序的行情缓冲
+///
+/// 可见时间最小的行情等待 `window` 后交付。晚于 `window` 到达的行情仍会交付，
+/// 但可能早于已交付行情的可见时间。
+struct ReorderBuffer {
+    window: Duration,
+    heap: BinaryHeap<Reverse<Pending>>,
+    seq: u64,
+}
+
+impl ReorderBuffer {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            heap: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    fn push(&mut self, data: DataEnum, received: Instant) {
+        self.seq += 1;
+        self.heap.push(Reverse(Pending {
+            data,
+            seq: self.seq,
+            received,
+        }));
+    }
+
+    /// 下一条行情可以交付的时间
+    fn deadline(&self) -> Option<Instant> {
+        self.heap
+            .peek()
+            .map(|Reverse(pending)| pending.received + self.window)
+    }
+
+    fn pop(&mut self, now: Instant) -> Option<DataEnum> {
+        if self.deadline()? > now {
+            return None;
+        }
+
+        self.pop_any()
+    }
+
+    fn pop_any(&mut self) -> Option<DataEnum> {
+        self.heap.pop().map(|Reverse(pending)| pending.data)
+    }
+}